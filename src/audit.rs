@@ -0,0 +1,215 @@
+use crate::distinguished_name;
+use async_std::io::{self, Write, WriteExt};
+use async_std::net::{IpAddr, SocketAddr};
+use async_std::path::Path;
+use async_std::sync::Mutex as AsyncMutex;
+use openssl::ssl::SslRef;
+use tide::http::Request;
+use tide::prelude::json;
+
+use std::fmt::{self, Debug, Formatter};
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Receives one [`AuditEntry`] per connection once it has closed, see
+/// [`TlsListenerBuilder::connection_audit_log`](crate::TlsListenerBuilder::connection_audit_log).
+///
+/// Entries are written from the task that served the connection, so a
+/// slow writer does not hold up other connections, but it does keep
+/// that task alive until the write completes.
+#[tide::utils::async_trait]
+pub trait ConnectionAuditWriter {
+    /// Persists a single entry.
+    async fn write(&self, entry: AuditEntry) -> io::Result<()>;
+}
+
+/// The audit record of a single connection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct AuditEntry {
+    /// When the connection was accepted
+    pub timestamp: SystemTime,
+    /// The client address, if it could be determined
+    pub peer_ip: Option<IpAddr>,
+    /// The local address the client connected to, if it could be
+    /// determined
+    pub server_ip: Option<IpAddr>,
+    /// The negotiated protocol version, such as `TLSv1.3`. This is
+    /// `None` if the handshake did not complete.
+    pub tls_version: Option<String>,
+    /// The negotiated cipher suite, in OpenSSL's naming
+    pub cipher: Option<String>,
+    /// The subject of the client certificate, if one was presented,
+    /// as in `/C=US/O=Acme Corp/CN=client01.acme.com`
+    pub peer_cert_dn: Option<String>,
+    /// Every request served on the connection, in order of arrival
+    pub requests: Vec<AuditRequest>,
+    /// When the connection was closed
+    pub closed_at: SystemTime,
+    /// Bytes read from the socket, including TLS framing
+    pub bytes_received: u64,
+    /// Bytes written to the socket, including TLS framing
+    pub bytes_sent: u64,
+}
+
+/// A request within an [`AuditEntry`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct AuditRequest {
+    /// When the request head had been read
+    pub received_at: SystemTime,
+    /// The request method
+    pub method: String,
+    /// The request path, without the query string
+    pub path: String,
+    /// The response status, or `None` if the handler failed without
+    /// producing a response
+    pub status: Option<u16>,
+}
+
+impl AuditRequest {
+    pub(crate) fn new(req: &Request) -> Self {
+        Self {
+            received_at: SystemTime::now(),
+            method: req.method().to_string(),
+            path: req.url().path().to_string(),
+            status: None,
+        }
+    }
+}
+
+/// Collects the audit entry of a connection while it is being served.
+#[derive(Debug)]
+pub(crate) struct ConnectionAudit(Mutex<AuditEntry>);
+
+impl ConnectionAudit {
+    pub(crate) fn new(local_addr: Option<SocketAddr>, peer_addr: Option<SocketAddr>) -> Self {
+        let now = SystemTime::now();
+        Self(Mutex::new(AuditEntry {
+            timestamp: now,
+            peer_ip: peer_addr.map(|addr| addr.ip()),
+            server_ip: local_addr.map(|addr| addr.ip()),
+            tls_version: None,
+            cipher: None,
+            peer_cert_dn: None,
+            requests: Vec::new(),
+            closed_at: now,
+            bytes_received: 0,
+            bytes_sent: 0,
+        }))
+    }
+
+    fn entry(&self) -> std::sync::MutexGuard<'_, AuditEntry> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Records the outcome of the handshake.
+    pub(crate) fn handshake(&self, ssl: &SslRef) {
+        let mut entry = self.entry();
+        entry.tls_version = Some(ssl.version_str().to_string());
+        entry.cipher = ssl.current_cipher().map(|cipher| cipher.name().to_string());
+        entry.peer_cert_dn = ssl
+            .peer_certificate()
+            .map(|cert| distinguished_name(cert.subject_name()));
+    }
+
+    pub(crate) fn request(&self, request: AuditRequest) {
+        self.entry().requests.push(request);
+    }
+
+    /// Completes the entry as the connection closes.
+    pub(crate) fn close(&self, bytes_received: u64, bytes_sent: u64) -> AuditEntry {
+        let mut entry = self.entry();
+        entry.closed_at = SystemTime::now();
+        entry.bytes_received = bytes_received;
+        entry.bytes_sent = bytes_sent;
+        entry.clone()
+    }
+}
+
+/// Writes each [`AuditEntry`] as a single line of JSON.
+///
+/// Times are written as seconds since the unix epoch, with millisecond
+/// precision.
+///
+/// ```rust
+/// # use std::sync::Arc;
+/// # use tide_openssl::{JsonLineAuditWriter, TlsListener};
+/// let listener = TlsListener::<()>::build()
+///     .addrs("localhost:4433")
+///     .cert("./tls/localhost-4433.cert")
+///     .key("./tls/localhost-4433.key")
+///     .connection_audit_log(Arc::new(JsonLineAuditWriter::new(async_std::io::stderr())))
+///     .finish();
+/// ```
+pub struct JsonLineAuditWriter {
+    writer: AsyncMutex<Pin<Box<dyn Write + Send>>>,
+}
+
+impl JsonLineAuditWriter {
+    /// Writes entries to `writer`, flushing after each line.
+    pub fn new(writer: impl Write + Send + 'static) -> Self {
+        Self {
+            writer: AsyncMutex::new(Box::pin(writer)),
+        }
+    }
+
+    /// Appends entries to the file at `path`, creating it if necessary.
+    pub async fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = async_std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await?;
+        Ok(Self::new(file))
+    }
+}
+
+impl Debug for JsonLineAuditWriter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JsonLineAuditWriter").finish()
+    }
+}
+
+pub(crate) fn unix_seconds(time: SystemTime) -> f64 {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    since_epoch.as_millis() as f64 / 1000.0
+}
+
+#[tide::utils::async_trait]
+impl ConnectionAuditWriter for JsonLineAuditWriter {
+    async fn write(&self, entry: AuditEntry) -> io::Result<()> {
+        let requests: Vec<_> = entry
+            .requests
+            .iter()
+            .map(|request| {
+                json!({
+                    "received_at": unix_seconds(request.received_at),
+                    "method": request.method,
+                    "path": request.path,
+                    "status": request.status,
+                })
+            })
+            .collect();
+
+        let mut line = json!({
+            "timestamp": unix_seconds(entry.timestamp),
+            "peer_ip": entry.peer_ip.map(|ip| ip.to_string()),
+            "server_ip": entry.server_ip.map(|ip| ip.to_string()),
+            "tls_version": entry.tls_version,
+            "cipher": entry.cipher,
+            "peer_cert_dn": entry.peer_cert_dn,
+            "requests": requests,
+            "closed_at": unix_seconds(entry.closed_at),
+            "bytes_received": entry.bytes_received,
+            "bytes_sent": entry.bytes_sent,
+        })
+        .to_string();
+        line.push('\n');
+
+        let mut writer = self.writer.lock().await;
+        writer.write_all(line.as_bytes()).await?;
+        writer.flush().await
+    }
+}
@@ -0,0 +1,293 @@
+#[cfg(feature = "remote-config")]
+use crate::AppliedDocument;
+use crate::{logging, AcceptorSource, TlsAcceptorOptions, TlsListenerConfig};
+use async_std::{io, task};
+use openssl::ssl::SslAcceptor;
+
+use std::fmt::{self, Debug, Formatter};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock, Weak};
+use std::time::Duration;
+
+/// How often a listener asks its [`CertProvider`] for the latest
+/// certificate, unless the provider says otherwise.
+const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A PEM encoded certificate chain, leaf first, and private key.
+pub type CertAndKey = (Vec<u8>, Vec<u8>);
+
+/// A source of the certificate and key a listener serves, for
+/// certificates that are renewed in a backing store, such as by
+/// cert-manager or an ACME client, see
+/// [`TlsListenerBuilder::cert_provider`](crate::TlsListenerBuilder::cert_provider).
+///
+/// The trait is object safe, so `fetch` returns a boxed future rather
+/// than being an `async fn`. The future's type is the same as
+/// `futures::future::BoxFuture<'_, io::Result<CertAndKey>>`.
+///
+/// ```rust
+/// use std::future::Future;
+/// use std::pin::Pin;
+/// use tide_openssl::{CertAndKey, CertProvider};
+///
+/// #[derive(Debug)]
+/// struct Files;
+///
+/// impl CertProvider for Files {
+///     fn fetch(&self) -> Pin<Box<dyn Future<Output = std::io::Result<CertAndKey>> + Send + '_>> {
+///         Box::pin(async {
+///             let cert = async_std::fs::read("./tls/localhost-4433.cert").await?;
+///             let key = async_std::fs::read("./tls/localhost-4433.key").await?;
+///             Ok((cert, key))
+///         })
+///     }
+/// }
+/// ```
+pub trait CertProvider {
+    /// Returns the current certificate chain and private key, PEM
+    /// encoded.
+    fn fetch(&self) -> Pin<Box<dyn Future<Output = io::Result<CertAndKey>> + Send + '_>>;
+
+    /// How often the listener calls [`CertProvider::fetch`] to pick up
+    /// a renewed certificate. Defaults to a minute.
+    fn refresh_interval(&self) -> Duration {
+        DEFAULT_REFRESH_INTERVAL
+    }
+}
+
+/// See [`TlsListenerBuilder::cert_provider`](crate::TlsListenerBuilder::cert_provider).
+pub(crate) type SharedCertProvider = Arc<dyn CertProvider + Send + Sync + 'static>;
+
+/// A listener's [`CertProvider`], with the certificate and key last
+/// fetched from it to build an acceptor, so that the refresh only
+/// rebuilds when they change. Clones share the last fetched pair.
+#[derive(Clone)]
+pub(crate) struct ProviderSource {
+    provider: SharedCertProvider,
+    last_fetched: Arc<Mutex<Option<CertAndKey>>>,
+}
+
+/// Wraps a [`CertProvider`] that is slow or expensive to call, such as
+/// a remote secrets store, answering from a cache that is refreshed in
+/// the background every `ttl`.
+///
+/// The first call to [`CertProvider::fetch`] waits for the wrapped
+/// provider and starts the background refresh, later calls return the
+/// cached certificate straight away. A failed refresh is logged and
+/// the cached certificate kept, so a store that is briefly unavailable
+/// does not take the listener down. The refresh stops once the
+/// provider is dropped.
+///
+/// ```rust
+/// # use std::future::Future;
+/// # use std::pin::Pin;
+/// # use tide_openssl::{CertAndKey, CertProvider};
+/// # #[derive(Debug)]
+/// # struct Vault;
+/// # impl CertProvider for Vault {
+/// #     fn fetch(&self) -> Pin<Box<dyn Future<Output = std::io::Result<CertAndKey>> + Send + '_>> {
+/// #         Box::pin(async { Ok((Vec::new(), Vec::new())) })
+/// #     }
+/// # }
+/// use std::time::Duration;
+/// use tide_openssl::{CachingCertProvider, TlsListener};
+///
+/// let listener = TlsListener::<()>::build()
+///     .addrs("localhost:4433")
+///     .cert_provider(CachingCertProvider::new(Vault, Duration::from_secs(300)))
+///     .finish();
+/// ```
+pub struct CachingCertProvider {
+    cache: Arc<Cache>,
+    ttl: Duration,
+}
+
+struct Cache {
+    provider: SharedCertProvider,
+    cached: RwLock<Option<CertAndKey>>,
+    refreshing: AtomicBool,
+}
+
+impl Debug for CachingCertProvider {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let cached = self.cache.cached.read().unwrap_or_else(|e| e.into_inner());
+        f.debug_struct("CachingCertProvider")
+            .field("ttl", &self.ttl)
+            .field("cached", &cached.as_ref().map(|_| "<redacted>"))
+            .finish()
+    }
+}
+
+impl CachingCertProvider {
+    /// Caches what `provider` returns for `ttl`.
+    pub fn new(provider: impl CertProvider + Send + Sync + 'static, ttl: Duration) -> Self {
+        Self {
+            cache: Arc::new(Cache {
+                provider: Arc::new(provider),
+                cached: RwLock::new(None),
+                refreshing: AtomicBool::new(false),
+            }),
+            ttl,
+        }
+    }
+
+    /// Fetches from the wrapped provider every `ttl` while the cache
+    /// is alive.
+    fn spawn_refresh(&self) {
+        if self.cache.refreshing.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let cache: Weak<Cache> = Arc::downgrade(&self.cache);
+        let ttl = self.ttl;
+        task::spawn(async move {
+            loop {
+                task::sleep(ttl).await;
+                let cache = match cache.upgrade() {
+                    Some(cache) => cache,
+                    None => break,
+                };
+                match cache.provider.fetch().await {
+                    Ok(fetched) => {
+                        *cache.cached.write().unwrap_or_else(|e| e.into_inner()) = Some(fetched);
+                    }
+                    Err(error) => {
+                        logging::error!("unable to refresh cached certificate", {
+                            error: error.to_string()
+                        });
+                    }
+                }
+            }
+        });
+    }
+}
+
+impl CertProvider for CachingCertProvider {
+    fn fetch(&self) -> Pin<Box<dyn Future<Output = io::Result<CertAndKey>> + Send + '_>> {
+        Box::pin(async move {
+            let cached = self
+                .cache
+                .cached
+                .read()
+                .unwrap_or_else(|e| e.into_inner())
+                .clone();
+            if let Some(cached) = cached {
+                return Ok(cached);
+            }
+
+            let fetched = self.cache.provider.fetch().await?;
+            *self.cache.cached.write().unwrap_or_else(|e| e.into_inner()) = Some(fetched.clone());
+            self.spawn_refresh();
+            Ok(fetched)
+        })
+    }
+
+    /// The listener checks the cache as often as it is refreshed.
+    fn refresh_interval(&self) -> Duration {
+        self.ttl
+    }
+}
+
+impl ProviderSource {
+    pub(crate) fn new(provider: SharedCertProvider) -> Self {
+        Self {
+            provider,
+            last_fetched: Arc::default(),
+        }
+    }
+
+    /// Fetches the certificate and key and builds an acceptor from
+    /// them with the listener's options.
+    pub(crate) async fn build_acceptor(
+        &self,
+        options: &TlsAcceptorOptions,
+    ) -> io::Result<SslAcceptor> {
+        let fetched = self.provider.fetch().await?;
+        self.set_last_fetched(fetched.clone());
+        let (cert, key) = fetched;
+        pem_config(cert, key)?.build_acceptor(options)
+    }
+
+    /// Records `fetched` as the last fetched pair, returning whether it
+    /// differs from the one before.
+    fn set_last_fetched(&self, fetched: CertAndKey) -> bool {
+        let mut last_fetched = self.last_fetched.lock().unwrap_or_else(|e| e.into_inner());
+        let changed = last_fetched.as_ref() != Some(&fetched);
+        *last_fetched = Some(fetched);
+        changed
+    }
+}
+
+fn pem_config(cert: Vec<u8>, key: Vec<u8>) -> io::Result<TlsListenerConfig> {
+    Ok(TlsListenerConfig::Pem {
+        cert: String::from_utf8(cert).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+        key: String::from_utf8(key).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+    })
+}
+
+/// Fetches from `provider` every refresh interval, and rebuilds the
+/// acceptor, with any applied remote configuration, whenever the
+/// certificate chain or key differs from the last fetched, starting
+/// with the pair the acceptor was first built from. The task ends once
+/// the listener, and with it the acceptor, is dropped.
+pub(crate) fn spawn_refresh(
+    source: ProviderSource,
+    acceptor: Weak<RwLock<SslAcceptor>>,
+    acceptor_options: Arc<TlsAcceptorOptions>,
+    #[cfg(feature = "remote-config")] applied_document: AppliedDocument,
+) {
+    let interval = source.provider.refresh_interval();
+    task::spawn(async move {
+        loop {
+            task::sleep(interval).await;
+            let acceptor = match acceptor.upgrade() {
+                Some(acceptor) => acceptor,
+                None => break,
+            };
+
+            let fetched = match source.provider.fetch().await {
+                Ok(fetched) => fetched,
+                Err(error) => {
+                    logging::error!("unable to fetch certificate from provider", {
+                        error: error.to_string()
+                    });
+                    continue;
+                }
+            };
+            if !source.set_last_fetched(fetched.clone()) {
+                continue;
+            }
+
+            let (cert, key) = fetched;
+            let config = match pem_config(cert, key) {
+                Ok(config) => AcceptorSource::Config(config),
+                Err(error) => {
+                    logging::error!("unable to rebuild tls acceptor from provider", {
+                        error: error.to_string()
+                    });
+                    continue;
+                }
+            };
+            #[cfg(feature = "remote-config")]
+            let rebuilt = applied_document
+                .rebuild(&acceptor, &config, &acceptor_options)
+                .await;
+            #[cfg(not(feature = "remote-config"))]
+            let rebuilt = config
+                .build_acceptor(&acceptor_options)
+                .await
+                .map(|rebuilt| *acceptor.write().unwrap_or_else(|e| e.into_inner()) = rebuilt);
+            match rebuilt {
+                Ok(()) => {
+                    logging::info!("tls acceptor rebuilt with certificate from provider");
+                }
+                Err(error) => {
+                    logging::error!("unable to rebuild tls acceptor from provider", {
+                        error: error.to_string()
+                    });
+                }
+            }
+        }
+    });
+}
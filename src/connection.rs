@@ -0,0 +1,71 @@
+use async_std::net::{SocketAddr, TcpListener};
+#[cfg(feature = "unix")]
+use async_std::os::unix::net::UnixListener;
+use std::fmt::{self, Debug, Display, Formatter};
+#[cfg(feature = "unix")]
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// What the listener accepts connections on, before and after it is
+/// bound.
+#[derive(Debug)]
+pub(crate) enum Connection {
+    Addrs(Vec<SocketAddr>),
+    /// Shared with the accept tasks, see
+    /// [`TlsListenerBuilder::accept_threads`](crate::TlsListenerBuilder::accept_threads).
+    Tcp(Arc<TcpListener>),
+    #[cfg(feature = "unix")]
+    UnixPath(PathBuf),
+    #[cfg(feature = "unix")]
+    Unix(Arc<UnixListener>),
+}
+
+impl Connection {
+    /// The transport reported in tide's `ListenInfo`.
+    pub(crate) fn transport(&self) -> &'static str {
+        match self {
+            Self::Addrs(_) | Self::Tcp(_) => "tcp",
+            #[cfg(feature = "unix")]
+            Self::UnixPath(_) | Self::Unix(_) => "unix",
+        }
+    }
+}
+
+impl Display for Connection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Addrs(addrs) => write!(
+                f,
+                "{}",
+                addrs
+                    .iter()
+                    .map(|a| format!("https://{}", a))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+
+            Self::Tcp(tcp) => write!(
+                f,
+                "https://{}",
+                tcp.local_addr()
+                    .ok()
+                    .map(|a| a.to_string())
+                    .as_deref()
+                    .unwrap_or("[unknown]")
+            ),
+
+            #[cfg(feature = "unix")]
+            Self::UnixPath(path) => write!(f, "{}", path.display()),
+
+            #[cfg(feature = "unix")]
+            Self::Unix(unix) => match unix
+                .local_addr()
+                .ok()
+                .and_then(|addr| addr.as_pathname().map(|path| path.display().to_string()))
+            {
+                Some(path) => write!(f, "{}", path),
+                None => write!(f, "[unknown]"),
+            },
+        }
+    }
+}
@@ -0,0 +1,358 @@
+use crate::{distinguished_name, logging, opaque};
+use crate::{
+    AuditRequest, ConnectionAudit, ConnectionAuditWriter, CookieSecurityOptions, ErrorLogFormat,
+    HealthProbes, RequestSigning, RequestTags, TlsSessionSummary, TlsStats,
+};
+use async_std::future;
+use async_std::net::{IpAddr, SocketAddr};
+use openssl::ssl::SslRef;
+use tide::http::{headers, Request, Response, StatusCode};
+use tide::Server;
+
+use std::fmt::{self, Debug, Formatter};
+use std::num::NonZeroUsize;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+pub(crate) type RequestTagger = Arc<dyn Fn(&Request) -> Vec<(String, String)> + Send + Sync>;
+pub(crate) type IpFilter = Arc<dyn Fn(IpAddr) -> bool + Send + Sync>;
+pub(crate) type SessionReuseCallback = Arc<dyn Fn(SocketAddr, bool) + Send + Sync>;
+pub(crate) type DnFilter = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+pub(crate) type AuditWriter = Arc<dyn ConnectionAuditWriter + Send + Sync>;
+
+/// Settings applied to each accepted connection, in the accept loop
+/// and in `handle_tls`.
+pub(crate) struct ConnectionOptions {
+    pub(crate) accept_tasks: NonZeroUsize,
+    pub(crate) ip_filter: IpFilter,
+    pub(crate) peer_dn_filter: Option<DnFilter>,
+    pub(crate) assert_no_compression: bool,
+    pub(crate) record_client_hello: bool,
+    #[cfg(feature = "debug")]
+    pub(crate) handshake_dump_dir: Option<std::path::PathBuf>,
+    pub(crate) request_tagger: Option<RequestTagger>,
+    pub(crate) session_reuse_callback: Option<SessionReuseCallback>,
+    pub(crate) slow_handshake_threshold: Option<Duration>,
+    pub(crate) error_log_format: ErrorLogFormat,
+    pub(crate) handler_timeout: Option<Duration>,
+    pub(crate) strict_http_parsing: bool,
+    pub(crate) max_requests_per_connection: Option<usize>,
+    pub(crate) cookie_security: Option<CookieSecurityOptions>,
+    pub(crate) protocol_buffer: Option<usize>,
+    #[cfg(feature = "h2")]
+    pub(crate) http2_max_request_body_size: usize,
+    pub(crate) disabled_http_methods: Vec<String>,
+    pub(crate) request_signing: RequestSigning,
+    pub(crate) audit_writer: Option<AuditWriter>,
+    pub(crate) last_session: Arc<RwLock<Option<TlsSessionSummary>>>,
+    pub(crate) stats_interval: Option<Duration>,
+    pub(crate) stats: Arc<TlsStats>,
+    pub(crate) health_probes: Arc<HealthProbes>,
+}
+
+impl Default for ConnectionOptions {
+    fn default() -> Self {
+        Self {
+            accept_tasks: NonZeroUsize::MIN,
+            ip_filter: Arc::new(|_| true),
+            peer_dn_filter: None,
+            assert_no_compression: false,
+            record_client_hello: false,
+            #[cfg(feature = "debug")]
+            handshake_dump_dir: None,
+            request_tagger: None,
+            session_reuse_callback: None,
+            slow_handshake_threshold: None,
+            error_log_format: ErrorLogFormat::default(),
+            handler_timeout: None,
+            strict_http_parsing: false,
+            max_requests_per_connection: None,
+            cookie_security: None,
+            protocol_buffer: None,
+            #[cfg(feature = "h2")]
+            http2_max_request_body_size: crate::http2::DEFAULT_MAX_REQUEST_BODY_SIZE,
+            disabled_http_methods: Vec::new(),
+            request_signing: RequestSigning::default(),
+            audit_writer: None,
+            last_session: Arc::default(),
+            stats_interval: None,
+            stats: Arc::default(),
+            health_probes: Arc::default(),
+        }
+    }
+}
+
+impl Clone for ConnectionOptions {
+    fn clone(&self) -> Self {
+        Self {
+            accept_tasks: self.accept_tasks,
+            ip_filter: self.ip_filter.clone(),
+            peer_dn_filter: self.peer_dn_filter.clone(),
+            assert_no_compression: self.assert_no_compression,
+            record_client_hello: self.record_client_hello,
+            #[cfg(feature = "debug")]
+            handshake_dump_dir: self.handshake_dump_dir.clone(),
+            request_tagger: self.request_tagger.clone(),
+            session_reuse_callback: self.session_reuse_callback.clone(),
+            slow_handshake_threshold: self.slow_handshake_threshold,
+            error_log_format: self.error_log_format,
+            handler_timeout: self.handler_timeout,
+            strict_http_parsing: self.strict_http_parsing,
+            max_requests_per_connection: self.max_requests_per_connection,
+            cookie_security: self.cookie_security,
+            protocol_buffer: self.protocol_buffer,
+            #[cfg(feature = "h2")]
+            http2_max_request_body_size: self.http2_max_request_body_size,
+            disabled_http_methods: self.disabled_http_methods.clone(),
+            request_signing: self.request_signing.clone(),
+            audit_writer: self.audit_writer.clone(),
+            // each listener tracks its own handshakes
+            last_session: Arc::default(),
+            stats_interval: self.stats_interval,
+            stats: Arc::default(),
+            health_probes: Arc::default(),
+        }
+    }
+}
+
+impl ConnectionOptions {
+    /// The options for a connection from the listener's own health
+    /// check, which is left out of the stats, the audit log and the
+    /// slow handshake log.
+    pub(crate) fn for_health_probe(&self) -> Self {
+        Self {
+            slow_handshake_threshold: None,
+            session_reuse_callback: None,
+            audit_writer: None,
+            ..self.clone()
+        }
+    }
+
+    /// Whether the peer certificate of a completed handshake passes the
+    /// distinguished name filter, if one is set. Without a peer
+    /// certificate there is nothing to match, so the filter fails.
+    pub(crate) fn allows_peer(&self, ssl: &SslRef) -> bool {
+        let filter = match &self.peer_dn_filter {
+            Some(filter) => filter,
+            None => return true,
+        };
+        let dn = match ssl.peer_certificate() {
+            Some(cert) => distinguished_name(cert.subject_name()),
+            None => {
+                logging::warning!("no peer certificate to match, closing connection");
+                return false;
+            }
+        };
+        if !filter(&dn) {
+            logging::warning!("peer certificate dn not allowed, closing connection", { dn: dn });
+            return false;
+        }
+        true
+    }
+
+    /// Warns about a completed handshake that took longer than the
+    /// slow handshake threshold.
+    pub(crate) fn log_slow_handshake(
+        &self,
+        ssl: &SslRef,
+        peer_addr: Option<SocketAddr>,
+        duration: Duration,
+    ) {
+        match self.slow_handshake_threshold {
+            Some(threshold) if duration > threshold => {}
+            _ => return,
+        }
+        let summary = TlsSessionSummary::new(ssl);
+        let peer_ip = peer_addr
+            .map(|addr| addr.ip().to_string())
+            .unwrap_or_else(|| String::from("unknown"));
+        logging::warning!("slow tls handshake", {
+            peer_ip: peer_ip,
+            cipher: summary.cipher,
+            tls_version: summary.tls_version,
+            duration: format!("{:?}", duration),
+        });
+    }
+
+    /// Fills in what tide cannot know about a freshly parsed request:
+    /// the scheme, the socket addresses and any request tags.
+    pub(crate) fn prepare(
+        &self,
+        req: &mut Request,
+        local_addr: Option<SocketAddr>,
+        peer_addr: Option<SocketAddr>,
+    ) {
+        if req.url_mut().set_scheme("https").is_err() {
+            logging::error!("unable to set https scheme on url", { url: req.url().to_string() });
+        }
+
+        req.set_local_addr(local_addr);
+        req.set_peer_addr(peer_addr);
+
+        if let Some(tagger) = &self.request_tagger {
+            let tags = tagger(req);
+            req.ext_mut().insert(RequestTags(tags));
+        }
+    }
+
+    /// With strict parsing enabled, answers HTTP/1.1 requests whose
+    /// framing or target is ambiguous with a 400 and closes the
+    /// connection, so the request never reaches tide.
+    ///
+    /// async-h1 has no strict mode of its own, and by the time this
+    /// runs it has already picked one interpretation of the head: it
+    /// uses the last of several `Content-Length` values and treats any
+    /// `Transfer-Encoding` other than `chunked` as an empty body. A
+    /// proxy in front of the server may have chosen differently.
+    pub(crate) fn reject_ambiguous(
+        &self,
+        req: &Request,
+        audit: Option<&ConnectionAudit>,
+    ) -> Option<Response> {
+        if !self.strict_http_parsing {
+            return None;
+        }
+
+        let reason = strict_http_violation(req)?;
+        logging::warning!("rejecting ambiguous request", { reason: reason });
+        let mut res = Response::new(StatusCode::BadRequest);
+        res.insert_header(headers::CONNECTION, "close");
+        if let Some(audit) = audit {
+            let mut request = AuditRequest::new(req);
+            request.status = Some(res.status() as u16);
+            audit.request(request);
+        }
+        Some(res)
+    }
+
+    /// Closes the HTTP/1.1 connection after the response to the
+    /// `served`-th request on it, once the request limit is reached.
+    pub(crate) fn limit_requests(&self, served: usize, res: &mut Response) {
+        if let Some(max) = self.max_requests_per_connection {
+            if served >= max {
+                res.insert_header(headers::CONNECTION, "close");
+            }
+        }
+    }
+
+    /// Runs the request through tide, within the handler timeout if
+    /// one is set, hardens the cookies it sets, and records it in the
+    /// connection's audit entry. Requests with a disabled method are
+    /// answered with a 405 instead, requests without a valid signature
+    /// with a 401, and signed requests with a body over the limit with
+    /// a 413.
+    pub(crate) async fn respond<State: Clone + Send + Sync + 'static>(
+        &self,
+        app: &Server<State>,
+        mut req: Request,
+        audit: Option<&ConnectionAudit>,
+    ) -> tide::http::Result<Response> {
+        let request = audit.map(|_| AuditRequest::new(&req));
+        #[cfg(feature = "metrics")]
+        let timing = crate::metrics::RequestTiming::start(&req);
+        let mut res = if self.disables_method(&req) {
+            Ok(Response::new(StatusCode::MethodNotAllowed))
+        } else if let Some(status) = self.request_signing.rejection(&mut req).await? {
+            Ok(Response::new(status))
+        } else {
+            self.respond_in_time(app, req).await
+        };
+        #[cfg(feature = "metrics")]
+        timing.finish();
+        if let (Ok(res), Some(cookie_security)) = (&mut res, &self.cookie_security) {
+            cookie_security.harden(res);
+        }
+        if let (Some(audit), Some(mut request)) = (audit, request) {
+            request.status = res.as_ref().ok().map(|res| res.status() as u16);
+            audit.request(request);
+        }
+        res
+    }
+
+    fn disables_method(&self, req: &Request) -> bool {
+        self.disabled_http_methods
+            .contains(&req.method().to_string())
+    }
+
+    async fn respond_in_time<State: Clone + Send + Sync + 'static>(
+        &self,
+        app: &Server<State>,
+        req: Request,
+    ) -> tide::http::Result<Response> {
+        let timeout = match self.handler_timeout {
+            Some(timeout) => timeout,
+            None => return app.respond(req).await,
+        };
+
+        match future::timeout(timeout, app.respond(req)).await {
+            Ok(res) => res,
+            Err(_) => {
+                logging::warning!("handler timed out", { timeout: format!("{:?}", timeout) });
+                let retry_after = timeout.as_secs() + u64::from(timeout.subsec_nanos() > 0);
+                let mut res = Response::new(StatusCode::ServiceUnavailable);
+                res.insert_header(headers::RETRY_AFTER, retry_after.max(1).to_string());
+                res.insert_header(headers::CONNECTION, "close");
+                Ok(res)
+            }
+        }
+    }
+}
+
+impl Debug for ConnectionOptions {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("ConnectionOptions");
+        debug
+            .field("accept_tasks", &self.accept_tasks)
+            .field("ip_filter", &"Fn(IpAddr) -> bool")
+            .field("peer_dn_filter", &opaque(&self.peer_dn_filter))
+            .field("assert_no_compression", &self.assert_no_compression)
+            .field("record_client_hello", &self.record_client_hello)
+            .field("slow_handshake_threshold", &self.slow_handshake_threshold)
+            .field("error_log_format", &self.error_log_format)
+            .field("handler_timeout", &self.handler_timeout)
+            .field("strict_http_parsing", &self.strict_http_parsing)
+            .field(
+                "max_requests_per_connection",
+                &self.max_requests_per_connection,
+            )
+            .field("cookie_security", &self.cookie_security)
+            .field("protocol_buffer", &self.protocol_buffer)
+            .field("disabled_http_methods", &self.disabled_http_methods)
+            .field("request_signing", &self.request_signing)
+            .field("stats_interval", &self.stats_interval)
+            .field("audit_writer", &opaque(&self.audit_writer))
+            .field("request_tagger", &opaque(&self.request_tagger))
+            .field(
+                "session_reuse_callback",
+                &opaque(&self.session_reuse_callback),
+            );
+        #[cfg(feature = "h2")]
+        debug.field(
+            "http2_max_request_body_size",
+            &self.http2_max_request_body_size,
+        );
+        #[cfg(feature = "debug")]
+        debug.field("handshake_dump_dir", &self.handshake_dump_dir);
+        debug.finish()
+    }
+}
+
+fn strict_http_violation(req: &Request) -> Option<&'static str> {
+    let count = |name| req.header(name).map_or(0, |values| values.iter().count());
+
+    if count(headers::CONTENT_LENGTH) > 1 {
+        return Some("duplicate content-length");
+    }
+    match count(headers::HOST) {
+        0 => return Some("missing host"),
+        1 => {}
+        _ => return Some("duplicate host"),
+    }
+    match req.header(headers::TRANSFER_ENCODING) {
+        Some(values) if values.iter().count() > 1 => Some("duplicate transfer-encoding"),
+        Some(value) if !value.as_str().trim().eq_ignore_ascii_case("chunked") => {
+            Some("unsupported transfer-encoding")
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,77 @@
+use tide::http::cookies::SameSite;
+use tide::http::{headers, Response};
+
+/// The attributes added to every `Set-Cookie` header, see
+/// [`TlsListenerBuilder::cookie_security`](crate::TlsListenerBuilder::cookie_security).
+///
+/// The default adds all of `Secure`, `HttpOnly` and `SameSite=Strict`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CookieSecurityOptions {
+    /// Adds `Secure`, so the cookie is never sent over plain http.
+    pub secure: bool,
+    /// Adds `HttpOnly`, hiding the cookie from scripts.
+    pub http_only: bool,
+    /// Adds `SameSite` with this value, unless the cookie already
+    /// has a `SameSite` attribute.
+    pub same_site: Option<SameSite>,
+}
+
+impl Default for CookieSecurityOptions {
+    fn default() -> Self {
+        Self {
+            secure: true,
+            http_only: true,
+            same_site: Some(SameSite::Strict),
+        }
+    }
+}
+
+impl CookieSecurityOptions {
+    /// Adds the missing attributes to each `Set-Cookie` header of the
+    /// response. Cookies that are already `Secure` were set with care
+    /// and are left unchanged.
+    pub(crate) fn harden(&self, res: &mut Response) {
+        let cookies: Vec<String> = match res.header(headers::SET_COOKIE) {
+            Some(values) => values
+                .iter()
+                .map(|value| self.harden_cookie(value.as_str()))
+                .collect(),
+            None => return,
+        };
+
+        res.remove_header(headers::SET_COOKIE);
+        for cookie in cookies {
+            res.append_header(headers::SET_COOKIE, cookie);
+        }
+    }
+
+    fn harden_cookie(&self, cookie: &str) -> String {
+        let attributes: Vec<String> = cookie
+            .split(';')
+            .skip(1)
+            .map(|attribute| {
+                let name = attribute.split('=').next().unwrap_or_default();
+                name.trim().to_ascii_lowercase()
+            })
+            .collect();
+        let has = |name: &str| attributes.iter().any(|attribute| attribute == name);
+
+        let mut cookie = cookie.to_string();
+        if has("secure") {
+            return cookie;
+        }
+
+        if self.secure {
+            cookie.push_str("; Secure");
+        }
+        if self.http_only && !has("httponly") {
+            cookie.push_str("; HttpOnly");
+        }
+        if let Some(same_site) = self.same_site {
+            if !has("samesite") {
+                cookie.push_str(&format!("; SameSite={}", same_site));
+            }
+        }
+        cookie
+    }
+}
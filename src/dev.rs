@@ -0,0 +1,80 @@
+//! Utilities for running a [`TlsListener`](crate::TlsListener) in
+//! development, without a certificate issued out-of-band.
+//!
+//! These are enabled by the `dev` cargo feature, which should not be
+//! turned on in release builds.
+//!
+//! ```rust
+//! # use tide_openssl::{dev, TlsListener};
+//! # fn main() -> std::io::Result<()> {
+//! let (cert, key) = dev::generate_self_signed("localhost")?;
+//! let listener = TlsListener::<()>::build()
+//!     .addrs("localhost:4433")
+//!     .cert_pem(String::from_utf8(cert).unwrap())
+//!     .key_pem(String::from_utf8(key).unwrap())
+//!     .finish()?;
+//! # Ok(()) }
+//! ```
+
+use async_std::io;
+use openssl::asn1::Asn1Time;
+use openssl::bn::{BigNum, MsbOption};
+use openssl::ec::{EcGroup, EcKey};
+use openssl::error::ErrorStack;
+use openssl::hash::MessageDigest;
+use openssl::nid::Nid;
+use openssl::pkey::PKey;
+use openssl::x509::extension::SubjectAlternativeName;
+use openssl::x509::{X509NameBuilder, X509};
+
+/// Generates a self-signed P-256 ECDSA certificate for `common_name`,
+/// valid for 90 days, returning the PEM encoded certificate and PKCS#8
+/// key, ready for [`TlsListenerBuilder::cert_pem`] and
+/// [`TlsListenerBuilder::key_pem`].
+///
+/// `common_name` is also set as the certificate's subject alternative
+/// name, as an IP address if it parses as one and as a DNS name
+/// otherwise, since clients only check the latter.
+///
+/// [`TlsListenerBuilder::cert_pem`]: crate::TlsListenerBuilder::cert_pem
+/// [`TlsListenerBuilder::key_pem`]: crate::TlsListenerBuilder::key_pem
+pub fn generate_self_signed(common_name: &str) -> io::Result<(Vec<u8>, Vec<u8>)> {
+    generate(common_name).map_err(io::Error::other)
+}
+
+fn generate(common_name: &str) -> Result<(Vec<u8>, Vec<u8>), ErrorStack> {
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+    let key = PKey::from_ec_key(EcKey::generate(&group)?)?;
+
+    let mut name = X509NameBuilder::new()?;
+    name.append_entry_by_text("CN", common_name)?;
+    let name = name.build();
+
+    let mut serial = BigNum::new()?;
+    serial.rand(64, MsbOption::MAYBE_ZERO, false)?;
+
+    let serial = serial.to_asn1_integer()?;
+    let not_before = Asn1Time::days_from_now(0)?;
+    let not_after = Asn1Time::days_from_now(90)?;
+
+    let mut cert = X509::builder()?;
+    cert.set_version(2)?;
+    cert.set_serial_number(&serial)?;
+    cert.set_subject_name(&name)?;
+    cert.set_issuer_name(&name)?;
+    cert.set_pubkey(&key)?;
+    cert.set_not_before(&not_before)?;
+    cert.set_not_after(&not_after)?;
+
+    let mut san = SubjectAlternativeName::new();
+    if common_name.parse::<std::net::IpAddr>().is_ok() {
+        san.ip(common_name);
+    } else {
+        san.dns(common_name);
+    }
+    let san = san.build(&cert.x509v3_context(None, None))?;
+    cert.append_extension(san)?;
+    cert.sign(&key, MessageDigest::sha256())?;
+
+    Ok((cert.build().to_pem()?, key.private_key_to_pem_pkcs8()?))
+}
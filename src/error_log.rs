@@ -0,0 +1,55 @@
+use crate::logging;
+use async_std::net::SocketAddr;
+use tide::prelude::json;
+
+use std::fmt::Display;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::SystemTime;
+
+static CONNECTION_IDS: AtomicU64 = AtomicU64::new(1);
+
+/// How errors that end a connection are logged, see
+/// [`TlsListenerBuilder::error_log_format`](crate::TlsListenerBuilder::error_log_format).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorLogFormat {
+    /// A message with the error as a key-value, in the format of the
+    /// installed logger.
+    #[default]
+    Structured,
+    /// A message that is itself a JSON object, with `timestamp` (unix
+    /// seconds), `level`, `message`, `error`, `peer_addr` and
+    /// `connection_id` fields, for log aggregation that does not
+    /// understand key-values.
+    Json,
+}
+
+impl ErrorLogFormat {
+    /// Logs an error on the connection numbered `connection_id`.
+    pub(crate) fn log(
+        self,
+        message: &'static str,
+        error: &dyn Display,
+        peer_addr: Option<SocketAddr>,
+        connection_id: u64,
+    ) {
+        match self {
+            Self::Structured => logging::error!("{}", message, { error: error.to_string() }),
+            Self::Json => {
+                let line = json!({
+                    "timestamp": crate::audit::unix_seconds(SystemTime::now()),
+                    "level": "error",
+                    "message": message,
+                    "error": error.to_string(),
+                    "peer_addr": peer_addr.map(|addr| addr.to_string()),
+                    "connection_id": connection_id,
+                });
+                logging::error!("{}", line);
+            }
+        }
+    }
+}
+
+/// A process-wide unique number for an accepted connection.
+pub(crate) fn next_connection_id() -> u64 {
+    CONNECTION_IDS.fetch_add(1, Ordering::Relaxed)
+}
@@ -0,0 +1,58 @@
+//! Writing the raw bytes of TLS handshakes to disk, see
+//! [`TlsListenerBuilder::debug_handshake_dump`](crate::TlsListenerBuilder::debug_handshake_dump).
+
+use crate::logging;
+use async_std::net::SocketAddr;
+use async_std::{fs, task};
+
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+/// Writes the bytes each side sent during a handshake to a new file in
+/// `dir`, in the background so the connection is not held up. The file
+/// is named after the connection id that errors on the connection are
+/// logged with.
+pub(crate) fn spawn_write(
+    dir: &Path,
+    id: u64,
+    peer_addr: Option<SocketAddr>,
+    client: Vec<u8>,
+    server: Vec<u8>,
+) {
+    let path = dir.join(format!("connection-{}-{}.hex", std::process::id(), id));
+
+    let mut dump = String::new();
+    match peer_addr {
+        Some(peer_addr) => {
+            let _ = writeln!(dump, "# connection {} from {}", id, peer_addr);
+        }
+        None => {
+            let _ = writeln!(dump, "# connection {}", id);
+        }
+    }
+    append_hex(&mut dump, "client", &client);
+    append_hex(&mut dump, "server", &server);
+
+    task::spawn(write(path, dump));
+}
+
+async fn write(path: PathBuf, dump: String) {
+    if let Err(error) = fs::write(&path, dump).await {
+        logging::error!("unable to write handshake dump", {
+            path: path.display().to_string(),
+            error: error.to_string()
+        });
+    }
+}
+
+/// Appends `bytes` under a `# <label>` heading, 32 bytes per line of
+/// plain hex, so that `xxd -r -p` turns them back into raw bytes.
+fn append_hex(dump: &mut String, label: &str, bytes: &[u8]) {
+    let _ = writeln!(dump, "# {} ({} bytes)", label, bytes.len());
+    for line in bytes.chunks(32) {
+        for byte in line {
+            let _ = write!(dump, "{:02x}", byte);
+        }
+        dump.push('\n');
+    }
+}
@@ -0,0 +1,169 @@
+use crate::{logging, opaque};
+use async_std::net::{SocketAddr, TcpStream};
+use async_std::{future, io, task};
+use async_std_openssl::SslStream;
+use openssl::asn1::Asn1Time;
+use openssl::ssl::{SslConnector, SslMethod, SslVerifyMode};
+use socket2::{Domain, Socket, Type};
+
+use std::collections::HashSet;
+use std::fmt::{self, Debug, Formatter};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+
+/// The longest a single check may take, however long the interval.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// See [`TlsListenerBuilder::on_health_check_failure`](crate::TlsListenerBuilder::on_health_check_failure).
+pub(crate) type HealthCheckFailure = Arc<dyn Fn(&io::Error) + Send + Sync + 'static>;
+
+/// Periodic TLS connections from the listener to itself, see
+/// [`TlsListenerBuilder::health_tls_check`](crate::TlsListenerBuilder::health_tls_check).
+#[derive(Clone, Default)]
+pub(crate) struct HealthCheck {
+    pub(crate) interval: Option<Duration>,
+    pub(crate) on_failure: Option<HealthCheckFailure>,
+}
+
+impl Debug for HealthCheck {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HealthCheck")
+            .field("interval", &self.interval)
+            .field("on_failure", &opaque(&self.on_failure))
+            .finish()
+    }
+}
+
+impl HealthCheck {
+    /// Connects to `addr` every interval, if one is set. The task ends
+    /// once `alive` can no longer be upgraded, that is once the
+    /// listener is dropped.
+    pub(crate) fn spawn<T: Send + Sync + 'static>(
+        &self,
+        addr: SocketAddr,
+        probes: Arc<HealthProbes>,
+        alive: Weak<T>,
+    ) {
+        let interval = match self.interval {
+            Some(interval) => interval,
+            None => return,
+        };
+        let on_failure = self.on_failure.clone();
+        let addr = loopback(addr);
+
+        task::spawn(async move {
+            loop {
+                task::sleep(interval).await;
+                if alive.upgrade().is_none() {
+                    break;
+                }
+
+                let timeout = interval.min(CHECK_TIMEOUT);
+                let result = match future::timeout(timeout, check(addr, &probes)).await {
+                    Ok(result) => result,
+                    Err(timeout) => Err(io::Error::new(io::ErrorKind::TimedOut, timeout)),
+                };
+                if let Err(error) = result {
+                    logging::error!("tls health check failed", { error: error.to_string() });
+                    if let Some(on_failure) = &on_failure {
+                        on_failure(&error);
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// Connects to listeners bound to all interfaces through the loopback
+/// interface.
+fn loopback(mut addr: SocketAddr) -> SocketAddr {
+    if addr.ip().is_unspecified() {
+        match addr {
+            SocketAddr::V4(ref mut addr) => addr.set_ip(std::net::Ipv4Addr::LOCALHOST),
+            SocketAddr::V6(ref mut addr) => addr.set_ip(std::net::Ipv6Addr::LOCALHOST),
+        }
+    }
+    addr
+}
+
+/// The local addresses of the connections a health check has open,
+/// which the listener tells apart from its clients by their peer
+/// address, to leave them out of its stats, audit log and slow
+/// handshake log.
+#[derive(Debug, Default)]
+pub(crate) struct HealthProbes(Mutex<HashSet<SocketAddr>>);
+
+impl HealthProbes {
+    pub(crate) fn contains(&self, peer_addr: Option<SocketAddr>) -> bool {
+        match peer_addr {
+            Some(peer_addr) => self.lock().contains(&peer_addr),
+            None => false,
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashSet<SocketAddr>> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Marks a local address as a health check's until dropped.
+struct Probe<'a>(&'a HealthProbes, SocketAddr);
+
+impl Drop for Probe<'_> {
+    fn drop(&mut self) {
+        self.0.lock().remove(&self.1);
+    }
+}
+
+/// Connects to `addr` from a local address that is registered with
+/// `probes` before the listener can accept the connection.
+async fn connect<'a>(
+    addr: SocketAddr,
+    probes: &'a HealthProbes,
+) -> io::Result<(TcpStream, Probe<'a>)> {
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, None)?;
+    socket.bind(&SocketAddr::new(addr.ip(), 0).into())?;
+    let local_addr = socket
+        .local_addr()?
+        .as_socket()
+        .ok_or_else(|| io::Error::other("health check socket has no address"))?;
+    probes.lock().insert(local_addr);
+    let probe = Probe(probes, local_addr);
+
+    let tcp = task::spawn_blocking(move || {
+        socket.connect(&addr.into())?;
+        Ok::<_, io::Error>(std::net::TcpStream::from(socket))
+    })
+    .await?;
+    Ok((TcpStream::from(tcp), probe))
+}
+
+/// Completes a handshake with `addr` and checks that the certificate
+/// it serves is within its validity period. The chain is not verified,
+/// since the issuing CA need not be in the system trust store.
+async fn check(addr: SocketAddr, probes: &HealthProbes) -> io::Result<()> {
+    let mut connector = SslConnector::builder(SslMethod::tls())?;
+    connector.set_verify(SslVerifyMode::NONE);
+    let ssl = connector.build().configure()?.into_ssl("localhost")?;
+
+    let (tcp, _probe) = connect(addr, probes).await?;
+    let mut stream = SslStream::new(ssl, tcp)?;
+    Pin::new(&mut stream)
+        .connect()
+        .await
+        .map_err(io::Error::other)?;
+
+    let cert = stream
+        .ssl()
+        .peer_certificate()
+        .ok_or_else(|| io::Error::other("no certificate served"))?;
+    let now = Asn1Time::days_from_now(0)?;
+    if cert.not_before() > now {
+        return Err(io::Error::other("certificate is not yet valid"));
+    }
+    if cert.not_after() < now {
+        return Err(io::Error::other("certificate has expired"));
+    }
+    Ok(())
+}
@@ -0,0 +1,226 @@
+//! Minimal parsing of the plaintext TLS hello messages, for the checks
+//! that OpenSSL does not expose through the safe `openssl` API.
+
+use openssl::error::ErrorStack;
+use openssl::ex_data::Index;
+use openssl::hash::{hash, MessageDigest};
+use openssl::ssl::{Ssl, SslRef};
+
+use std::sync::{Arc, Mutex, OnceLock};
+
+const CHANGE_CIPHER_SPEC_RECORD: u8 = 0x14;
+const HANDSHAKE_RECORD: u8 = 0x16;
+const CLIENT_HELLO: u8 = 0x01;
+const SERVER_HELLO: u8 = 0x02;
+
+const SUPPORTED_GROUPS: u16 = 10;
+const EC_POINT_FORMATS: u16 = 11;
+
+/// The bytes a client has sent so far, shared between the
+/// [`RecordingStream`](crate::RecordingStream) that records them and
+/// the `Ssl` they belong to.
+pub(crate) type ReadRecording = Arc<Mutex<Vec<u8>>>;
+
+/// The `Ssl` ex data slot holding the [`ReadRecording`] of the
+/// connection, for callbacks that need the raw ClientHello.
+pub(crate) fn read_recording_index() -> Result<Index<Ssl, ReadRecording>, ErrorStack> {
+    static INDEX: OnceLock<Index<Ssl, ReadRecording>> = OnceLock::new();
+    if let Some(index) = INDEX.get() {
+        return Ok(*index);
+    }
+    let index = Ssl::new_ex_index()?;
+    Ok(*INDEX.get_or_init(|| index))
+}
+
+/// Takes what the client has sent since it was last taken, which from
+/// the ClientHello callback on is at least the whole ClientHello. The
+/// callback runs again for the second ClientHello after a
+/// HelloRetryRequest, which then starts the recording.
+pub(crate) fn take_read(ssl: &SslRef) -> Option<Vec<u8>> {
+    let recording = ssl.ex_data(read_recording_index().ok()?)?;
+    let mut recording = recording.lock().unwrap_or_else(|e| e.into_inner());
+    Some(std::mem::take(&mut *recording))
+}
+
+/// Whether `value` is one of the reserved GREASE values of RFC 8701,
+/// which clients send at random to keep servers tolerant of unknown
+/// values.
+pub(crate) fn is_grease(value: u16) -> bool {
+    value & 0x0f0f == 0x0a0a && value >> 8 == value & 0xff
+}
+
+/// A cursor over a byte slice that fails softly on truncated input.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        if self.0.len() < n {
+            return None;
+        }
+        let (head, tail) = self.0.split_at(n);
+        self.0 = tail;
+        Some(head)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.take(1).map(|b| b[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.take(2).map(|b| u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn u24(&mut self) -> Option<usize> {
+        self.take(3)
+            .map(|b| (b[0] as usize) << 16 | (b[1] as usize) << 8 | b[2] as usize)
+    }
+}
+
+/// Parses the compression method chosen in a ServerHello, given the
+/// bytes the server wrote starting at its first record.
+pub(crate) fn server_hello_compression(written: &[u8]) -> Option<u8> {
+    let mut record = Reader(written);
+    if record.u8()? != HANDSHAKE_RECORD {
+        return None;
+    }
+    let _version = record.u16()?;
+    let len = record.u16()? as usize;
+
+    let mut hello = Reader(record.take(len)?);
+    if hello.u8()? != SERVER_HELLO {
+        return None;
+    }
+    let _len = hello.u24()?;
+    let _version = hello.u16()?;
+    let _random = hello.take(32)?;
+    let session_id_len = hello.u8()? as usize;
+    let _session_id = hello.take(session_id_len)?;
+    let _cipher = hello.u16()?;
+    hello.u8()
+}
+
+/// Reassembles the first handshake message from the records starting
+/// at `read`, since a large ClientHello may span several records.
+///
+/// After a TLS 1.3 HelloRetryRequest the client may send a dummy
+/// ChangeCipherSpec record ahead of its second ClientHello, which is
+/// skipped.
+fn first_handshake_message(read: &[u8]) -> Option<Vec<u8>> {
+    let mut records = Reader(read);
+    let mut message = Vec::new();
+    loop {
+        let content_type = records.u8()?;
+        let _version = records.u16()?;
+        let len = records.u16()? as usize;
+        let fragment = records.take(len)?;
+        match content_type {
+            HANDSHAKE_RECORD => message.extend_from_slice(fragment),
+            CHANGE_CIPHER_SPEC_RECORD if message.is_empty() => continue,
+            _ => return None,
+        }
+
+        if message.len() >= 4 {
+            let len = Reader(&message[1..4]).u24()? + 4;
+            if message.len() >= len {
+                message.truncate(len);
+                return Some(message);
+            }
+        }
+    }
+}
+
+/// The parts of a ClientHello that the checks on it look at.
+#[derive(Debug, Default)]
+pub(crate) struct ClientHello {
+    pub(crate) version: u16,
+    pub(crate) ciphers: Vec<u16>,
+    /// Extension types, in the order the client sent them.
+    pub(crate) extensions: Vec<u16>,
+    /// The contents of the supported_groups extension.
+    pub(crate) groups: Vec<u16>,
+    /// The contents of the ec_point_formats extension.
+    pub(crate) point_formats: Vec<u8>,
+}
+
+impl ClientHello {
+    /// Parses a ClientHello, given the bytes the client wrote starting
+    /// at its first record.
+    pub(crate) fn parse(read: &[u8]) -> Option<Self> {
+        let message = first_handshake_message(read)?;
+        let mut hello = Reader(&message);
+        if hello.u8()? != CLIENT_HELLO {
+            return None;
+        }
+        let _len = hello.u24()?;
+        let version = hello.u16()?;
+        let _random = hello.take(32)?;
+        let session_id_len = hello.u8()? as usize;
+        let _session_id = hello.take(session_id_len)?;
+        let ciphers_len = hello.u16()? as usize;
+        let mut ciphers = Reader(hello.take(ciphers_len)?);
+        let compression_len = hello.u8()? as usize;
+        let _compression = hello.take(compression_len)?;
+
+        let mut parsed = Self {
+            version,
+            ..Self::default()
+        };
+        while !ciphers.0.is_empty() {
+            parsed.ciphers.push(ciphers.u16()?);
+        }
+
+        // the extensions block may be left out entirely
+        if hello.0.is_empty() {
+            return Some(parsed);
+        }
+        let extensions_len = hello.u16()? as usize;
+        let mut extensions = Reader(hello.take(extensions_len)?);
+        while !extensions.0.is_empty() {
+            let extension = extensions.u16()?;
+            let len = extensions.u16()? as usize;
+            let mut data = Reader(extensions.take(len)?);
+            match extension {
+                SUPPORTED_GROUPS => {
+                    let len = data.u16()? as usize;
+                    let mut groups = Reader(data.take(len)?);
+                    while !groups.0.is_empty() {
+                        parsed.groups.push(groups.u16()?);
+                    }
+                }
+                EC_POINT_FORMATS => {
+                    let len = data.u8()? as usize;
+                    parsed.point_formats = data.take(len)?.to_vec();
+                }
+                _ => {}
+            }
+            parsed.extensions.push(extension);
+        }
+        Some(parsed)
+    }
+
+    /// The JA3 fingerprint of the ClientHello: the MD5 hash, in lower
+    /// case hex, of its version, ciphers, extensions, groups and point
+    /// formats, with GREASE values left out.
+    pub(crate) fn ja3(&self) -> Result<String, ErrorStack> {
+        fn join<T: Copy + Into<u16>>(values: &[T]) -> String {
+            values
+                .iter()
+                .map(|&value| value.into())
+                .filter(|&value| !is_grease(value))
+                .map(|value| value.to_string())
+                .collect::<Vec<_>>()
+                .join("-")
+        }
+
+        let ja3 = format!(
+            "{},{},{},{},{}",
+            self.version,
+            join(&self.ciphers),
+            join(&self.extensions),
+            join(&self.groups),
+            join(&self.point_formats),
+        );
+        let digest = hash(MessageDigest::md5(), ja3.as_bytes())?;
+        Ok(digest.iter().map(|byte| format!("{:02x}", byte)).collect())
+    }
+}
@@ -0,0 +1,227 @@
+use crate::tls_acceptor_options::BaseProfile;
+use crate::{TlsAcceptorOptions, TlsListenerConfig};
+use async_std::io;
+use openssl::ssl::{
+    NameType, SniError, SslAcceptor, SslAcceptorBuilder, SslAlert, SslContext, SslMethod,
+    SslVersion,
+};
+
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Formatter};
+use std::path::{Path, PathBuf};
+
+/// The certificate and TLS settings served to clients that ask for a
+/// particular host name, see
+/// [`TlsListenerBuilder::add_host_config`](crate::TlsListenerBuilder::add_host_config).
+/// Created with [`HostConfig::build`].
+#[derive(Debug, Clone)]
+pub struct HostConfig {
+    material: TlsListenerConfig,
+    alpn_protocols: Option<Vec<Vec<u8>>>,
+    min_tls_version: Option<SslVersion>,
+}
+
+impl HostConfig {
+    /// Starts building a host config.
+    ///
+    /// ```rust
+    /// # use tide_openssl::HostConfig;
+    /// let grpc = HostConfig::build()
+    ///     .cert("./tls/localhost-4433.cert")
+    ///     .key("./tls/localhost-4433.key")
+    ///     .alpn_protocols(["h2"])
+    ///     .finish();
+    /// ```
+    pub fn build() -> HostConfigBuilder {
+        HostConfigBuilder::default()
+    }
+
+    /// Builds the context for this host, starting out from the
+    /// listener's Mozilla profile, with the listener's options applied
+    /// on top of the host's own.
+    fn build_context(&self, options: &TlsAcceptorOptions) -> io::Result<SslContext> {
+        let mut acceptor = match options.profile {
+            BaseProfile::MozillaIntermediateV5 => {
+                SslAcceptor::mozilla_intermediate_v5(SslMethod::tls())
+            }
+            // a custom builder only serves the listener's own acceptor
+            BaseProfile::MozillaModernV5 | BaseProfile::Custom(_) => {
+                SslAcceptor::mozilla_modern_v5(SslMethod::tls())
+            }
+        }
+        .map_err(io::Error::other)?;
+        self.material.load_into(&mut acceptor, options)?;
+        let alpn_protocols = self
+            .alpn_protocols
+            .as_deref()
+            .or(options.alpn_protocols.as_deref());
+        options.apply_to_host(&mut acceptor, alpn_protocols)?;
+        let acceptor = acceptor.build();
+        options.inspect(&acceptor);
+        Ok(acceptor.into_context())
+    }
+}
+
+/// # A builder for [`HostConfig`]s
+///
+/// Exactly one of a cert and key path or cert and key PEM contents
+/// must be provided.
+#[derive(Clone, Default)]
+pub struct HostConfigBuilder {
+    cert: Option<PathBuf>,
+    key: Option<PathBuf>,
+    cert_pem: Option<String>,
+    key_pem: Option<String>,
+    alpn_protocols: Option<Vec<Vec<u8>>>,
+    min_tls_version: Option<SslVersion>,
+}
+
+impl Debug for HostConfigBuilder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HostConfigBuilder")
+            .field("cert", &self.cert)
+            .field("key", &self.key)
+            .field("cert_pem", &self.cert_pem.as_ref().map(|_| ".."))
+            .field("key_pem", &self.key_pem.as_ref().map(|_| "<redacted>"))
+            .field("alpn_protocols", &self.alpn_protocols)
+            .field("min_tls_version", &self.min_tls_version)
+            .finish()
+    }
+}
+
+impl HostConfigBuilder {
+    /// Provide a path to the host's certificate file, followed by its
+    /// chain, in PEM format.
+    pub fn cert(mut self, path: impl AsRef<Path>) -> Self {
+        self.cert = Some(path.as_ref().into());
+        self
+    }
+
+    /// Provide a path to the host's private key file.
+    pub fn key(mut self, path: impl AsRef<Path>) -> Self {
+        self.key = Some(path.as_ref().into());
+        self
+    }
+
+    /// Provide the host's PEM encoded certificate chain, leaf first.
+    pub fn cert_pem(mut self, pem: impl Into<String>) -> Self {
+        self.cert_pem = Some(pem.into());
+        self
+    }
+
+    /// Provide the host's PEM encoded private key.
+    pub fn key_pem(mut self, pem: impl Into<String>) -> Self {
+        self.key_pem = Some(pem.into());
+        self
+    }
+
+    /// The protocols advertised to this host's clients during ALPN
+    /// negotiation, in order of preference, instead of those of the
+    /// listener.
+    pub fn alpn_protocols<P: AsRef<[u8]>>(
+        mut self,
+        protocols: impl IntoIterator<Item = P>,
+    ) -> Self {
+        self.alpn_protocols = Some(
+            protocols
+                .into_iter()
+                .map(|protocol| protocol.as_ref().to_vec())
+                .collect(),
+        );
+        self
+    }
+
+    /// Refuses handshakes for this host below `version`. The protocol
+    /// version is negotiated before the host name is known, within the
+    /// versions the listener supports, so this can only narrow those:
+    /// the handshake of a client that negotiated an older version is
+    /// refused.
+    pub fn min_tls_version(mut self, version: SslVersion) -> Self {
+        self.min_tls_version = Some(version);
+        self
+    }
+
+    /// Creates the host config. This returns an error unless exactly
+    /// one of a cert and key path or cert and key PEM contents were
+    /// provided. The key material is only read once the listener's
+    /// acceptor is built.
+    pub fn finish(self) -> io::Result<HostConfig> {
+        let material = match (self.cert, self.key, self.cert_pem, self.key_pem) {
+            (Some(cert), Some(key), None, None) => TlsListenerConfig::Paths {
+                cert,
+                key,
+                chain: None,
+            },
+            (None, None, Some(cert), Some(key)) => TlsListenerConfig::Pem { cert, key },
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "need exactly one of cert + key or cert_pem + key_pem",
+                ))
+            }
+        };
+        Ok(HostConfig {
+            material,
+            alpn_protocols: self.alpn_protocols,
+            min_tls_version: self.min_tls_version,
+        })
+    }
+}
+
+/// Switches a handshake to the context of the host name the client
+/// asked for, if there is one. Names are matched case-insensitively,
+/// and clients asking for any other name, or none, are served the
+/// listener's own context.
+pub(crate) fn set_servername_callback(
+    acceptor: &mut SslAcceptorBuilder,
+    hosts: &[(String, HostConfig)],
+    options: &TlsAcceptorOptions,
+) -> io::Result<()> {
+    if hosts.is_empty() {
+        return Ok(());
+    }
+    let mut contexts = HashMap::new();
+    for (hostname, host) in hosts {
+        let context = host.build_context(options)?;
+        contexts.insert(
+            hostname.to_ascii_lowercase(),
+            (context, host.min_tls_version),
+        );
+    }
+
+    acceptor.set_servername_callback(move |ssl, alert| {
+        let hostname = match ssl.servername(NameType::HOST_NAME) {
+            Some(hostname) => hostname.to_ascii_lowercase(),
+            None => return Ok(()),
+        };
+        let (context, min_tls_version) = match contexts.get(&hostname) {
+            Some(host) => host,
+            None => return Ok(()),
+        };
+        if let (Some(min), Some(version)) = (min_tls_version, ssl.version2()) {
+            if version_order(version) < version_order(*min) {
+                // the openssl crate has no protocol_version alert
+                *alert = SslAlert::ILLEGAL_PARAMETER;
+                return Err(SniError::ALERT_FATAL);
+            }
+        }
+        ssl.set_ssl_context(context)
+            .map_err(|_| SniError::ALERT_FATAL)
+    });
+    Ok(())
+}
+
+/// Orders protocol versions, which openssl does not.
+fn version_order(version: SslVersion) -> u8 {
+    if version == SslVersion::TLS1_3 {
+        4
+    } else if version == SslVersion::TLS1_2 {
+        3
+    } else if version == SslVersion::TLS1_1 {
+        2
+    } else if version == SslVersion::TLS1 {
+        1
+    } else {
+        0
+    }
+}
@@ -0,0 +1,220 @@
+use crate::{logging, ConnectionAudit, ConnectionOptions};
+use async_std::io::{self, BufRead, BufReader, Read, Write};
+use async_std::net::SocketAddr;
+use async_std::task;
+
+use bytes::Bytes;
+use h2::server::SendResponse;
+use h2::{RecvStream, SendStream};
+use http::header::{self, HeaderMap, HeaderName};
+use tide::http::{Body, Request, Response, StatusCode};
+use tide::Server;
+use tokio_util::compat::FuturesAsyncReadCompatExt;
+
+use std::convert::TryFrom;
+use std::future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+/// The largest request body read from an h2 stream, unless set with
+/// [`TlsListenerBuilder::http2_max_request_body_size`](crate::TlsListenerBuilder::http2_max_request_body_size).
+pub(crate) const DEFAULT_MAX_REQUEST_BODY_SIZE: usize = 10 * 1024 * 1024;
+
+/// Headers that only apply to a single HTTP/1.1 connection, which
+/// HTTP/2 forbids (RFC 9113, section 8.2.2).
+const CONNECTION_HEADERS: [&str; 5] = [
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "transfer-encoding",
+    "upgrade",
+];
+
+/// Serves HTTP/2 over a negotiated TLS stream until the client goes away.
+///
+/// Each stream is handled on its own task. Request bodies are streamed
+/// to tide as the client sends them, up to the configured size, and
+/// responses are streamed back within the client's flow control
+/// window.
+pub(crate) async fn accept<State, IO>(
+    app: Server<State>,
+    io: IO,
+    options: Arc<ConnectionOptions>,
+    audit: Option<Arc<ConnectionAudit>>,
+    local_addr: Option<SocketAddr>,
+    peer_addr: Option<SocketAddr>,
+) -> Result<(), h2::Error>
+where
+    State: Clone + Send + Sync + 'static,
+    IO: Read + Write + Unpin,
+{
+    let mut connection = h2::server::handshake(io.compat()).await?;
+
+    while let Some(result) = connection.accept().await {
+        let (request, respond) = result?;
+        let (app, options, audit) = (app.clone(), options.clone(), audit.clone());
+        task::spawn(async move {
+            let audit = audit.as_deref();
+            let res = respond_to(
+                app, request, respond, &options, audit, local_addr, peer_addr,
+            )
+            .await;
+            if let Err(error) = res {
+                logging::error!("h2 error", { error: error.to_string() });
+            }
+        });
+    }
+
+    Ok(())
+}
+
+async fn respond_to<State: Clone + Send + Sync + 'static>(
+    app: Server<State>,
+    request: http::Request<RecvStream>,
+    mut respond: SendResponse<Bytes>,
+    options: &ConnectionOptions,
+    audit: Option<&ConnectionAudit>,
+    local_addr: Option<SocketAddr>,
+    peer_addr: Option<SocketAddr>,
+) -> tide::http::Result<()> {
+    let (parts, recv) = request.into_parts();
+    let max_size = options.http2_max_request_body_size;
+    let len = parts
+        .headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|len| len.to_str().ok()?.parse::<usize>().ok());
+    if len.is_some_and(|len| len > max_size) {
+        return send(&mut respond, Response::new(StatusCode::PayloadTooLarge)).await;
+    }
+
+    let exceeded = Arc::new(AtomicBool::new(false));
+    let body = RequestBody {
+        recv,
+        pending: Bytes::new(),
+        remaining: max_size,
+        exceeded: exceeded.clone(),
+    };
+    let body = Body::from_reader(BufReader::new(body), len);
+    let mut req = Request::try_from(http::Request::from_parts(parts, body))?;
+    options.prepare(&mut req, local_addr, peer_addr);
+    let res = options.respond(&app, req, audit).await?;
+
+    // the handler saw a failed read, and may have answered as if the
+    // body had simply ended
+    if exceeded.load(Ordering::SeqCst) {
+        return send(&mut respond, Response::new(StatusCode::PayloadTooLarge)).await;
+    }
+    send(&mut respond, res).await
+}
+
+/// Sends `res` on the stream, streaming its body as the client's flow
+/// control window allows.
+async fn send(respond: &mut SendResponse<Bytes>, res: Response) -> tide::http::Result<()> {
+    let (mut parts, mut body) = http::Response::<Body>::from(res).into_parts();
+    parts.version = http::Version::HTTP_2;
+    strip_connection_headers(&mut parts.headers);
+
+    let head = http::Response::from_parts(parts, ());
+    if body.is_empty() == Some(true) {
+        respond.send_response(head, true)?;
+        return Ok(());
+    }
+    let mut stream = respond.send_response(head, false)?;
+    loop {
+        let chunk = future::poll_fn(|cx| {
+            Pin::new(&mut body)
+                .poll_fill_buf(cx)
+                .map_ok(Bytes::copy_from_slice)
+        })
+        .await?;
+        if chunk.is_empty() {
+            break;
+        }
+        Pin::new(&mut body).consume(chunk.len());
+        send_data(&mut stream, chunk).await?;
+    }
+    stream.send_data(Bytes::new(), true)?;
+    Ok(())
+}
+
+/// Sends `data` in as many frames as the flow control window needs.
+async fn send_data(stream: &mut SendStream<Bytes>, mut data: Bytes) -> Result<(), h2::Error> {
+    while !data.is_empty() {
+        stream.reserve_capacity(data.len());
+        let capacity = match future::poll_fn(|cx| stream.poll_capacity(cx)).await {
+            Some(capacity) => capacity?,
+            // the client reset the stream, sending returns why
+            None => return stream.send_data(Bytes::new(), true),
+        };
+        let frame = data.split_to(capacity.min(data.len()));
+        stream.send_data(frame, false)?;
+    }
+    Ok(())
+}
+
+/// Removes the headers HTTP/2 forbids, along with any that a
+/// `Connection` header names. `TE` is only allowed as `trailers`.
+fn strip_connection_headers(headers: &mut HeaderMap) {
+    let named: Vec<HeaderName> = headers
+        .get_all(header::CONNECTION)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|name| HeaderName::from_bytes(name.trim().as_bytes()).ok())
+        .collect();
+    for name in named {
+        headers.remove(name);
+    }
+    for name in CONNECTION_HEADERS {
+        headers.remove(name);
+    }
+    if headers
+        .get(header::TE)
+        .is_some_and(|te| !te.as_bytes().eq_ignore_ascii_case(b"trailers"))
+    {
+        headers.remove(header::TE);
+    }
+}
+
+/// The body of an h2 request, handed to tide as it arrives. Received
+/// data is only released to the client's flow control window once it
+/// has been read, so a slow handler holds the client back.
+struct RequestBody {
+    recv: RecvStream,
+    pending: Bytes,
+    remaining: usize,
+    exceeded: Arc<AtomicBool>,
+}
+
+impl Read for RequestBody {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        while this.pending.is_empty() {
+            let chunk = match this.recv.poll_data(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(None) => return Poll::Ready(Ok(0)),
+                Poll::Ready(Some(chunk)) => chunk.map_err(io::Error::other)?,
+            };
+            if chunk.len() > this.remaining {
+                this.exceeded.store(true, Ordering::SeqCst);
+                return Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "h2 request body is too large",
+                )));
+            }
+            this.remaining -= chunk.len();
+            this.pending = chunk;
+        }
+
+        let read = this.pending.len().min(buf.len());
+        buf[..read].copy_from_slice(&this.pending.split_to(read));
+        let _ = this.recv.flow_control().release_capacity(read);
+        Poll::Ready(Ok(read))
+    }
+}
@@ -28,13 +28,74 @@
     unused_qualifications
 )]
 
-mod tcp_connection;
+#[cfg(all(feature = "unix", not(unix)))]
+compile_error!("the unix feature is only available on unix platforms");
+
+mod audit;
+mod cert_provider;
+mod connection;
+mod connection_options;
+mod cookie_security;
+mod error_log;
+#[cfg(feature = "debug")]
+mod handshake_dump;
+mod health_check;
+mod hello;
+mod host_config;
+#[cfg(feature = "h2")]
+mod http2;
+mod logging;
+#[cfg(feature = "metrics")]
+mod metrics;
+mod opaque;
+mod protocol_buffer;
+mod recording_stream;
+#[cfg(feature = "remote-config")]
+mod remote_config;
+mod request_signing;
+mod request_tags;
+mod tcp_options;
+mod tls_acceptor_options;
+mod tls_incoming;
 mod tls_listener;
 mod tls_listener_builder;
 mod tls_listener_config;
+mod tls_session_summary;
+mod tls_stats;
+mod x509;
+
+#[cfg(feature = "dev")]
+pub mod dev;
+#[cfg(feature = "test-helpers")]
+pub mod test_helpers;
+pub mod typestate;
 
-pub(crate) use tcp_connection::TcpConnection;
-pub(crate) use tls_listener_config::TlsListenerConfig;
+pub(crate) use audit::ConnectionAudit;
+pub(crate) use cert_provider::{ProviderSource, SharedCertProvider};
+pub(crate) use connection::Connection;
+pub(crate) use connection_options::{ConnectionOptions, RequestTagger};
+pub(crate) use health_check::{HealthCheck, HealthCheckFailure, HealthProbes};
+pub(crate) use opaque::opaque;
+pub(crate) use protocol_buffer::ProtocolBuffer;
+pub(crate) use recording_stream::RecordingStream;
+#[cfg(feature = "remote-config")]
+pub(crate) use remote_config::{AppliedDocument, RemoteConfig};
+pub(crate) use request_signing::RequestSigning;
+pub(crate) use tcp_options::TcpOptions;
+pub(crate) use tls_acceptor_options::{ContextHook, DhParams, Material, TlsAcceptorOptions};
+pub(crate) use tls_listener_config::{AcceptorFactory, AcceptorSource, InvalidationCheck};
+pub(crate) use tls_stats::TlsStats;
+pub(crate) use x509::distinguished_name;
 
+pub use audit::{AuditEntry, AuditRequest, ConnectionAuditWriter, JsonLineAuditWriter};
+pub use cert_provider::{CachingCertProvider, CertAndKey, CertProvider};
+pub use cookie_security::CookieSecurityOptions;
+pub use error_log::ErrorLogFormat;
+pub use host_config::{HostConfig, HostConfigBuilder};
+pub use request_signing::SigningDigest;
+pub use request_tags::RequestTags;
+pub use tls_acceptor_options::{CurvesPreference, TlsProfile};
 pub use tls_listener::TlsListener;
 pub use tls_listener_builder::TlsListenerBuilder;
+pub use tls_listener_config::TlsListenerConfig;
+pub use tls_session_summary::TlsSessionSummary;
@@ -0,0 +1,67 @@
+//! Log events, sent to `tracing` with the `tracing` feature and to
+//! `tide::log` otherwise. The macros take the `tide::log` form: a
+//! message, optionally followed by format arguments or by key-values
+//! in braces.
+
+macro_rules! event {
+    ($level:ident, $message:literal) => {{
+        #[cfg(feature = "tracing")]
+        tracing::$level!($message);
+        #[cfg(not(feature = "tracing"))]
+        tide::log::$level!($message);
+    }};
+    ($level:ident, $message:literal, { $($key:ident: $value:expr),* $(,)? }) => {{
+        #[cfg(feature = "tracing")]
+        tracing::$level!($($key = %$value,)* $message);
+        #[cfg(not(feature = "tracing"))]
+        tide::log::$level!($message, { $($key: $value),* });
+    }};
+    ($level:ident, $format:literal, $arg:expr, { $($key:ident: $value:expr),* $(,)? }) => {{
+        #[cfg(feature = "tracing")]
+        tracing::$level!($($key = %$value,)* $format, $arg);
+        #[cfg(not(feature = "tracing"))]
+        tide::log::$level!($format, $arg, { $($key: $value),* });
+    }};
+    ($level:ident, $format:literal, $($arg:expr),+ $(,)?) => {{
+        #[cfg(feature = "tracing")]
+        tracing::$level!($format, $($arg),+);
+        #[cfg(not(feature = "tracing"))]
+        tide::log::$level!($format, $($arg),+);
+    }};
+}
+
+macro_rules! error {
+    ($($event:tt)+) => {
+        crate::logging::event!(error, $($event)+)
+    };
+}
+
+macro_rules! info {
+    ($($event:tt)+) => {
+        crate::logging::event!(info, $($event)+)
+    };
+}
+
+macro_rules! warning {
+    ($($event:tt)+) => {
+        crate::logging::event!(warn, $($event)+)
+    };
+}
+
+pub(crate) use {error, event, info, warning};
+
+/// The span a connection is handled in, with the negotiated protocol
+/// and cipher recorded once the handshake completes.
+#[cfg(feature = "tracing")]
+pub(crate) fn connection_span(
+    local_addr: Option<std::net::SocketAddr>,
+    peer_addr: Option<std::net::SocketAddr>,
+) -> tracing::Span {
+    tracing::info_span!(
+        "tls_connection",
+        local_addr = ?local_addr,
+        peer_addr = ?peer_addr,
+        protocol_version = tracing::field::Empty,
+        cipher = tracing::field::Empty,
+    )
+}
@@ -0,0 +1,76 @@
+use async_std::net::{IpAddr, SocketAddr};
+use tide::http::{Request, Version};
+
+use std::time::{Duration, Instant};
+
+/// The time from accepting a tcp connection to a completed handshake.
+pub(crate) const HANDSHAKE_DURATION: &str = "tide_openssl.handshake_duration_seconds";
+
+/// The time tide took to respond to a request.
+pub(crate) const REQUEST_DURATION: &str = "tide_openssl.request_duration_seconds";
+
+/// Records a duration in the `name` histogram of the installed
+/// `metrics` recorder, labelled with the peer's address, binned to
+/// its network, and the protocol.
+pub(crate) fn record(
+    name: &'static str,
+    duration: Duration,
+    peer_addr: Option<SocketAddr>,
+    alpn_protocol: Option<&[u8]>,
+) {
+    let peer = peer_addr
+        .map(|addr| peer_network(addr.ip()))
+        .unwrap_or_else(|| String::from("unknown"));
+    let alpn_protocol = alpn_protocol
+        .map(|protocol| String::from_utf8_lossy(protocol).into_owned())
+        .unwrap_or_else(|| String::from("none"));
+    metrics::histogram!(name, "peer_addr" => peer, "alpn_protocol" => alpn_protocol)
+        .record(duration.as_secs_f64());
+}
+
+/// Times how long tide takes to respond to a request, labelled with
+/// the protocol the request came in over.
+#[derive(Debug)]
+pub(crate) struct RequestTiming {
+    started: Instant,
+    peer_addr: Option<SocketAddr>,
+    alpn_protocol: &'static [u8],
+}
+
+impl RequestTiming {
+    pub(crate) fn start(req: &Request) -> Self {
+        Self {
+            started: Instant::now(),
+            peer_addr: req.peer_addr().and_then(|addr| addr.parse().ok()),
+            alpn_protocol: match req.version() {
+                Some(Version::Http2_0) => b"h2",
+                _ => b"http/1.1",
+            },
+        }
+    }
+
+    pub(crate) fn finish(self) {
+        let duration = self.started.elapsed();
+        record(
+            REQUEST_DURATION,
+            duration,
+            self.peer_addr,
+            Some(self.alpn_protocol),
+        );
+    }
+}
+
+/// The /24 or /48 network of a peer, so a label does not take a
+/// distinct value per client.
+fn peer_network(ip: IpAddr) -> String {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, c, _] = ip.octets();
+            format!("{}.{}.{}.0/24", a, b, c)
+        }
+        IpAddr::V6(ip) => {
+            let segments = ip.segments();
+            format!("{:x}:{:x}:{:x}::/48", segments[0], segments[1], segments[2])
+        }
+    }
+}
@@ -0,0 +1,9 @@
+/// `Some(_)` or `None`, for the `Debug` output of optional values that
+/// cannot be formatted themselves, such as callbacks.
+pub(crate) fn opaque<T>(value: &Option<T>) -> &'static str {
+    if value.is_some() {
+        "Some(_)"
+    } else {
+        "None"
+    }
+}
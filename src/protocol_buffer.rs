@@ -0,0 +1,48 @@
+use async_std::io::{self, BufReader, Read, Write};
+
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// Buffers what is read from the TLS stream ahead of async-h1's own
+/// reader, see
+/// [`TlsListenerBuilder::protocol_buffer`](crate::TlsListenerBuilder::protocol_buffer).
+/// Writes go straight through.
+pub(crate) struct ProtocolBuffer<S> {
+    inner: BufReader<S>,
+}
+
+impl<S: Read> ProtocolBuffer<S> {
+    pub(crate) fn new(inner: S, capacity: usize) -> Self {
+        Self {
+            inner: BufReader::with_capacity(capacity, inner),
+        }
+    }
+}
+
+impl<S: Read + Unpin> Read for ProtocolBuffer<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl<S: Read + Write + Unpin> Write for ProtocolBuffer<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(self.inner.get_mut()).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(self.inner.get_mut()).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(self.inner.get_mut()).poll_close(cx)
+    }
+}
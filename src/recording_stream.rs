@@ -0,0 +1,146 @@
+use crate::hello::ReadRecording;
+use async_std::io::{self, Read, Write};
+
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+/// The most we keep of either direction: one maximum-size TLS record
+/// plus its header, which is enough to hold the hello messages.
+const MAX_RECORDED: usize = 16384 + 5;
+
+/// Running totals of the bytes that passed through a
+/// [`RecordingStream`], readable after the stream itself has been
+/// handed off.
+#[derive(Debug, Default)]
+pub(crate) struct ByteCounts {
+    read: AtomicU64,
+    written: AtomicU64,
+}
+
+impl ByteCounts {
+    pub(crate) fn read(&self) -> u64 {
+        self.read.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn written(&self) -> u64 {
+        self.written.load(Ordering::Relaxed)
+    }
+}
+
+/// Wraps the transport underneath OpenSSL so the raw bytes of the
+/// handshake can be inspected once it has completed, and counts all
+/// bytes in either direction.
+#[derive(Debug)]
+pub(crate) struct RecordingStream<S> {
+    inner: S,
+    written: Option<Vec<u8>>,
+    read: Option<ReadRecording>,
+    read_copy: Option<Vec<u8>>,
+    counts: Arc<ByteCounts>,
+}
+
+impl<S> RecordingStream<S> {
+    pub(crate) fn new(inner: S, record_written: bool) -> Self {
+        Self {
+            inner,
+            written: if record_written {
+                Some(Vec::new())
+            } else {
+                None
+            },
+            read: None,
+            read_copy: None,
+            counts: Arc::default(),
+        }
+    }
+
+    /// Starts recording what is read, into a buffer that can be handed
+    /// to OpenSSL callbacks.
+    pub(crate) fn record_read(&mut self) -> ReadRecording {
+        self.read.get_or_insert_with(ReadRecording::default).clone()
+    }
+
+    /// Stops recording what is read.
+    pub(crate) fn stop_recording_read(&mut self) {
+        self.read = None;
+    }
+
+    /// Starts keeping a private copy of what is read, unaffected by
+    /// callbacks taking from the shared recording.
+    #[cfg(feature = "debug")]
+    pub(crate) fn copy_read(&mut self) {
+        self.read_copy.get_or_insert_with(Vec::new);
+    }
+
+    /// Stops copying and returns what was read so far.
+    #[cfg(feature = "debug")]
+    pub(crate) fn take_read_copy(&mut self) -> Vec<u8> {
+        self.read_copy.take().unwrap_or_default()
+    }
+
+    pub(crate) fn counts(&self) -> Arc<ByteCounts> {
+        self.counts.clone()
+    }
+
+    /// Stops recording and returns what was written so far.
+    pub(crate) fn take_written(&mut self) -> Vec<u8> {
+        self.written.take().unwrap_or_default()
+    }
+}
+
+fn record(recording: &mut Vec<u8>, bytes: &[u8]) {
+    let room = MAX_RECORDED.saturating_sub(recording.len());
+    recording.extend_from_slice(&bytes[..bytes.len().min(room)]);
+}
+
+impl<S: Read + Unpin> Read for RecordingStream<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        let poll = Pin::new(&mut this.inner).poll_read(cx, buf);
+        if let Poll::Ready(Ok(n)) = poll {
+            if let Some(read) = &this.read {
+                record(
+                    &mut read.lock().unwrap_or_else(|e| e.into_inner()),
+                    &buf[..n],
+                );
+            }
+            if let Some(read_copy) = &mut this.read_copy {
+                record(read_copy, &buf[..n]);
+            }
+            this.counts.read.fetch_add(n as u64, Ordering::Relaxed);
+        }
+        poll
+    }
+}
+
+impl<S: Write + Unpin> Write for RecordingStream<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        let poll = Pin::new(&mut this.inner).poll_write(cx, buf);
+        if let Poll::Ready(Ok(n)) = poll {
+            if let Some(written) = &mut this.written {
+                record(written, &buf[..n]);
+            }
+            this.counts.written.fetch_add(n as u64, Ordering::Relaxed);
+        }
+        poll
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_close(cx)
+    }
+}
@@ -0,0 +1,368 @@
+use crate::{logging, opaque, AcceptorSource, TlsAcceptorOptions, TlsListenerConfig};
+use async_std::io::ReadExt;
+use async_std::net::TcpStream;
+use async_std::{future, io, task};
+use async_std_openssl::SslStream;
+use openssl::ssl::{SslAcceptor, SslConnector, SslMethod, SslVersion};
+use serde::Deserialize;
+use tide::http::url::Host;
+use tide::http::{headers, Request, Response, Url};
+
+use std::fmt::{self, Debug, Formatter};
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{Arc, RwLock, Weak};
+use std::time::Duration;
+
+/// How long fetching the document may take, independent of how often
+/// it is polled.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The largest document that is read.
+const MAX_DOCUMENT_SIZE: usize = 1024 * 1024;
+
+/// How many redirects are followed before a fetch fails.
+const MAX_REDIRECTS: usize = 5;
+
+/// Where to poll for configuration updates and how often, see
+/// [`TlsListenerBuilder::remote_configuration`](crate::TlsListenerBuilder::remote_configuration).
+#[derive(Debug, Clone)]
+pub(crate) struct RemoteConfig {
+    pub(crate) url: String,
+    pub(crate) interval: Duration,
+}
+
+/// The JSON document served at the configuration url. Every field is
+/// optional: key material that is left out is taken from the
+/// listener's own configuration, and cipher and version settings that
+/// are left out are those of the listener's profile.
+#[derive(Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RemoteDocument {
+    cert: Option<PathBuf>,
+    key: Option<PathBuf>,
+    cert_chain: Option<PathBuf>,
+    cert_pem: Option<String>,
+    key_pem: Option<String>,
+    cipher_list: Option<String>,
+    ciphersuites: Option<String>,
+    min_protocol_version: Option<String>,
+    max_protocol_version: Option<String>,
+}
+
+impl RemoteDocument {
+    /// Parses a document, checking that it holds at most one pair of
+    /// key material and only known protocol versions.
+    fn from_json(json: &[u8]) -> io::Result<Self> {
+        let document: Self = serde_json::from_slice(json)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        document.config()?;
+        document.options(&TlsAcceptorOptions::default())?;
+        Ok(document)
+    }
+
+    /// The key material to serve instead of the listener's own, if
+    /// the document holds any.
+    fn config(&self) -> io::Result<Option<TlsListenerConfig>> {
+        let invalid = |message| Err(io::Error::new(io::ErrorKind::InvalidData, message));
+        match (
+            (&self.cert, &self.key),
+            (&self.cert_pem, &self.key_pem),
+            &self.cert_chain,
+        ) {
+            ((None, None), (None, None), None) => Ok(None),
+            ((Some(cert), Some(key)), (None, None), chain) => Ok(Some(TlsListenerConfig::Paths {
+                cert: cert.clone(),
+                key: key.clone(),
+                chain: chain.clone(),
+            })),
+            ((None, None), (Some(cert), Some(key)), None) => Ok(Some(TlsListenerConfig::Pem {
+                cert: cert.clone(),
+                key: key.clone(),
+            })),
+            ((None, None), (Some(_), Some(_)), Some(_)) => {
+                invalid("cert_chain can only be used with cert and key paths")
+            }
+            _ => invalid("exactly one of cert and key or cert_pem and key_pem must be provided"),
+        }
+    }
+
+    /// The listener's acceptor options with the document's cipher and
+    /// version settings in place.
+    fn options(&self, base: &TlsAcceptorOptions) -> io::Result<TlsAcceptorOptions> {
+        let mut options = base.clone();
+        if let Some(cipher_list) = &self.cipher_list {
+            options.cipher_list = Some(cipher_list.clone());
+        }
+        if let Some(ciphersuites) = &self.ciphersuites {
+            options.ciphersuites = Some(ciphersuites.clone());
+        }
+        if let Some(version) = &self.min_protocol_version {
+            options.min_protocol_version = Some(protocol_version(version)?);
+        }
+        if let Some(version) = &self.max_protocol_version {
+            options.max_protocol_version = Some(protocol_version(version)?);
+        }
+        Ok(options)
+    }
+}
+
+/// A protocol version, named as OpenSSL names it.
+fn protocol_version(name: &str) -> io::Result<SslVersion> {
+    match name {
+        "TLSv1" => Ok(SslVersion::TLS1),
+        "TLSv1.1" => Ok(SslVersion::TLS1_1),
+        "TLSv1.2" => Ok(SslVersion::TLS1_2),
+        "TLSv1.3" => Ok(SslVersion::TLS1_3),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unknown protocol version {}", name),
+        )),
+    }
+}
+
+/// The document last applied to a listener, shared with the tasks
+/// that rebuild its acceptor for other reasons, such as
+/// [`TlsListenerBuilder::invalidation_check`](crate::TlsListenerBuilder::invalidation_check),
+/// so that a rebuild keeps the document in place rather than reverting
+/// to the listener's own configuration.
+#[derive(Clone, Default)]
+pub(crate) struct AppliedDocument(Arc<RwLock<Option<RemoteDocument>>>);
+
+impl Debug for AppliedDocument {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("AppliedDocument")
+            .field(&opaque(&self.get()))
+            .finish()
+    }
+}
+
+impl AppliedDocument {
+    fn get(&self) -> Option<RemoteDocument> {
+        self.0.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Builds an acceptor from `config`, with the applied document, if
+    /// any, in place, and swaps it in. It is dropped instead if another
+    /// document was applied while it was built, as that one's acceptor
+    /// is newer.
+    pub(crate) async fn rebuild(
+        &self,
+        acceptor: &RwLock<SslAcceptor>,
+        config: &AcceptorSource,
+        acceptor_options: &TlsAcceptorOptions,
+    ) -> io::Result<()> {
+        let document = self.get();
+        let rebuilt = match &document {
+            Some(document) => build_acceptor(document, config, acceptor_options).await?,
+            None => config.build_acceptor(acceptor_options).await?,
+        };
+        // documents are applied while holding the acceptor's lock
+        let mut acceptor = acceptor.write().unwrap_or_else(|e| e.into_inner());
+        if self.get() == document {
+            *acceptor = rebuilt;
+        }
+        Ok(())
+    }
+
+    /// Swaps in an acceptor built from `document`, and records it as
+    /// applied.
+    fn apply(
+        &self,
+        acceptor: &RwLock<SslAcceptor>,
+        rebuilt: SslAcceptor,
+        document: RemoteDocument,
+    ) {
+        let mut acceptor = acceptor.write().unwrap_or_else(|e| e.into_inner());
+        *acceptor = rebuilt;
+        *self.0.write().unwrap_or_else(|e| e.into_inner()) = Some(document);
+    }
+}
+
+impl RemoteConfig {
+    /// Checks that the url can be fetched from, see [`checked_url`].
+    pub(crate) fn check_url(&self) -> io::Result<()> {
+        checked_url(&self.url).map(|_| ())
+    }
+
+    /// Fetches the document every interval and rebuilds the acceptor
+    /// whenever it differs from the one last applied. The task ends
+    /// once the listener, and with it the acceptor, is dropped.
+    pub(crate) fn spawn(
+        &self,
+        acceptor: Weak<RwLock<SslAcceptor>>,
+        config: Arc<AcceptorSource>,
+        acceptor_options: Arc<TlsAcceptorOptions>,
+        applied: AppliedDocument,
+    ) {
+        let RemoteConfig { url, interval } = self.clone();
+        task::spawn(async move {
+            loop {
+                task::sleep(interval).await;
+                let acceptor = match acceptor.upgrade() {
+                    Some(acceptor) => acceptor,
+                    None => break,
+                };
+
+                let document = match future::timeout(FETCH_TIMEOUT, fetch(&url)).await {
+                    Ok(Ok(document)) => document,
+                    Ok(Err(error)) => {
+                        logging::error!("unable to fetch remote tls configuration", {
+                            error: error.to_string()
+                        });
+                        continue;
+                    }
+                    Err(timeout) => {
+                        logging::error!("unable to fetch remote tls configuration", {
+                            error: timeout.to_string()
+                        });
+                        continue;
+                    }
+                };
+                if applied.get().as_ref() == Some(&document) {
+                    continue;
+                }
+
+                match build_acceptor(&document, &config, &acceptor_options).await {
+                    Ok(rebuilt) => {
+                        applied.apply(&acceptor, rebuilt, document);
+                        logging::info!("remote tls configuration applied");
+                    }
+                    Err(error) => {
+                        // not marked as applied, so it is tried again on
+                        // the next poll in case referenced files appear
+                        logging::error!("unable to apply remote tls configuration", {
+                            error: error.to_string()
+                        });
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// Builds an acceptor from the document, falling back to the
+/// listener's own key material.
+async fn build_acceptor(
+    document: &RemoteDocument,
+    config: &AcceptorSource,
+    acceptor_options: &TlsAcceptorOptions,
+) -> io::Result<SslAcceptor> {
+    let options = document.options(acceptor_options)?;
+    match document.config()? {
+        Some(config) => config.build_acceptor(&options),
+        None => config.build_acceptor(&options).await,
+    }
+}
+
+/// Parses `url`, which must be https, or plain http to a loopback
+/// host such as a local configuration agent. A document can replace
+/// the key material and lower the protocol version, so it must not
+/// cross the network unprotected.
+fn checked_url(url: &str) -> io::Result<Url> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+    let url = Url::parse(url).map_err(|e| invalid(e.to_string()))?;
+    let loopback = match url.host() {
+        Some(Host::Domain(domain)) => domain.eq_ignore_ascii_case("localhost"),
+        Some(Host::Ipv4(ip)) => ip.is_loopback(),
+        Some(Host::Ipv6(ip)) => ip.is_loopback(),
+        None => return Err(invalid(String::from("missing host"))),
+    };
+    match url.scheme() {
+        "https" => Ok(url),
+        "http" if loopback => Ok(url),
+        "http" => Err(invalid(format!(
+            "remote configuration over plain http is only fetched from loopback hosts, not {}",
+            url
+        ))),
+        scheme => Err(invalid(format!("unsupported scheme {}", scheme))),
+    }
+}
+
+/// Fetches and parses the document, following redirects. Https
+/// servers are verified against the system trust store. Proxies are
+/// not used.
+async fn fetch(url: &str) -> io::Result<RemoteDocument> {
+    let mut url = checked_url(url)?;
+    for _ in 0..=MAX_REDIRECTS {
+        let mut response = get(&url).await?;
+        if response.status().is_redirection() {
+            let location = response.header(headers::LOCATION).ok_or_else(|| {
+                io::Error::other(format!("{} redirect without a location", response.status()))
+            })?;
+            let location = url
+                .join(location.last().as_str())
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            url = checked_url(location.as_str())?;
+            continue;
+        }
+        if !response.status().is_success() {
+            return Err(io::Error::other(format!(
+                "remote configuration responded with {}",
+                response.status()
+            )));
+        }
+
+        let too_large = || {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "remote configuration is larger than {} bytes",
+                    MAX_DOCUMENT_SIZE
+                ),
+            )
+        };
+        if response.len().is_some_and(|len| len > MAX_DOCUMENT_SIZE) {
+            return Err(too_large());
+        }
+        let mut body = Vec::new();
+        response
+            .take_body()
+            .take(MAX_DOCUMENT_SIZE as u64 + 1)
+            .read_to_end(&mut body)
+            .await?;
+        if body.len() > MAX_DOCUMENT_SIZE {
+            return Err(too_large());
+        }
+        return RemoteDocument::from_json(&body);
+    }
+    Err(io::Error::other(format!(
+        "remote configuration redirected more than {} times",
+        MAX_REDIRECTS
+    )))
+}
+
+/// Makes a single GET request to `url`.
+async fn get(url: &Url) -> io::Result<Response> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidInput, message);
+    let port = url
+        .port_or_known_default()
+        .ok_or_else(|| invalid("missing port"))?;
+    // ip addresses are connected to without resolving, and without
+    // the brackets of an IPv6 url host
+    let (tcp, host) = match url.host() {
+        Some(Host::Domain(domain)) => (
+            TcpStream::connect((domain, port)).await?,
+            domain.to_string(),
+        ),
+        Some(Host::Ipv4(ip)) => (TcpStream::connect((ip, port)).await?, ip.to_string()),
+        Some(Host::Ipv6(ip)) => (TcpStream::connect((ip, port)).await?, ip.to_string()),
+        None => return Err(invalid("missing host")),
+    };
+
+    let request = Request::get(url.clone());
+    match url.scheme() {
+        "https" => {
+            let connector = SslConnector::builder(SslMethod::tls())?.build();
+            let ssl = connector.configure()?.into_ssl(&host)?;
+            let mut stream = SslStream::new(ssl, tcp)?;
+            Pin::new(&mut stream)
+                .connect()
+                .await
+                .map_err(io::Error::other)?;
+            async_h1::connect(stream, request).await
+        }
+        _ => async_h1::connect(tcp, request).await,
+    }
+    .map_err(|e| io::Error::other(e.to_string()))
+}
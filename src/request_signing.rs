@@ -0,0 +1,133 @@
+use openssl::hash::MessageDigest;
+use openssl::memcmp;
+use openssl::pkey::PKey;
+use openssl::sign::Signer;
+use tide::http::{Request, StatusCode};
+
+use async_std::io::ReadExt;
+
+use std::fmt::{self, Debug, Formatter};
+
+/// The largest body read to be verified, unless
+/// [`TlsListenerBuilder::request_signing_max_body_size`](crate::TlsListenerBuilder::request_signing_max_body_size)
+/// is set.
+const DEFAULT_MAX_BODY_SIZE: usize = 1024 * 1024;
+
+/// The hash function of the HMAC used to sign request bodies, see
+/// [`TlsListenerBuilder::request_signing_digest`](crate::TlsListenerBuilder::request_signing_digest).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SigningDigest {
+    /// HMAC-SHA256
+    #[default]
+    Sha256,
+    /// HMAC-SHA384
+    Sha384,
+    /// HMAC-SHA512
+    Sha512,
+}
+
+impl SigningDigest {
+    fn message_digest(self) -> MessageDigest {
+        match self {
+            Self::Sha256 => MessageDigest::sha256(),
+            Self::Sha384 => MessageDigest::sha384(),
+            Self::Sha512 => MessageDigest::sha512(),
+        }
+    }
+}
+
+/// The key, header and digest requests are verified with, see
+/// [`TlsListenerBuilder::request_signing_key`](crate::TlsListenerBuilder::request_signing_key).
+#[derive(Clone)]
+pub(crate) struct RequestSigning {
+    pub(crate) key: Option<Vec<u8>>,
+    pub(crate) header: String,
+    pub(crate) digest: SigningDigest,
+    pub(crate) max_body_size: usize,
+}
+
+impl Default for RequestSigning {
+    fn default() -> Self {
+        Self {
+            key: None,
+            header: String::from("X-Signature"),
+            digest: SigningDigest::default(),
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+        }
+    }
+}
+
+impl Debug for RequestSigning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RequestSigning")
+            .field("key", &self.key.as_ref().map(|_| "<redacted>"))
+            .field("header", &self.header)
+            .field("digest", &self.digest)
+            .field("max_body_size", &self.max_body_size)
+            .finish()
+    }
+}
+
+impl RequestSigning {
+    /// Checks the hex encoded HMAC in the signature header against the
+    /// request body, reading the body and putting it back for tide,
+    /// and returns the status to refuse the request with if it does
+    /// not match. The header is checked before any of the body is
+    /// read, and a body over the size limit is refused with a 413
+    /// without reading more of it than the limit. Every request passes
+    /// when no key is set.
+    pub(crate) async fn rejection(
+        &self,
+        req: &mut Request,
+    ) -> tide::http::Result<Option<StatusCode>> {
+        let key = match &self.key {
+            Some(key) => key,
+            None => return Ok(None),
+        };
+        let digest = self.digest.message_digest();
+        let signature = match req.header(self.header.as_str()).and_then(|values| {
+            let value = values.last().as_str().trim();
+            decode_hex(value)
+        }) {
+            Some(signature) if signature.len() == digest.size() => signature,
+            _ => return Ok(Some(StatusCode::Unauthorized)),
+        };
+        if req.len().is_some_and(|len| len > self.max_body_size) {
+            return Ok(Some(StatusCode::PayloadTooLarge));
+        }
+
+        let mut body = Vec::new();
+        req.take_body()
+            .take(self.max_body_size as u64 + 1)
+            .read_to_end(&mut body)
+            .await?;
+        if body.len() > self.max_body_size {
+            return Ok(Some(StatusCode::PayloadTooLarge));
+        }
+        let key = PKey::hmac(key)?;
+        let mut signer = Signer::new(digest, &key)?;
+        signer.update(&body)?;
+        let expected = signer.sign_to_vec()?;
+        req.set_body(body);
+
+        if memcmp::eq(&expected, &signature) {
+            Ok(None)
+        } else {
+            Ok(Some(StatusCode::Unauthorized))
+        }
+    }
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| match pair {
+            [high, low] => Some((hex_digit(*high)? << 4) | hex_digit(*low)?),
+            _ => None,
+        })
+        .collect()
+}
+
+fn hex_digit(digit: u8) -> Option<u8> {
+    (digit as char).to_digit(16).map(|value| value as u8)
+}
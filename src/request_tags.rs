@@ -0,0 +1,27 @@
+use std::ops::Deref;
+
+/// Tags computed for a request by
+/// [`TlsListenerBuilder::request_tagger`](crate::TlsListenerBuilder::request_tagger).
+///
+/// These are stored as a request extension, so middleware and
+/// endpoints can read them with `req.ext::<RequestTags>()`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RequestTags(pub Vec<(String, String)>);
+
+impl RequestTags {
+    /// Returns the value of the first tag with this key, if any.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+impl Deref for RequestTags {
+    type Target = [(String, String)];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
@@ -0,0 +1,88 @@
+use async_std::io;
+use async_std::net::{SocketAddr, TcpListener, TcpStream};
+use socket2::{Domain, Protocol, SockRef, Socket, Type};
+
+/// Socket level settings, applied when binding and to each accepted
+/// stream.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct TcpOptions {
+    pub(crate) nodelay: Option<bool>,
+    pub(crate) ttl: Option<u32>,
+    pub(crate) recv_buffer_size: Option<usize>,
+    pub(crate) send_buffer_size: Option<usize>,
+    pub(crate) reuse_addr: Option<bool>,
+    #[cfg(unix)]
+    pub(crate) reuse_port: Option<bool>,
+}
+
+impl TcpOptions {
+    /// Binds the first of `addrs` that succeeds, like
+    /// [`TcpListener::bind`], setting any socket options that have to
+    /// be in place before `bind(2)`.
+    pub(crate) async fn bind(&self, addrs: &[SocketAddr]) -> io::Result<TcpListener> {
+        if !self.needs_raw_socket() {
+            return TcpListener::bind(addrs).await;
+        }
+
+        let mut last_error = None;
+        for addr in addrs {
+            match self.bind_one(addr) {
+                Ok(listener) => return Ok(listener),
+                Err(error) => last_error = Some(error),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "no addresses to bind to")
+        }))
+    }
+
+    fn needs_raw_socket(&self) -> bool {
+        #[cfg(unix)]
+        let reuse_port = self.reuse_port.is_some();
+        #[cfg(not(unix))]
+        let reuse_port = false;
+
+        self.reuse_addr.is_some() || reuse_port
+    }
+
+    fn bind_one(&self, addr: &SocketAddr) -> io::Result<TcpListener> {
+        let socket = Socket::new(
+            Domain::for_address(*addr),
+            Type::STREAM,
+            Some(Protocol::TCP),
+        )?;
+        if let Some(reuse_addr) = self.reuse_addr {
+            socket.set_reuse_address(reuse_addr)?;
+        }
+        #[cfg(unix)]
+        if let Some(reuse_port) = self.reuse_port {
+            socket.set_reuse_port(reuse_port)?;
+        }
+        socket.bind(&(*addr).into())?;
+        socket.listen(1024)?;
+        Ok(std::net::TcpListener::from(socket).into())
+    }
+
+    /// Applies the per-stream options to an accepted connection.
+    pub(crate) fn apply(&self, stream: &TcpStream) -> io::Result<()> {
+        if let Some(nodelay) = self.nodelay {
+            stream.set_nodelay(nodelay)?;
+        }
+
+        if let Some(ttl) = self.ttl {
+            stream.set_ttl(ttl)?;
+        }
+
+        if self.recv_buffer_size.is_some() || self.send_buffer_size.is_some() {
+            let socket = SockRef::from(stream);
+            if let Some(size) = self.recv_buffer_size {
+                socket.set_recv_buffer_size(size)?;
+            }
+            if let Some(size) = self.send_buffer_size {
+                socket.set_send_buffer_size(size)?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,201 @@
+//! Helpers for testing applications served by a [`TlsListener`] over
+//! real TLS connections, with certificate verification left on.
+//!
+//! These are enabled by the `test-helpers` cargo feature and panic on
+//! failure, so they are only suitable for test code.
+//!
+//! ```rust
+//! use std::io::{Read, Write};
+//! use tide_openssl::test_helpers;
+//!
+//! async_std::task::block_on(async {
+//!     let (cert, key) = test_helpers::self_signed_cert();
+//!     let mut app = tide::new();
+//!     app.at("/").get(|_| async { Ok("hello") });
+//!     let addr = test_helpers::spawn_test_server(app, &cert, &key).await;
+//!
+//!     let connector = test_helpers::make_test_connector(&cert);
+//!     let tcp = std::net::TcpStream::connect(addr).unwrap();
+//!     let mut stream = connector.connect("localhost", tcp).unwrap();
+//!     stream
+//!         .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+//!         .unwrap();
+//!     let mut response = String::new();
+//!     stream.read_to_string(&mut response).unwrap();
+//!     assert!(response.ends_with("hello"));
+//! });
+//! ```
+
+use crate::TlsListener;
+use async_std::net::SocketAddr;
+use async_std::task;
+use openssl::asn1::Asn1Time;
+use openssl::bn::{BigNum, MsbOption};
+use openssl::ec::{EcGroup, EcKey};
+use openssl::hash::MessageDigest;
+use openssl::nid::Nid;
+use openssl::pkey::{PKey, Private};
+use openssl::ssl::{SslAcceptor, SslConnector, SslMethod};
+use openssl::x509::extension::{BasicConstraints, SubjectAlternativeName, SubjectKeyIdentifier};
+use openssl::x509::store::X509StoreBuilder;
+use openssl::x509::{X509NameBuilder, X509};
+use tide::listener::Listener;
+use tide::Server;
+
+use std::ops::Range;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Generates a self-signed ECDSA certificate for `localhost` and
+/// `127.0.0.1`, valid for one day, returning the PEM encoded
+/// certificate and PKCS#8 key. The certificate can be passed to
+/// [`make_test_connector`] as its own CA.
+pub fn self_signed_cert() -> (Vec<u8>, Vec<u8>) {
+    let key = generate_key();
+
+    let mut name = X509NameBuilder::new().unwrap();
+    name.append_entry_by_text("CN", "localhost").unwrap();
+    let name = name.build();
+
+    let mut serial = BigNum::new().unwrap();
+    serial.rand(64, MsbOption::MAYBE_ZERO, false).unwrap();
+
+    let mut cert = X509::builder().unwrap();
+    cert.set_version(2).unwrap();
+    cert.set_serial_number(&serial.to_asn1_integer().unwrap())
+        .unwrap();
+    cert.set_subject_name(&name).unwrap();
+    cert.set_issuer_name(&name).unwrap();
+    cert.set_pubkey(&key).unwrap();
+    cert.set_not_before(&Asn1Time::days_from_now(0).unwrap())
+        .unwrap();
+    cert.set_not_after(&Asn1Time::days_from_now(1).unwrap())
+        .unwrap();
+    let san = SubjectAlternativeName::new()
+        .dns("localhost")
+        .ip("127.0.0.1")
+        .build(&cert.x509v3_context(None, None))
+        .unwrap();
+    cert.append_extension(san).unwrap();
+    cert.sign(&key, MessageDigest::sha256()).unwrap();
+
+    (
+        cert.build().to_pem().unwrap(),
+        key.private_key_to_pem_pkcs8().unwrap(),
+    )
+}
+
+/// Generates a P-256 ECDSA key.
+pub fn generate_key() -> PKey<Private> {
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+    PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap()
+}
+
+/// Issues a certificate for `key`, for the cases [`self_signed_cert`]
+/// does not cover, such as client certificates, certificates issued by
+/// a test CA or expired ones.
+///
+/// `subject` lists the entries of the subject name, such as
+/// `[("O", "Acme"), ("CN", "localhost")]`, and the certificate is
+/// valid over the `days` relative to today, so `-2..-1` is expired.
+/// It is signed by `issuer` and its key, or is a self-signed CA
+/// certificate when `issuer` is `None`.
+pub fn issue_cert(
+    subject: &[(&str, &str)],
+    serial: u32,
+    key: &PKey<Private>,
+    issuer: Option<(&X509, &PKey<Private>)>,
+    days: Range<i64>,
+) -> X509 {
+    let mut name = X509NameBuilder::new().unwrap();
+    for (field, value) in subject {
+        name.append_entry_by_text(field, value).unwrap();
+    }
+    let name = name.build();
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    let day = |days: i64| Asn1Time::from_unix((now + days * 86400) as _).unwrap();
+
+    let mut cert = X509::builder().unwrap();
+    cert.set_version(2).unwrap();
+    cert.set_serial_number(&BigNum::from_u32(serial).unwrap().to_asn1_integer().unwrap())
+        .unwrap();
+    cert.set_subject_name(&name).unwrap();
+    cert.set_pubkey(key).unwrap();
+    cert.set_not_before(&day(days.start)).unwrap();
+    cert.set_not_after(&day(days.end)).unwrap();
+    let signing_key = match issuer {
+        Some((issuer, issuer_key)) => {
+            cert.set_issuer_name(issuer.subject_name()).unwrap();
+            issuer_key
+        }
+        None => {
+            cert.set_issuer_name(&name).unwrap();
+            let basic_constraints = BasicConstraints::new().critical().ca().build().unwrap();
+            cert.append_extension(basic_constraints).unwrap();
+            let ski = SubjectKeyIdentifier::new()
+                .build(&cert.x509v3_context(None, None))
+                .unwrap();
+            cert.append_extension(ski).unwrap();
+            key
+        }
+    };
+    cert.sign(signing_key, MessageDigest::sha256()).unwrap();
+    cert.build()
+}
+
+/// Builds an acceptor from a PEM encoded certificate and key, using
+/// the same Mozilla modern profile a [`TlsListener`] starts out from.
+pub fn make_test_acceptor(cert: &[u8], key: &[u8]) -> SslAcceptor {
+    let cert = X509::from_pem(cert).expect("invalid test certificate");
+    let key = PKey::private_key_from_pem(key).expect("invalid test key");
+
+    let mut acceptor = SslAcceptor::mozilla_modern_v5(SslMethod::tls()).unwrap();
+    acceptor.set_certificate(&cert).unwrap();
+    acceptor.set_private_key(&key).unwrap();
+    acceptor
+        .check_private_key()
+        .expect("test key does not match");
+    acceptor.build()
+}
+
+/// Builds a connector that trusts only the PEM encoded certificate
+/// `ca` and verifies the peer certificate and host name as usual.
+pub fn make_test_connector(ca: &[u8]) -> SslConnector {
+    let mut store = X509StoreBuilder::new().unwrap();
+    for cert in X509::stack_from_pem(ca).expect("invalid test ca") {
+        store.add_cert(cert).unwrap();
+    }
+
+    let mut connector = SslConnector::builder(SslMethod::tls()).unwrap();
+    connector.set_verify_cert_store(store.build()).unwrap();
+    connector.build()
+}
+
+/// Serves `app` over TLS with the given PEM encoded certificate and
+/// key on a random port of `127.0.0.1`, returning the address to
+/// connect to. The server runs until the test process exits.
+pub async fn spawn_test_server<State: Clone + Send + Sync + 'static>(
+    app: Server<State>,
+    cert: &[u8],
+    key: &[u8],
+) -> SocketAddr {
+    let acceptor = make_test_acceptor(cert, key);
+    let tcp = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = tcp.local_addr().unwrap();
+
+    let mut listener = TlsListener::build()
+        .tcp(tcp)
+        .async_configure(move || {
+            let acceptor = acceptor.clone();
+            Box::pin(async move { Ok(acceptor) })
+        })
+        .finish()
+        .unwrap();
+    listener.bind(app).await.unwrap();
+    task::spawn(async move { listener.accept().await });
+
+    addr
+}
@@ -0,0 +1,501 @@
+use crate::{hello, host_config, logging, HostConfig};
+use async_std::io;
+use openssl::bn::BigNum;
+use openssl::dh::Dh;
+use openssl::error::ErrorStack;
+use openssl::pkey::{PKey, Params, Private};
+use openssl::ssl::{
+    AlpnError, ClientHelloResponse, SslAcceptor, SslAcceptorBuilder, SslAlert, SslContextRef,
+    SslFiletype, SslMethod, SslOptions, SslVersion,
+};
+use openssl::x509::store::X509Lookup;
+use openssl::x509::verify::X509VerifyFlags;
+use openssl::x509::X509;
+
+use std::fmt::{self, Debug, Formatter};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Whose key exchange group ordering wins when negotiating the ECDH
+/// curve, see
+/// [`TlsListenerBuilder::ssl_curves_preference`](crate::TlsListenerBuilder::ssl_curves_preference).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CurvesPreference {
+    /// Follow the client's preference list. This is OpenSSL's default.
+    #[default]
+    Client,
+    /// Follow the server's group list, as set by
+    /// [`TlsListenerBuilder::ssl_groups`](crate::TlsListenerBuilder::ssl_groups).
+    /// This also makes the server's cipher suite ordering win, as
+    /// OpenSSL uses one option for both.
+    /// Without an explicit list, `P-256` is preferred on x86, which has
+    /// optimised assembly for it, and `X25519` elsewhere.
+    Server,
+}
+
+impl CurvesPreference {
+    fn default_groups() -> &'static str {
+        if cfg!(any(target_arch = "x86", target_arch = "x86_64")) {
+            "P-256:X25519:P-384"
+        } else {
+            "X25519:P-256:P-384"
+        }
+    }
+}
+
+/// The configuration an acceptor starts out from, before the key
+/// material and the remaining builder options are applied, see
+/// [`TlsListenerBuilder::tls_profile`](crate::TlsListenerBuilder::tls_profile).
+#[derive(Default)]
+pub enum TlsProfile {
+    /// Mozilla's modern profile, version 5: TLS 1.3 only.
+    #[default]
+    MozillaModernV5,
+    /// Mozilla's intermediate profile, version 5: TLS 1.2 and 1.3,
+    /// with a wider list of cipher suites for older clients.
+    MozillaIntermediateV5,
+    /// A pre-configured acceptor builder. It can only be used for a
+    /// single acceptor, so it is not compatible with
+    /// [`TlsListenerBuilder::invalidation_check`](crate::TlsListenerBuilder::invalidation_check).
+    Custom(SslAcceptorBuilder),
+}
+
+impl Debug for TlsProfile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MozillaModernV5 => write!(f, "MozillaModernV5"),
+            Self::MozillaIntermediateV5 => write!(f, "MozillaIntermediateV5"),
+            Self::Custom(_) => write!(f, "Custom(..)"),
+        }
+    }
+}
+
+/// A [`TlsProfile`] as kept by the listener, with a custom builder
+/// taken out when the acceptor is built. Clones of a custom profile
+/// share the one builder.
+#[derive(Clone, Default)]
+pub(crate) enum BaseProfile {
+    #[default]
+    MozillaModernV5,
+    MozillaIntermediateV5,
+    Custom(Arc<Mutex<Option<SslAcceptorBuilder>>>),
+}
+
+impl From<TlsProfile> for BaseProfile {
+    fn from(profile: TlsProfile) -> Self {
+        match profile {
+            TlsProfile::MozillaModernV5 => Self::MozillaModernV5,
+            TlsProfile::MozillaIntermediateV5 => Self::MozillaIntermediateV5,
+            TlsProfile::Custom(builder) => Self::Custom(Arc::new(Mutex::new(Some(builder)))),
+        }
+    }
+}
+
+impl Debug for BaseProfile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MozillaModernV5 => write!(f, "MozillaModernV5"),
+            Self::MozillaIntermediateV5 => write!(f, "MozillaIntermediateV5"),
+            Self::Custom(_) => write!(f, "Custom(..)"),
+        }
+    }
+}
+
+/// A certificate or private key, as a path to a PEM file or as
+/// in-memory PEM or DER bytes.
+#[derive(Clone)]
+pub(crate) enum Material {
+    Path(PathBuf),
+    Pem(Vec<u8>),
+    Der(Vec<u8>),
+}
+
+impl Material {
+    fn certificate(&self) -> io::Result<X509> {
+        match self {
+            Self::Path(path) => X509::from_pem(&std::fs::read(path)?),
+            Self::Pem(pem) => X509::from_pem(pem),
+            Self::Der(der) => X509::from_der(der),
+        }
+        .map_err(io::Error::other)
+    }
+
+    fn private_key(&self) -> io::Result<PKey<Private>> {
+        match self {
+            Self::Path(path) => PKey::private_key_from_pem(&std::fs::read(path)?),
+            Self::Pem(pem) => PKey::private_key_from_pem(pem),
+            Self::Der(der) => PKey::private_key_from_der(der),
+        }
+        .map_err(io::Error::other)
+    }
+
+    fn dh_params(&self) -> io::Result<Dh<Params>> {
+        match self {
+            Self::Path(path) => Dh::params_from_pem(&std::fs::read(path)?),
+            Self::Pem(pem) => Dh::params_from_pem(pem),
+            Self::Der(der) => Dh::params_from_der(der),
+        }
+        .map_err(io::Error::other)
+    }
+}
+
+impl Debug for Material {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Path(path) => f.debug_tuple("Path").field(path).finish(),
+            Self::Pem(_) => write!(f, "Pem(..)"),
+            Self::Der(_) => write!(f, "Der(..)"),
+        }
+    }
+}
+
+/// Diffie-Hellman parameters for the TLS 1.2 DHE cipher suites.
+#[derive(Debug, Clone)]
+pub(crate) enum DhParams {
+    /// The 2048-bit MODP group from RFC 3526.
+    Rfc3526,
+    Custom(Material),
+}
+
+impl DhParams {
+    fn load(&self) -> io::Result<Dh<Params>> {
+        match self {
+            Self::Rfc3526 => {
+                let prime = BigNum::get_rfc3526_prime_2048()?;
+                Ok(Dh::from_pqg(prime, None, BigNum::from_u32(2)?)?)
+            }
+            Self::Custom(material) => material.dh_params(),
+        }
+    }
+}
+
+/// See [`TlsListenerBuilder::ssl_context_hook`](crate::TlsListenerBuilder::ssl_context_hook).
+#[derive(Clone)]
+pub(crate) struct ContextHook(pub(crate) Arc<dyn Fn(&SslContextRef) + Send + Sync + 'static>);
+
+impl Debug for ContextHook {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "ContextHook(..)")
+    }
+}
+
+/// The most intermediate certificates the served chain may hold, see
+/// [`TlsListenerBuilder::max_cert_chain_depth_validate`](crate::TlsListenerBuilder::max_cert_chain_depth_validate).
+const DEFAULT_MAX_CERT_CHAIN_DEPTH: u32 = 5;
+
+/// Settings applied to the [`SslAcceptorBuilder`] after the
+/// certificate and key have been loaded.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TlsAcceptorOptions {
+    pub(crate) alpn_protocols: Option<Vec<Vec<u8>>>,
+    pub(crate) curves_preference: CurvesPreference,
+    pub(crate) groups: Option<String>,
+    pub(crate) secondary_cert: Option<Material>,
+    pub(crate) secondary_key: Option<Material>,
+    pub(crate) dh_params: Option<DhParams>,
+    pub(crate) profile: BaseProfile,
+    pub(crate) client_crl: Option<PathBuf>,
+    pub(crate) client_crl_check_all: bool,
+    pub(crate) disable_renegotiation: bool,
+    pub(crate) tls_record_split: Option<bool>,
+    pub(crate) max_cert_chain_depth: Option<u32>,
+    pub(crate) allowed_tls_extensions: Option<Vec<u16>>,
+    pub(crate) allowed_ja3_fingerprints: Option<Vec<String>>,
+    pub(crate) context_hook: Option<ContextHook>,
+    pub(crate) cipher_list: Option<String>,
+    pub(crate) ciphersuites: Option<String>,
+    pub(crate) min_protocol_version: Option<SslVersion>,
+    pub(crate) max_protocol_version: Option<SslVersion>,
+    pub(crate) host_configs: Vec<(String, HostConfig)>,
+}
+
+impl TlsAcceptorOptions {
+    /// The Mozilla profile the acceptor starts out from, before the
+    /// key material and the remaining options are applied.
+    pub(crate) fn base_acceptor(&self) -> io::Result<SslAcceptorBuilder> {
+        match &self.profile {
+            BaseProfile::MozillaModernV5 => SslAcceptor::mozilla_modern_v5(SslMethod::tls()),
+            BaseProfile::MozillaIntermediateV5 => {
+                logging::warning!(
+                    "legacy client support enables TLS 1.2 and weaker cipher suites, reducing security"
+                );
+                SslAcceptor::mozilla_intermediate_v5(SslMethod::tls())
+            }
+            BaseProfile::Custom(builder) => {
+                let builder = builder.lock().unwrap_or_else(|e| e.into_inner()).take();
+                return builder.ok_or_else(|| {
+                    io::Error::other("a custom tls profile can only build a single acceptor")
+                });
+            }
+        }
+        .map_err(io::Error::other)
+    }
+
+    /// Whether the profile can only build a single acceptor, as with a
+    /// custom builder.
+    pub(crate) fn builds_once(&self) -> bool {
+        matches!(self.profile, BaseProfile::Custom(_))
+    }
+
+    pub(crate) fn apply(&self, acceptor: &mut SslAcceptorBuilder) -> io::Result<()> {
+        self.apply_to_host(acceptor, self.alpn_protocols.as_deref())?;
+
+        match (&self.secondary_cert, &self.secondary_key) {
+            (Some(cert), Some(key)) => {
+                // OpenSSL keeps one certificate per key type, so a cert of
+                // a different type than the primary fills a second slot
+                let (cert, key) = (cert.certificate()?, key.private_key()?);
+                acceptor
+                    .set_certificate(&cert)
+                    .and_then(|_| acceptor.set_private_key(&key))
+                    .and_then(|_| acceptor.check_private_key())
+                    .map_err(io::Error::other)?;
+            }
+            (None, None) => {}
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "secondary cert and secondary key must be provided together",
+                ))
+            }
+        }
+
+        host_config::set_servername_callback(acceptor, &self.host_configs, self)?;
+
+        if self.allowed_tls_extensions.is_some() || self.allowed_ja3_fingerprints.is_some() {
+            // OpenSSL holds a single ClientHello callback, so every check
+            // on the ClientHello runs from this one
+            let allowed_extensions = self.allowed_tls_extensions.clone();
+            let allowed_fingerprints = self.allowed_ja3_fingerprints.clone();
+            acceptor.set_client_hello_callback(move |ssl, alert| {
+                let client_hello = match hello::take_read(ssl)
+                    .as_deref()
+                    .and_then(hello::ClientHello::parse)
+                {
+                    Some(client_hello) => client_hello,
+                    None => {
+                        logging::warning!("unable to parse client hello, closing connection");
+                        *alert = SslAlert::DECODE_ERROR;
+                        return Err(ErrorStack::get());
+                    }
+                };
+
+                if let Some(allowed) = &allowed_extensions {
+                    let unexpected = client_hello.extensions.iter().find(|&&extension| {
+                        !hello::is_grease(extension) && !allowed.contains(&extension)
+                    });
+                    if let Some(extension) = unexpected {
+                        logging::warning!("client hello has a disallowed extension, closing connection", {
+                            extension: extension
+                        });
+                        *alert = SslAlert::ILLEGAL_PARAMETER;
+                        return Err(ErrorStack::get());
+                    }
+                }
+
+                if let Some(allowed) = &allowed_fingerprints {
+                    let fingerprint = client_hello.ja3()?;
+                    if !allowed.contains(&fingerprint) {
+                        logging::warning!("client hello has an unknown JA3 fingerprint, closing connection", {
+                            ja3: fingerprint
+                        });
+                        // the openssl crate has no handshake_failure alert
+                        *alert = SslAlert::ILLEGAL_PARAMETER;
+                        return Err(ErrorStack::get());
+                    }
+                }
+
+                Ok(ClientHelloResponse::SUCCESS)
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Applies the options that the contexts of
+    /// [`HostConfig`](crate::HostConfig)s share with the listener's:
+    /// everything but its own key material and the callbacks that run
+    /// before the host name is known.
+    pub(crate) fn apply_to_host(
+        &self,
+        acceptor: &mut SslAcceptorBuilder,
+        alpn_protocols: Option<&[Vec<u8>]>,
+    ) -> io::Result<()> {
+        // overrides of the profile, applied first so that the checks
+        // below see the versions in effect
+        if let Some(cipher_list) = &self.cipher_list {
+            acceptor.set_cipher_list(cipher_list)?;
+        }
+        if let Some(ciphersuites) = &self.ciphersuites {
+            acceptor.set_ciphersuites(ciphersuites)?;
+        }
+        if let Some(version) = self.min_protocol_version {
+            acceptor.set_min_proto_version(Some(version))?;
+        }
+        if let Some(version) = self.max_protocol_version {
+            acceptor.set_max_proto_version(Some(version))?;
+        }
+
+        match &self.dh_params {
+            Some(dh_params) => {
+                let dh = dh_params.load()?;
+                acceptor.set_tmp_dh(&dh)?;
+            }
+            // the intermediate profile installs the ffdhe2048 group and a
+            // custom one may have its own, only the modern profile has
+            // none when its minimum version is lowered
+            None if matches!(self.profile, BaseProfile::MozillaModernV5)
+                && acceptor.min_proto_version() != Some(SslVersion::TLS1_3) =>
+            {
+                logging::warning!(
+                    "TLS 1.2 is enabled without DH parameters, DHE cipher suites will not be negotiated"
+                );
+            }
+            None => {}
+        }
+
+        if let Some(crl) = &self.client_crl {
+            // read on every build, so a rebuilt acceptor sees a new CRL
+            let filetype = if std::fs::read(crl)?.starts_with(b"-----BEGIN") {
+                SslFiletype::PEM
+            } else {
+                SslFiletype::ASN1
+            };
+            acceptor
+                .cert_store_mut()
+                .add_lookup(X509Lookup::file())?
+                .load_crl_file(crl, filetype)?;
+
+            let mut flags = X509VerifyFlags::CRL_CHECK;
+            if self.client_crl_check_all {
+                flags |= X509VerifyFlags::CRL_CHECK_ALL;
+            }
+            acceptor.verify_param_mut().set_flags(flags)?;
+        }
+
+        if self.disable_renegotiation {
+            if acceptor.min_proto_version() == Some(SslVersion::TLS1_3) {
+                logging::warning!(
+                    "renegotiation is disabled, but only TLS 1.3 is enabled, which has no renegotiation"
+                );
+            }
+            acceptor.set_options(SslOptions::NO_RENEGOTIATION);
+        }
+
+        // the openssl crate clears SSL_OP_DONT_INSERT_EMPTY_FRAGMENTS
+        // on every context it creates, so the split is on unless turned
+        // off here or by a custom profile
+        match self.tls_record_split {
+            Some(true) => acceptor.clear_options(SslOptions::DONT_INSERT_EMPTY_FRAGMENTS),
+            Some(false) => acceptor.set_options(SslOptions::DONT_INSERT_EMPTY_FRAGMENTS),
+            None => SslOptions::empty(),
+        };
+
+        self.set_alpn_protocols(acceptor, alpn_protocols)?;
+
+        let groups = match (self.curves_preference, &self.groups) {
+            (_, Some(groups)) => Some(groups.as_str()),
+            (CurvesPreference::Server, None) => Some(CurvesPreference::default_groups()),
+            (CurvesPreference::Client, None) => None,
+        };
+        if let Some(groups) = groups {
+            acceptor.set_groups_list(groups)?;
+        }
+        if self.curves_preference == CurvesPreference::Server {
+            // OpenSSL has no separate option for groups, this one covers
+            // both groups and ciphers
+            acceptor.set_options(SslOptions::CIPHER_SERVER_PREFERENCE);
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the served chain holds no more than the maximum
+    /// number of certificates after the leaf.
+    pub(crate) fn check_chain_depth(&self, intermediates: usize) -> io::Result<()> {
+        let max_depth = self
+            .max_cert_chain_depth
+            .unwrap_or(DEFAULT_MAX_CERT_CHAIN_DEPTH);
+        if intermediates > max_depth as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "certificate chain has {} intermediate certificates, more than the maximum of {}",
+                    intermediates, max_depth
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Hands the context of a freshly built acceptor to the hook, if
+    /// one is set.
+    pub(crate) fn inspect(&self, acceptor: &SslAcceptor) {
+        if let Some(ContextHook(hook)) = &self.context_hook {
+            hook(acceptor.context());
+        }
+    }
+
+    /// Advertises `protocols` during ALPN negotiation, or the default
+    /// protocols if `None`.
+    pub(crate) fn set_alpn_protocols(
+        &self,
+        acceptor: &mut SslAcceptorBuilder,
+        protocols: Option<&[Vec<u8>]>,
+    ) -> io::Result<()> {
+        if let Some(wire) = alpn_wire_format(protocols)? {
+            acceptor.set_alpn_select_callback(move |_, client| {
+                select_protocol(&wire, client).ok_or(AlpnError::NOACK)
+            });
+        }
+        Ok(())
+    }
+}
+
+/// The protocols to advertise, in the length-prefixed ALPN wire
+/// format.
+fn alpn_wire_format(protocols: Option<&[Vec<u8>]>) -> io::Result<Option<Vec<u8>>> {
+    let protocols = match protocols {
+        Some(protocols) => protocols.to_vec(),
+        None if cfg!(feature = "h2") => vec![b"h2".to_vec(), b"http/1.1".to_vec()],
+        None => return Ok(None),
+    };
+
+    let mut wire = Vec::new();
+    for protocol in protocols {
+        if protocol.is_empty() || protocol.len() > 255 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "alpn protocol names must be between 1 and 255 bytes",
+            ));
+        }
+        wire.push(protocol.len() as u8);
+        wire.extend(protocol);
+    }
+    Ok(Some(wire))
+}
+
+/// Picks the first server protocol that the client also offered,
+/// returning the matching slice of the client's list. This is what
+/// [`openssl::ssl::select_next_proto`] does, but without tying the
+/// result to the lifetime of the server list.
+fn select_protocol<'a>(server: &[u8], client: &'a [u8]) -> Option<&'a [u8]> {
+    let mut server = server;
+    while let Some((&len, rest)) = server.split_first() {
+        let (protocol, rest) = rest.split_at(len as usize);
+        server = rest;
+
+        let mut offered = client;
+        while let Some((&len, rest)) = offered.split_first() {
+            if rest.len() < len as usize {
+                return None;
+            }
+            let (candidate, rest) = rest.split_at(len as usize);
+            if candidate == protocol {
+                return Some(candidate);
+            }
+            offered = rest;
+        }
+    }
+    None
+}
@@ -0,0 +1,120 @@
+use crate::tls_listener::is_transient_error;
+use crate::{error_log, ConnectionOptions, TcpOptions, TlsSessionSummary};
+use async_std::net::{SocketAddr, TcpListener, TcpStream};
+use async_std::{io, task};
+use async_std_openssl::SslStream;
+use futures_util::future;
+use futures_util::stream::{self, Stream, StreamExt};
+use openssl::ssl::{Ssl, SslAcceptor};
+
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+/// How many handshakes [`incoming`] runs at once, so that a slow
+/// client does not hold up the ones behind it.
+const CONCURRENT_HANDSHAKES: usize = 64;
+
+/// How long a handshake may take before the connection is dropped, so
+/// that clients that connect and send nothing cannot take up every
+/// handshake slot.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long to wait before accepting again after an error that is not
+/// down to a single connection, such as running out of file
+/// descriptors, which accepting straight away would run into again.
+const ACCEPT_ERROR_DELAY: Duration = Duration::from_millis(500);
+
+/// The connections accepted on `listener`, once their handshake has
+/// completed, see
+/// [`TlsListener::into_stream`](crate::TlsListener::into_stream).
+pub(crate) fn incoming(
+    listener: Arc<TcpListener>,
+    acceptor: Arc<RwLock<SslAcceptor>>,
+    tcp_options: TcpOptions,
+    options: Arc<ConnectionOptions>,
+) -> impl Stream<Item = io::Result<(SslStream<TcpStream>, SocketAddr)>> + Send + 'static {
+    stream::unfold(listener, |listener| async move {
+        let accepted = listener.accept().await;
+        if matches!(&accepted, Err(e) if !is_transient_error(e)) {
+            task::sleep(ACCEPT_ERROR_DELAY).await;
+        }
+        Some((accepted, listener))
+    })
+    .map(move |accepted| {
+        let acceptor = acceptor.read().unwrap_or_else(|e| e.into_inner()).clone();
+        handshake(accepted, acceptor, tcp_options, options.clone())
+    })
+    .buffer_unordered(CONCURRENT_HANDSHAKES)
+    .filter_map(future::ready)
+}
+
+/// Runs the handshake on an accepted connection, returning `None` for
+/// connections that are filtered out or fail the handshake, which are
+/// logged like those served by tide.
+async fn handshake(
+    accepted: io::Result<(TcpStream, SocketAddr)>,
+    acceptor: SslAcceptor,
+    tcp_options: TcpOptions,
+    options: Arc<ConnectionOptions>,
+) -> Option<io::Result<(SslStream<TcpStream>, SocketAddr)>> {
+    let (stream, peer_addr) = match accepted {
+        Ok(accepted) => accepted,
+        Err(ref e) if is_transient_error(e) => return None,
+        Err(e) => return Some(Err(e)),
+    };
+    options.stats.accepted();
+    if !(options.ip_filter)(peer_addr.ip()) {
+        options.stats.rejected();
+        return None;
+    }
+    if let Err(e) = tcp_options.apply(&stream) {
+        return Some(Err(e));
+    }
+
+    let accepted_at = Instant::now();
+    let connection_id = error_log::next_connection_id();
+    let format = options.error_log_format;
+    let ssl_stream = Ssl::new(acceptor.context()).and_then(|ssl| SslStream::new(ssl, stream));
+    let mut ssl_stream = match ssl_stream {
+        Ok(s) => s,
+        Err(e) => {
+            format.log("ssl error", &e, Some(peer_addr), connection_id);
+            options.stats.handshake_failed();
+            return None;
+        }
+    };
+    let accepted =
+        async_std::future::timeout(HANDSHAKE_TIMEOUT, Pin::new(&mut ssl_stream).accept());
+    match accepted.await {
+        Ok(Ok(())) => {}
+        Ok(Err(tls_error)) => {
+            format.log("tls error", &tls_error, Some(peer_addr), connection_id);
+            options.stats.handshake_failed();
+            return None;
+        }
+        Err(timeout) => {
+            format.log("tls error", &timeout, Some(peer_addr), connection_id);
+            options.stats.handshake_failed();
+            return None;
+        }
+    }
+    let session_reused = ssl_stream.ssl().session_reused();
+    options.stats.handshake_succeeded(session_reused);
+    options.log_slow_handshake(ssl_stream.ssl(), Some(peer_addr), accepted_at.elapsed());
+
+    if let Some(callback) = &options.session_reuse_callback {
+        callback(peer_addr, session_reused);
+    }
+    if !options.allows_peer(ssl_stream.ssl()) {
+        options.stats.rejected();
+        return None;
+    }
+
+    let summary = TlsSessionSummary::new(ssl_stream.ssl());
+    *options
+        .last_session
+        .write()
+        .unwrap_or_else(|e| e.into_inner()) = Some(summary);
+    Some(Ok((ssl_stream, peer_addr)))
+}
@@ -1,36 +1,59 @@
-use crate::{TcpConnection, TlsListenerBuilder, TlsListenerConfig};
+use crate::typestate::Yes;
+use crate::{cert_provider, error_log, hello, logging, tls_incoming};
+use crate::{
+    AcceptorSource, Connection, ConnectionAudit, ConnectionOptions, HealthCheck, InvalidationCheck,
+    ProtocolBuffer, RecordingStream, TcpOptions, TlsAcceptorOptions, TlsListenerBuilder,
+    TlsSessionSummary,
+};
+#[cfg(feature = "remote-config")]
+use crate::{AppliedDocument, RemoteConfig};
+use async_dup::Mutex;
 use async_std_openssl::SslStream;
-use async_std_openssl::SslStreamWrapper;
+use futures_util::future::{select_all, BoxFuture};
 
-use openssl::ssl::{Ssl, SslAcceptor, SslFiletype, SslMethod};
+use openssl::ssl::{Ssl, SslAcceptor};
 use tide::listener::ListenInfo;
 use tide::listener::{Listener, ToListener};
 use tide::Server;
 
-use async_std::net::{TcpListener, TcpStream};
+use async_std::io::{Read, Write};
+use async_std::net::{SocketAddr, TcpListener, TcpStream};
+#[cfg(feature = "unix")]
+use async_std::os::unix::net::UnixListener;
 use async_std::prelude::*;
 use async_std::{io, task};
 
 use std::fmt::{self, Debug, Display, Formatter};
 use std::pin::Pin;
-use std::time::Duration;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 
 /// The primary type for this crate
 pub struct TlsListener<State> {
-    connection: TcpConnection,
-    config: TlsListenerConfig,
-    acceptor: Option<SslAcceptor>,
+    connection: Connection,
+    config: Arc<AcceptorSource>,
+    acceptor: Option<Arc<RwLock<SslAcceptor>>>,
     server: Option<Server<State>>,
-    tcp_nodelay: Option<bool>,
-    tcp_ttl: Option<u32>,
+    tcp_options: TcpOptions,
+    acceptor_options: Arc<TlsAcceptorOptions>,
+    connection_options: Arc<ConnectionOptions>,
+    invalidation_check: Option<(InvalidationCheck, Duration)>,
+    health_check: HealthCheck,
+    #[cfg(feature = "remote-config")]
+    remote_config: Option<RemoteConfig>,
+    #[cfg(feature = "remote-config")]
+    applied_document: AppliedDocument,
+    started: bool,
 }
 
 impl<State> Debug for TlsListener<State> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        f.debug_struct("TlsListener")
-            .field(&"connection", &self.connection)
+        let mut debug = f.debug_struct("TlsListener");
+        debug
+            .field("connection", &self.connection)
             .field(
-                &"acceptor",
+                "acceptor",
                 if self.acceptor.is_some() {
                     &"Some(SslAcceptor)"
                 } else {
@@ -38,35 +61,71 @@ impl<State> Debug for TlsListener<State> {
                 },
             )
             .field(
-                &"server",
+                "server",
                 if self.server.is_some() {
                     &"Some(Server<State>)"
                 } else {
                     &"None"
                 },
             )
-            .field("tcp_ttl", &self.tcp_ttl)
-            .field("tcp_nodelay", &self.tcp_nodelay)
-            .finish()
+            .field("tcp_options", &self.tcp_options)
+            .field("acceptor_options", &self.acceptor_options)
+            .field("connection_options", &self.connection_options)
+            .field(
+                "invalidation_check",
+                &self
+                    .invalidation_check
+                    .as_ref()
+                    .map(|(_, interval)| interval),
+            )
+            .field("health_check", &self.health_check);
+        #[cfg(feature = "remote-config")]
+        debug
+            .field("remote_config", &self.remote_config)
+            .field("applied_document", &self.applied_document);
+        debug.finish()
     }
 }
 
 impl<State> TlsListener<State> {
     pub(crate) fn new(
-        connection: TcpConnection,
-        config: TlsListenerConfig,
-        tcp_nodelay: Option<bool>,
-        tcp_ttl: Option<u32>,
+        connection: Connection,
+        config: AcceptorSource,
+        acceptor: Option<SslAcceptor>,
+        tcp_options: TcpOptions,
+        acceptor_options: TlsAcceptorOptions,
+        connection_options: ConnectionOptions,
+        invalidation_check: Option<(InvalidationCheck, Duration)>,
     ) -> Self {
         Self {
             connection,
-            config,
-            acceptor: None,
+            config: Arc::new(config),
+            acceptor: acceptor.map(|acceptor| Arc::new(RwLock::new(acceptor))),
             server: None,
-            tcp_nodelay,
-            tcp_ttl,
+            tcp_options,
+            acceptor_options: Arc::new(acceptor_options),
+            connection_options: Arc::new(connection_options),
+            invalidation_check,
+            health_check: HealthCheck::default(),
+            #[cfg(feature = "remote-config")]
+            remote_config: None,
+            #[cfg(feature = "remote-config")]
+            applied_document: AppliedDocument::default(),
+            started: false,
         }
     }
+
+    pub(crate) fn with_health_check(mut self, health_check: HealthCheck) -> Self {
+        self.health_check = health_check;
+        self
+    }
+
+    #[cfg(feature = "remote-config")]
+    pub(crate) fn with_remote_config(mut self, remote_config: Option<RemoteConfig>) -> Self {
+        self.remote_config = remote_config;
+        self
+    }
+
     /// The primary entrypoint to create a TlsListener. See
     /// [TlsListenerBuilder](crate::TlsListenerBuilder) for more
     /// configuration options.
@@ -85,27 +144,117 @@ impl<State> TlsListener<State> {
         TlsListenerBuilder::new()
     }
 
+    /// The protocol version and cipher suite negotiated by the most
+    /// recent successful handshake, or `None` if no client has
+    /// completed one yet.
+    pub fn tls_summary(&self) -> Option<TlsSessionSummary> {
+        let last_session = &self.connection_options.last_session;
+        last_session
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// A handle to the acceptor that new connections are accepted
+    /// with, or `None` before it is built. Acceptors from
+    /// [`TlsListenerBuilder::async_configure`] are only built when the
+    /// listener is bound. A rebuilt acceptor, as from
+    /// [`TlsListenerBuilder::invalidation_check`], is not reflected in
+    /// handles taken earlier.
+    pub fn clone_acceptor(&self) -> Option<SslAcceptor> {
+        let acceptor = self.acceptor.as_ref()?;
+        Some(acceptor.read().unwrap_or_else(|e| e.into_inner()).clone())
+    }
+
+    /// Turns the listener into a stream of TLS connections, each with
+    /// its handshake completed, for serving them with something other
+    /// than tide, such as a custom protocol or a TLS proxy. The
+    /// listener is bound first if [`Listener::bind`] has not been
+    /// called yet, in which case no tide server is needed.
+    ///
+    /// Handshakes run concurrently, and connections are yielded in the
+    /// order their handshakes complete. Connections that are filtered
+    /// out, fail their handshake or do not complete it within ten
+    /// seconds are logged and skipped. Accept errors are yielded, after
+    /// a short pause unless they only concern the one connection. The
+    /// settings that apply once a connection is served over HTTP, and
+    /// the audit log, have no effect here. Settings that inspect the
+    /// raw handshake bytes, such as
+    /// [`TlsListenerBuilder::tls_fingerprint_allowlist`] or
+    /// [`TlsListenerBuilder::assert_no_compression`], are not
+    /// supported and make this return an error, as does a listener on
+    /// a unix domain socket.
+    ///
+    /// ```rust,no_run
+    /// # use async_std::prelude::*;
+    /// # use tide_openssl::TlsListener;
+    /// # async_std::task::block_on(async {
+    /// let listener = TlsListener::<()>::build()
+    ///     .addrs("localhost:4433")
+    ///     .cert("./tls/localhost-4433.cert")
+    ///     .key("./tls/localhost-4433.key")
+    ///     .finish()?;
+    /// let mut connections = Box::pin(listener.into_stream().await?);
+    /// while let Some(connection) = connections.next().await {
+    ///     let (stream, peer_addr) = connection?;
+    ///     // hand `stream` over to a protocol implementation
+    /// }
+    /// # std::io::Result::Ok(()) });
+    /// ```
+    pub async fn into_stream(
+        mut self,
+    ) -> io::Result<
+        impl Stream<Item = io::Result<(SslStream<TcpStream>, SocketAddr)>> + Send + 'static,
+    > {
+        if self.connection_options.record_client_hello {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "into_stream does not support inspecting the client hello",
+            ));
+        }
+        if self.connection_options.assert_no_compression {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "into_stream does not support assert_no_compression",
+            ));
+        }
+        #[cfg(feature = "debug")]
+        if self.connection_options.handshake_dump_dir.is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "into_stream does not support debug_handshake_dump",
+            ));
+        }
+        if self.connection.transport() != "tcp" {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "into_stream only supports tcp listeners",
+            ));
+        }
+        self.start().await?;
+        let listener = self
+            .tcp()
+            .cloned()
+            .ok_or_else(|| io::Error::other("into_stream - listener"))?;
+        let acceptor = self
+            .acceptor
+            .clone()
+            .ok_or_else(|| io::Error::other("into_stream - acceptor"))?;
+        Ok(tls_incoming::incoming(
+            listener,
+            acceptor,
+            self.tcp_options,
+            self.connection_options.clone(),
+        ))
+    }
+
     async fn configure(&mut self) -> io::Result<()> {
         // TODO: Support ServerConfig and CustomTlsAcceptor
-        match &self.config {
-            TlsListenerConfig::Paths { cert, key } => {
-                let mut acceptor = SslAcceptor::mozilla_modern_v5(SslMethod::tls())
-                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
-                acceptor
-                    .set_private_key_file(key, SslFiletype::PEM)
-                    .and_then(|_| acceptor.set_certificate_chain_file(cert))
-                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
-                self.acceptor = Some(acceptor.build());
-
-                Ok(())
-            }
-            _ => {
-                return Err(io::Error::new(
-                    io::ErrorKind::InvalidInput,
-                    "need exactly one of cert + key",
-                ))
-            }
+        if self.acceptor.is_none() {
+            let acceptor = self.config.build_acceptor(&self.acceptor_options).await?;
+            self.acceptor = Some(Arc::new(RwLock::new(acceptor)));
         }
+        Ok(())
 
         // self.config = match std::mem::take(&mut self.config) {
         //     TlsListenerConfig::Paths { cert, key } => {
@@ -136,6 +285,58 @@ impl<State> TlsListener<State> {
         // };
     }
 
+    /// Polls `check` every `interval` and rebuilds the acceptor from
+    /// the config, and any applied remote configuration, whenever it
+    /// returns true. The task ends once the listener, and with it the
+    /// acceptor, is dropped.
+    fn spawn_invalidation_check(
+        &self,
+        acceptor: &Arc<RwLock<SslAcceptor>>,
+        check: InvalidationCheck,
+        interval: Duration,
+    ) {
+        let acceptor = Arc::downgrade(acceptor);
+        let config = self.config.clone();
+        let acceptor_options = self.acceptor_options.clone();
+        #[cfg(feature = "remote-config")]
+        let applied_document = self.applied_document.clone();
+        task::spawn(async move {
+            loop {
+                task::sleep(interval).await;
+                let acceptor = match acceptor.upgrade() {
+                    Some(acceptor) => acceptor,
+                    None => break,
+                };
+                if !check() {
+                    continue;
+                }
+
+                // connections that already hold the old acceptor keep it
+                // until they finish, but new handshakes only see the new
+                // context and cannot resume sessions from the old one
+                #[cfg(feature = "remote-config")]
+                let rebuilt = applied_document
+                    .rebuild(&acceptor, &config, &acceptor_options)
+                    .await;
+                #[cfg(not(feature = "remote-config"))]
+                let rebuilt = config
+                    .build_acceptor(&acceptor_options)
+                    .await
+                    .map(|rebuilt| *acceptor.write().unwrap_or_else(|e| e.into_inner()) = rebuilt);
+                match rebuilt {
+                    Ok(()) => {
+                        logging::warning!("tls acceptor invalidated and rebuilt");
+                    }
+                    Err(error) => {
+                        logging::error!("unable to rebuild invalidated tls acceptor", {
+                            error: error.to_string()
+                        });
+                    }
+                }
+            }
+        });
+    }
+
     // fn acceptor(&self) -> Option<&Arc<dyn CustomTlsAcceptor>> {
     //     match self.config {
     //         TlsListenerConfig::Acceptor(ref a) => Some(a),
@@ -143,62 +344,305 @@ impl<State> TlsListener<State> {
     //     }
     // }
 
-    fn tcp(&self) -> Option<&TcpListener> {
+    /// Builds the acceptor, binds the socket and starts the background
+    /// checks, unless the listener has been started already.
+    async fn start(&mut self) -> io::Result<()> {
+        if self.started {
+            return Ok(());
+        }
+        self.configure().await?;
+        if let (Some(acceptor), Some((check, interval))) =
+            (&self.acceptor, &self.invalidation_check)
+        {
+            self.spawn_invalidation_check(acceptor, check.clone(), *interval);
+        }
+        if let (Some(acceptor), AcceptorSource::Provider(source)) = (&self.acceptor, &*self.config)
+        {
+            cert_provider::spawn_refresh(
+                source.clone(),
+                Arc::downgrade(acceptor),
+                self.acceptor_options.clone(),
+                #[cfg(feature = "remote-config")]
+                self.applied_document.clone(),
+            );
+        }
+        #[cfg(feature = "remote-config")]
+        if let (Some(acceptor), Some(remote_config)) = (&self.acceptor, &self.remote_config) {
+            remote_config.spawn(
+                Arc::downgrade(acceptor),
+                self.config.clone(),
+                self.acceptor_options.clone(),
+                self.applied_document.clone(),
+            );
+        }
+        self.connect().await?;
+        if let (Some(acceptor), Some(tcp)) = (&self.acceptor, self.tcp()) {
+            self.health_check.spawn(
+                tcp.local_addr()?,
+                self.connection_options.health_probes.clone(),
+                Arc::downgrade(acceptor),
+            );
+        }
+        if let Some(acceptor) = &self.acceptor {
+            let options = &self.connection_options;
+            options
+                .stats
+                .spawn(options.stats_interval, Arc::downgrade(acceptor));
+        }
+        self.started = true;
+        Ok(())
+    }
+
+    fn tcp(&self) -> Option<&Arc<TcpListener>> {
         match self.connection {
-            TcpConnection::Connected(ref t) => Some(t),
+            Connection::Tcp(ref t) => Some(t),
             _ => None,
         }
     }
 
     async fn connect(&mut self) -> io::Result<()> {
-        if let TcpConnection::Addrs(addrs) = &self.connection {
-            let tcp = TcpListener::bind(&addrs[..]).await?;
-            self.connection = TcpConnection::Connected(tcp);
+        match &self.connection {
+            Connection::Addrs(addrs) => {
+                let tcp = self.tcp_options.bind(addrs).await?;
+                self.connection = Connection::Tcp(Arc::new(tcp));
+            }
+            #[cfg(feature = "unix")]
+            Connection::UnixPath(path) => {
+                let unix = UnixListener::bind(path).await?;
+                self.connection = Connection::Unix(Arc::new(unix));
+            }
+            _ => {}
         }
         Ok(())
     }
 }
 
-fn handle_tls<State: Clone + Send + Sync + 'static>(
+/// Serves a connection on its own task. Unix domain sockets have no
+/// socket addresses to pass on.
+fn handle_tls<State, S>(
     app: Server<State>,
-    stream: TcpStream,
+    stream: S,
+    local_addr: Option<SocketAddr>,
+    peer_addr: Option<SocketAddr>,
     acceptor: SslAcceptor,
-) {
-    task::spawn(async move {
-        let local_addr = stream.local_addr().ok();
-        let peer_addr = stream.peer_addr().ok();
-
-        let ssl_stream = Ssl::new(acceptor.context()).and_then(|ssl| SslStream::new(ssl, stream));
-        let mut ssl_stream = match ssl_stream {
-            Ok(s) => s,
-            Err(e) => {
-                tide::log::error!("ssl error", { error: e.to_string() });
-                return;
+    options: Arc<ConnectionOptions>,
+) where
+    State: Clone + Send + Sync + 'static,
+    S: Read + Write + Unpin + Send + Sync + 'static,
+{
+    let connection = async move {
+        let _active = options.stats.connection_opened();
+        let audit = options
+            .audit_writer
+            .as_ref()
+            .map(|_| Arc::new(ConnectionAudit::new(local_addr, peer_addr)));
+        #[cfg(feature = "debug")]
+        let record_written = options.assert_no_compression || options.handshake_dump_dir.is_some();
+        #[cfg(not(feature = "debug"))]
+        let record_written = options.assert_no_compression;
+        let stream = RecordingStream::new(stream, record_written);
+        let counts = stream.counts();
+
+        serve_tls(
+            app,
+            stream,
+            acceptor,
+            &options,
+            audit.clone(),
+            local_addr,
+            peer_addr,
+        )
+        .await;
+
+        if let (Some(writer), Some(audit)) = (&options.audit_writer, audit) {
+            let entry = audit.close(counts.read(), counts.written());
+            if let Err(error) = writer.write(entry).await {
+                logging::error!("unable to write audit entry", { error: error.to_string() });
             }
-        };
+        }
+    };
+    #[cfg(feature = "tracing")]
+    let connection = tracing::Instrument::instrument(
+        connection,
+        logging::connection_span(local_addr, peer_addr),
+    );
+    task::spawn(connection);
+}
 
-        match Pin::new(&mut ssl_stream).accept().await {
-            Ok(_) => {
-                let stream = SslStreamWrapper::new(ssl_stream);
-                let fut = async_h1::accept(stream, |mut req| async {
-                    if req.url_mut().set_scheme("https").is_err() {
-                        tide::log::error!("unable to set https scheme on url", { url: req.url().to_string() });
-                    }
+async fn serve_tls<State, S>(
+    app: Server<State>,
+    mut stream: RecordingStream<S>,
+    acceptor: SslAcceptor,
+    options: &Arc<ConnectionOptions>,
+    audit: Option<Arc<ConnectionAudit>>,
+    local_addr: Option<SocketAddr>,
+    peer_addr: Option<SocketAddr>,
+) where
+    State: Clone + Send + Sync + 'static,
+    S: Read + Write + Unpin + Send + Sync + 'static,
+{
+    let accepted_at = Instant::now();
+    let connection_id = error_log::next_connection_id();
+    let format = options.error_log_format;
+    #[cfg(feature = "debug")]
+    if options.handshake_dump_dir.is_some() {
+        stream.copy_read();
+    }
+    let ssl_stream = Ssl::new(acceptor.context()).and_then(|mut ssl| {
+        if options.record_client_hello {
+            ssl.set_ex_data(hello::read_recording_index()?, stream.record_read());
+        }
+        SslStream::new(ssl, stream)
+    });
+    let mut ssl_stream = match ssl_stream {
+        Ok(s) => s,
+        Err(e) => {
+            format.log("ssl error", &e, peer_addr, connection_id);
+            options.stats.handshake_failed();
+            return;
+        }
+    };
+
+    let accepted = Pin::new(&mut ssl_stream).accept().await;
+    let written = ssl_stream.get_mut().take_written();
+    #[cfg(feature = "debug")]
+    if let Some(dir) = &options.handshake_dump_dir {
+        // dumped whether or not the handshake succeeded, failures being
+        // what the dumps are for
+        let read = ssl_stream.get_mut().take_read_copy();
+        crate::handshake_dump::spawn_write(dir, connection_id, peer_addr, read, written.clone());
+    }
+    if let Err(tls_error) = accepted {
+        format.log("tls error", &tls_error, peer_addr, connection_id);
+        options.stats.handshake_failed();
+        return;
+    }
+    let session_reused = ssl_stream.ssl().session_reused();
+    options.stats.handshake_succeeded(session_reused);
+    ssl_stream.get_mut().stop_recording_read();
+    #[cfg(feature = "tracing")]
+    {
+        let span = tracing::Span::current();
+        span.record("protocol_version", ssl_stream.ssl().version_str());
+        if let Some(cipher) = ssl_stream.ssl().current_cipher() {
+            span.record("cipher", cipher.name());
+        }
+    }
+    let handshake_duration = accepted_at.elapsed();
+    #[cfg(feature = "metrics")]
+    crate::metrics::record(
+        crate::metrics::HANDSHAKE_DURATION,
+        handshake_duration,
+        peer_addr,
+        ssl_stream.ssl().selected_alpn_protocol(),
+    );
+    options.log_slow_handshake(ssl_stream.ssl(), peer_addr, handshake_duration);
+
+    if let (Some(callback), Some(peer_addr)) = (&options.session_reuse_callback, peer_addr) {
+        callback(peer_addr, session_reused);
+    }
 
-                    req.set_local_addr(local_addr);
-                    req.set_peer_addr(peer_addr);
-                    app.respond(req).await
-                });
+    if !options.allows_peer(ssl_stream.ssl()) {
+        options.stats.rejected();
+        return;
+    }
 
-                if let Err(error) = fut.await {
-                    tide::log::error!("async-h1 error", { error: error.to_string() });
-                }
-            }
-            Err(tls_error) => {
-                tide::log::error!("tls error", { error: tls_error.to_string() });
-            }
+    if options.assert_no_compression {
+        // TLS 1.3 always sends a null legacy compression method, so
+        // this only ever trips on a TLS 1.2 or older handshake
+        if hello::server_hello_compression(&written) != Some(0) {
+            logging::error!("tls compression negotiated, closing connection");
+            options.stats.rejected();
+            return;
         }
-    });
+    }
+
+    let summary = TlsSessionSummary::new(ssl_stream.ssl());
+    *options
+        .last_session
+        .write()
+        .unwrap_or_else(|e| e.into_inner()) = Some(summary);
+    if let Some(audit) = &audit {
+        audit.handshake(ssl_stream.ssl());
+    }
+
+    #[cfg(feature = "h2")]
+    if ssl_stream.ssl().selected_alpn_protocol() == Some(b"h2") {
+        let options = options.clone();
+        if let Err(error) =
+            crate::http2::accept(app, ssl_stream, options, audit, local_addr, peer_addr).await
+        {
+            format.log("h2 error", &error, peer_addr, connection_id);
+        }
+        return;
+    }
+
+    let audit = audit.as_deref();
+    let served = match options.protocol_buffer {
+        Some(size) => {
+            let buffered = ProtocolBuffer::new(ssl_stream, size);
+            serve_http1(&app, buffered, options, audit, local_addr, peer_addr).await
+        }
+        None => serve_http1(&app, ssl_stream, options, audit, local_addr, peer_addr).await,
+    };
+    if let Err(error) = served {
+        format.log("async-h1 error", &error, peer_addr, connection_id);
+    }
+}
+
+/// Serves HTTP/1.1 over the decrypted stream until the connection ends.
+async fn serve_http1<State, S>(
+    app: &Server<State>,
+    stream: S,
+    options: &ConnectionOptions,
+    audit: Option<&ConnectionAudit>,
+    local_addr: Option<SocketAddr>,
+    peer_addr: Option<SocketAddr>,
+) -> tide::http::Result<()>
+where
+    State: Clone + Send + Sync + 'static,
+    S: Read + Write + Unpin + Send + Sync + 'static,
+{
+    let stream = async_dup::Arc::new(Mutex::new(stream));
+    let served = AtomicUsize::new(0);
+    async_h1::accept(stream, |mut req| async {
+        if let Some(res) = options.reject_ambiguous(&req, audit) {
+            return Ok(res);
+        }
+        let served = served.fetch_add(1, Ordering::Relaxed) + 1;
+        options.prepare(&mut req, local_addr, peer_addr);
+        let mut res = options.respond(app, req, audit).await?;
+        options.limit_requests(served, &mut res);
+        Ok(res)
+    })
+    .await
+}
+
+impl<State: Clone + Send + Sync + 'static> TlsListener<State> {
+    /// A future running an accept loop on the bound listener.
+    fn accept_loop(
+        &self,
+        acceptor: &Arc<RwLock<SslAcceptor>>,
+        server: &Server<State>,
+    ) -> io::Result<BoxFuture<'static, io::Result<()>>> {
+        match &self.connection {
+            Connection::Tcp(listener) => Ok(Box::pin(accept_loop(
+                listener.clone(),
+                acceptor.clone(),
+                server.clone(),
+                self.tcp_options,
+                self.connection_options.clone(),
+            ))),
+            #[cfg(feature = "unix")]
+            Connection::Unix(listener) => Ok(Box::pin(accept_unix_loop(
+                listener.clone(),
+                acceptor.clone(),
+                server.clone(),
+                self.connection_options.clone(),
+            ))),
+            _ => Err(io::Error::other("accept - listener")),
+        }
+    }
 }
 
 impl<State: Clone + Send + Sync + 'static> ToListener<State> for TlsListener<State> {
@@ -208,7 +652,9 @@ impl<State: Clone + Send + Sync + 'static> ToListener<State> for TlsListener<Sta
     }
 }
 
-impl<State: Clone + Send + Sync + 'static> ToListener<State> for TlsListenerBuilder<State> {
+impl<State: Clone + Send + Sync + 'static> ToListener<State>
+    for TlsListenerBuilder<State, Yes, Yes, Yes>
+{
     type Listener = TlsListener<State>;
     fn to_listener(self) -> io::Result<Self::Listener> {
         self.finish()
@@ -218,63 +664,163 @@ impl<State: Clone + Send + Sync + 'static> ToListener<State> for TlsListenerBuil
 #[tide::utils::async_trait]
 impl<State: Clone + Send + Sync + 'static> Listener<State> for TlsListener<State> {
     async fn bind(&mut self, server: Server<State>) -> io::Result<()> {
-        self.configure().await?;
-        self.connect().await?;
+        self.start().await?;
         self.server = Some(server);
         Ok(())
     }
 
     async fn accept(&mut self) -> io::Result<()> {
-        let listener = self
-            .tcp()
-            .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "accept - listener"))?;
-        let mut incoming = listener.incoming();
         let acceptor = self
             .acceptor
             .as_ref()
-            .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "accept - acceptor"))?;
+            .ok_or_else(|| io::Error::other("accept - acceptor"))?;
         let server = self
             .server
             .as_ref()
-            .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "accept - server"))?;
+            .ok_or_else(|| io::Error::other("accept - server"))?;
+
+        let accept_tasks = self.connection_options.accept_tasks.get();
+        if accept_tasks == 1 {
+            return self.accept_loop(acceptor, server)?.await;
+        }
+
+        let tasks = (0..accept_tasks)
+            .map(|_| self.accept_loop(acceptor, server).map(task::spawn))
+            .collect::<io::Result<Vec<_>>>()?;
+        // the first task to end decides the result, the others would
+        // only keep accepting without anyone waiting on them
+        let (result, _, rest) = select_all(tasks).await;
+        for task in rest {
+            task.cancel().await;
+        }
+        result
+    }
 
+    fn info(&self) -> Vec<ListenInfo> {
+        vec![ListenInfo::new(
+            format!("{} ({})", self.connection, openssl::version::version()),
+            String::from(self.connection.transport()),
+            true,
+        )]
+    }
+}
+
+/// Accepts connections off `listener` and hands them to `handle_tls`,
+/// until the listener fails with a non-transient error.
+async fn accept_loop<State: Clone + Send + Sync + 'static>(
+    listener: Arc<TcpListener>,
+    acceptor: Arc<RwLock<SslAcceptor>>,
+    server: Server<State>,
+    tcp_options: TcpOptions,
+    options: Arc<ConnectionOptions>,
+) -> io::Result<()> {
+    #[cfg(feature = "tracing")]
+    let span = tracing::info_span!("tls_accept", local_addr = ?listener.local_addr().ok());
+    let connections = accept_connections(listener, acceptor, server, tcp_options, options);
+    #[cfg(feature = "tracing")]
+    let connections = tracing::Instrument::instrument(connections, span);
+    connections.await
+}
+
+async fn accept_connections<State: Clone + Send + Sync + 'static>(
+    listener: Arc<TcpListener>,
+    acceptor: Arc<RwLock<SslAcceptor>>,
+    server: Server<State>,
+    tcp_options: TcpOptions,
+    options: Arc<ConnectionOptions>,
+) -> io::Result<()> {
+    let mut incoming = listener.incoming();
+    while let Some(stream) = incoming.next().await {
+        match stream {
+            Err(ref e) if is_transient_error(e) => continue,
+
+            Err(error) => {
+                let delay = Duration::from_millis(500);
+                logging::error!("Error: {}. Pausing for {:?}.", error, delay);
+                task::sleep(delay).await;
+                continue;
+            }
+
+            Ok(stream) => {
+                let options = if options.health_probes.contains(stream.peer_addr().ok()) {
+                    Arc::new(options.for_health_probe())
+                } else {
+                    options.clone()
+                };
+                options.stats.accepted();
+                let allowed = match stream.peer_addr() {
+                    Ok(peer_addr) => (options.ip_filter)(peer_addr.ip()),
+                    Err(_) => false,
+                };
+                if !allowed {
+                    options.stats.rejected();
+                    continue;
+                }
+
+                tcp_options.apply(&stream)?;
+
+                let (local_addr, peer_addr) = (stream.local_addr().ok(), stream.peer_addr().ok());
+                let acceptor = acceptor.read().unwrap_or_else(|e| e.into_inner()).clone();
+                handle_tls(
+                    server.clone(),
+                    stream,
+                    local_addr,
+                    peer_addr,
+                    acceptor,
+                    options,
+                )
+            }
+        };
+    }
+    Ok(())
+}
+
+/// Accepts connections off a unix domain socket, like `accept_loop`.
+/// The ip filter and tcp options do not apply.
+#[cfg(feature = "unix")]
+async fn accept_unix_loop<State: Clone + Send + Sync + 'static>(
+    listener: Arc<UnixListener>,
+    acceptor: Arc<RwLock<SslAcceptor>>,
+    server: Server<State>,
+    options: Arc<ConnectionOptions>,
+) -> io::Result<()> {
+    #[cfg(feature = "tracing")]
+    let span = tracing::info_span!("tls_accept", local_addr = ?listener.local_addr().ok());
+    let connections = async move {
+        let mut incoming = listener.incoming();
         while let Some(stream) = incoming.next().await {
             match stream {
                 Err(ref e) if is_transient_error(e) => continue,
 
                 Err(error) => {
                     let delay = Duration::from_millis(500);
-                    tide::log::error!("Error: {}. Pausing for {:?}.", error, delay);
+                    logging::error!("Error: {}. Pausing for {:?}.", error, delay);
                     task::sleep(delay).await;
                     continue;
                 }
 
                 Ok(stream) => {
-                    if let Some(nodelay) = self.tcp_nodelay {
-                        stream.set_nodelay(nodelay)?;
-                    }
-
-                    if let Some(ttl) = self.tcp_ttl {
-                        stream.set_ttl(ttl)?;
-                    }
-
-                    handle_tls(server.clone(), stream, acceptor.clone())
+                    options.stats.accepted();
+                    let acceptor = acceptor.read().unwrap_or_else(|e| e.into_inner()).clone();
+                    handle_tls(
+                        server.clone(),
+                        stream,
+                        None,
+                        None,
+                        acceptor,
+                        options.clone(),
+                    )
                 }
             };
         }
         Ok(())
-    }
-
-    fn info(&self) -> Vec<ListenInfo> {
-        vec![ListenInfo::new(
-            self.connection.to_string(),
-            String::from("tcp"),
-            true,
-        )]
-    }
+    };
+    #[cfg(feature = "tracing")]
+    let connections = tracing::Instrument::instrument(connections, span);
+    connections.await
 }
 
-fn is_transient_error(e: &io::Error) -> bool {
+pub(crate) fn is_transient_error(e: &io::Error) -> bool {
     use io::ErrorKind::*;
     matches!(
         e.kind(),
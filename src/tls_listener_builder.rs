@@ -1,11 +1,30 @@
 use async_std::io;
 use async_std::net::TcpListener;
 
-use super::{TcpConnection, TlsListener, TlsListenerConfig};
+use crate::tls_acceptor_options::BaseProfile;
+use crate::typestate::{Marker, No, Yes};
 
+use super::{
+    opaque, AcceptorFactory, AcceptorSource, CertProvider, Connection, ConnectionAuditWriter,
+    ConnectionOptions, ContextHook, CookieSecurityOptions, CurvesPreference, DhParams,
+    ErrorLogFormat, HealthCheck, HealthCheckFailure, HostConfig, InvalidationCheck, Material,
+    ProviderSource, RequestTagger, SharedCertProvider, SigningDigest, TcpOptions,
+    TlsAcceptorOptions, TlsListener, TlsListenerConfig, TlsProfile,
+};
+
+#[cfg(feature = "remote-config")]
+use crate::RemoteConfig;
+use openssl::ssl::{SslAcceptor, SslContextRef};
+use tide::http::url::Host;
+
+use std::future::Future;
 use std::marker::PhantomData;
-use std::net::{SocketAddr, ToSocketAddrs};
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
 
 /// # A builder for TlsListeners
 ///
@@ -35,17 +54,58 @@ use std::path::{Path, PathBuf};
 ///     .tcp_nodelay(true)
 ///     .finish();
 /// ```
-
-pub struct TlsListenerBuilder<State> {
+///
+/// A builder can be cloned to share common settings between
+/// listeners. Closures and a listener passed to
+/// [`TlsListenerBuilder::tcp`] are shared between the clones, and a
+/// [`TlsProfile::Custom`](crate::TlsProfile::Custom) builder still
+/// only builds a single acceptor.
+///
+/// ```rust
+/// # use tide_openssl::TlsListener;
+/// let base = TlsListener::<()>::build()
+///     .cert("./tls/localhost-4433.cert")
+///     .key("./tls/localhost-4433.key");
+/// let first = base.clone().addrs("localhost:4433").finish();
+/// let second = base.addrs("localhost:4434").finish();
+/// ```
+///
+/// The last three type parameters record whether a certificate, a key
+/// and an address have been provided, see [`typestate`](crate::typestate).
+/// [`TlsListenerBuilder::finish`] only exists once all three have, so
+/// that forgetting one is a compile error rather than a runtime error.
+///
+/// ```rust,compile_fail
+/// # use tide_openssl::TlsListener;
+/// let listener = TlsListener::<()>::build()
+///     .addrs("localhost:4433")
+///     .cert("./tls/localhost-4433.cert")
+///     .finish();
+/// ```
+pub struct TlsListenerBuilder<State, Cert: Marker = No, Key: Marker = No, Addr: Marker = No> {
     key: Option<PathBuf>,
     cert: Option<PathBuf>,
+    cert_chain: Option<PathBuf>,
+    key_pem: Option<String>,
+    cert_pem: Option<String>,
+    acceptor_factory: Option<AcceptorFactory>,
+    cert_provider: Option<SharedCertProvider>,
+    config: Option<TlsListenerConfig>,
     // config: Option<ServerConfig>,
     // tls_acceptor: Option<Arc<dyn CustomTlsAcceptor>>,
-    tcp: Option<TcpListener>,
+    tcp: Option<Arc<TcpListener>>,
     addrs: Option<Vec<SocketAddr>>,
-    tcp_nodelay: Option<bool>,
-    tcp_ttl: Option<u32>,
+    #[cfg(feature = "unix")]
+    unix_path: Option<PathBuf>,
+    tcp_options: TcpOptions,
+    acceptor_options: TlsAcceptorOptions,
+    connection_options: ConnectionOptions,
+    invalidation_check: Option<(InvalidationCheck, Duration)>,
+    health_check: HealthCheck,
+    #[cfg(feature = "remote-config")]
+    remote_config: Option<RemoteConfig>,
     _state: PhantomData<State>,
+    _required: PhantomData<(Cert, Key, Addr)>,
 }
 
 impl<State> Default for TlsListenerBuilder<State> {
@@ -53,22 +113,73 @@ impl<State> Default for TlsListenerBuilder<State> {
         Self {
             key: None,
             cert: None,
+            cert_chain: None,
+            key_pem: None,
+            cert_pem: None,
+            acceptor_factory: None,
+            cert_provider: None,
+            config: None,
             // config: None,
             // tls_acceptor: None,
             tcp: None,
             addrs: None,
-            tcp_nodelay: None,
-            tcp_ttl: None,
+            #[cfg(feature = "unix")]
+            unix_path: None,
+            tcp_options: TcpOptions::default(),
+            acceptor_options: TlsAcceptorOptions::default(),
+            connection_options: ConnectionOptions::default(),
+            invalidation_check: None,
+            health_check: HealthCheck::default(),
+            #[cfg(feature = "remote-config")]
+            remote_config: None,
+            _state: PhantomData,
+            _required: PhantomData,
+        }
+    }
+}
+
+impl<State, C: Marker, K: Marker, A: Marker> Clone for TlsListenerBuilder<State, C, K, A> {
+    fn clone(&self) -> Self {
+        Self {
+            key: self.key.clone(),
+            cert: self.cert.clone(),
+            cert_chain: self.cert_chain.clone(),
+            key_pem: self.key_pem.clone(),
+            cert_pem: self.cert_pem.clone(),
+            acceptor_factory: self.acceptor_factory.clone(),
+            cert_provider: self.cert_provider.clone(),
+            config: self.config.clone(),
+            tcp: self.tcp.clone(),
+            addrs: self.addrs.clone(),
+            #[cfg(feature = "unix")]
+            unix_path: self.unix_path.clone(),
+            tcp_options: self.tcp_options,
+            acceptor_options: self.acceptor_options.clone(),
+            connection_options: self.connection_options.clone(),
+            invalidation_check: self.invalidation_check.clone(),
+            health_check: self.health_check.clone(),
+            #[cfg(feature = "remote-config")]
+            remote_config: self.remote_config.clone(),
             _state: PhantomData,
+            _required: PhantomData,
         }
     }
 }
 
-impl<State> std::fmt::Debug for TlsListenerBuilder<State> {
+impl<State, C: Marker, K: Marker, A: Marker> std::fmt::Debug
+    for TlsListenerBuilder<State, C, K, A>
+{
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("TlsListenerBuilder")
+        let mut debug = f.debug_struct("TlsListenerBuilder");
+        debug
             .field("key", &self.key)
             .field("cert", &self.cert)
+            .field("cert_chain", &self.cert_chain)
+            .field("key_pem", &self.key_pem.as_ref().map(|_| "<redacted>"))
+            .field("cert_pem", &self.cert_pem.as_ref().map(|_| ".."))
+            .field("acceptor_factory", &opaque(&self.acceptor_factory))
+            .field("cert_provider", &opaque(&self.cert_provider))
+            .field("config", &self.config)
             // .field(
             //     "config",
             //     &if self.config.is_some() {
@@ -86,10 +197,142 @@ impl<State> std::fmt::Debug for TlsListenerBuilder<State> {
             //     },
             // )
             .field("tcp", &self.tcp)
-            .field("addrs", &self.addrs)
-            .field("tcp_nodelay", &self.tcp_nodelay)
-            .field("tcp_ttl", &self.tcp_ttl)
-            .finish()
+            .field("addrs", &self.addrs);
+        #[cfg(feature = "unix")]
+        debug.field("unix_path", &self.unix_path);
+        debug
+            .field("tcp_options", &self.tcp_options)
+            .field("acceptor_options", &self.acceptor_options)
+            .field("connection_options", &self.connection_options)
+            .field(
+                "invalidation_check",
+                &self
+                    .invalidation_check
+                    .as_ref()
+                    .map(|(_, interval)| interval),
+            )
+            .field("health_check", &self.health_check);
+        #[cfg(feature = "remote-config")]
+        debug.field("remote_config", &self.remote_config);
+        debug.finish()
+    }
+}
+
+impl<State, C: Marker, K: Marker, A: Marker> std::fmt::Display
+    for TlsListenerBuilder<State, C, K, A>
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let addr = match (&self.tcp, &self.addrs) {
+            (Some(tcp), _) => tcp
+                .local_addr()
+                .map(|addr| addr.to_string())
+                .unwrap_or_else(|_| String::from("[unknown]")),
+            (None, Some(addrs)) => addrs
+                .iter()
+                .map(|addr| addr.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+            (None, None) => String::from("none"),
+        };
+        #[cfg(feature = "unix")]
+        let addr = match &self.unix_path {
+            Some(path) => path.display().to_string(),
+            None => addr,
+        };
+
+        let cert = match (
+            &self.cert,
+            &self.cert_pem,
+            &self.acceptor_factory,
+            &self.config,
+        ) {
+            (Some(path), _, _, _) => path.display().to_string(),
+            (None, Some(_), _, _) => String::from("<pem>"),
+            (None, None, Some(_), _) => String::from("<async_configure>"),
+            (None, None, None, _) if self.cert_provider.is_some() => {
+                String::from("<cert_provider>")
+            }
+            (None, None, None, Some(TlsListenerConfig::Paths { cert, .. })) => {
+                cert.display().to_string()
+            }
+            (None, None, None, Some(TlsListenerConfig::Pem { .. })) => String::from("<pem>"),
+            (None, None, None, Some(TlsListenerConfig::Der { .. })) => String::from("<der>"),
+            (None, None, None, Some(TlsListenerConfig::PrebuiltAcceptor(_))) => {
+                String::from("<acceptor>")
+            }
+            (None, None, None, None) => String::from("none"),
+        };
+
+        write!(
+            f,
+            "TlsListenerBuilder {{ addr: {}, cert: {}, profile: {:?} }}",
+            addr, cert, self.acceptor_options.profile
+        )
+    }
+}
+
+/// Builds on an address, a certificate path and a key path, so that
+/// `TlsListenerBuilder::from(("localhost:4433", cert, key))` can be
+/// passed straight to [`tide::Server::listen`]. Rust's orphan rules
+/// rule out implementing [`tide::listener::ToListener`] for the tuple
+/// itself.
+impl<State, A, C, K> From<(A, C, K)> for TlsListenerBuilder<State, Yes, Yes, Yes>
+where
+    A: ToSocketAddrs,
+    C: AsRef<Path>,
+    K: AsRef<Path>,
+{
+    fn from((addrs, cert, key): (A, C, K)) -> Self {
+        TlsListenerBuilder::new().addrs(addrs).cert(cert).key(key)
+    }
+}
+
+/// Parses a listener description such as
+/// `tls://localhost:4433?cert=/path/cert.pem&key=/path/key.pem`, as
+/// found in configuration files. The query must hold `cert` and `key`
+/// and may hold a `cert_chain` path. IPv6 addresses go in brackets, as
+/// in `tls://[::1]:4433`.
+impl<State> std::str::FromStr for TlsListenerBuilder<State, Yes, Yes, Yes> {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> io::Result<Self> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+        let url = tide::http::Url::parse(s).map_err(|e| invalid(e.to_string()))?;
+        if url.scheme() != "tls" {
+            return Err(invalid(format!("unsupported scheme {}", url.scheme())));
+        }
+
+        let port = url
+            .port()
+            .ok_or_else(|| invalid(String::from("missing port")))?;
+        // ip addresses are taken as they are, host names are resolved
+        // like TlsListenerBuilder::addrs resolves them
+        let addrs: Vec<SocketAddr> = match url.host() {
+            Some(Host::Ipv4(ip)) => vec![SocketAddr::from((ip, port))],
+            Some(Host::Ipv6(ip)) => vec![SocketAddr::from((ip, port))],
+            Some(Host::Domain(host)) => (host, port).to_socket_addrs()?.collect(),
+            None => return Err(invalid(String::from("missing host"))),
+        };
+
+        let (mut cert, mut key, mut cert_chain) = (None, None, None);
+        for (name, value) in url.query_pairs() {
+            let path = Some(PathBuf::from(&*value));
+            match &*name {
+                "cert" => cert = path,
+                "key" => key = path,
+                "cert_chain" => cert_chain = path,
+                _ => return Err(invalid(format!("unknown parameter {}", name))),
+            };
+        }
+        let cert = cert.ok_or_else(|| invalid(String::from("missing cert")))?;
+        let key = key.ok_or_else(|| invalid(String::from("missing key")))?;
+
+        let mut builder = TlsListenerBuilder::new()
+            .addrs(&addrs[..])
+            .cert(cert)
+            .key(key);
+        builder.cert_chain = cert_chain;
+        Ok(builder)
     }
 }
 
@@ -97,52 +340,259 @@ impl<State> TlsListenerBuilder<State> {
     pub(crate) fn new() -> Self {
         Self::default()
     }
+}
+
+impl<State, C: Marker, K: Marker, A: Marker> TlsListenerBuilder<State, C, K, A> {
+    /// Moves the settings into a builder with different required
+    /// settings marked as provided.
+    fn mark<C2: Marker, K2: Marker, A2: Marker>(self) -> TlsListenerBuilder<State, C2, K2, A2> {
+        TlsListenerBuilder {
+            key: self.key,
+            cert: self.cert,
+            cert_chain: self.cert_chain,
+            key_pem: self.key_pem,
+            cert_pem: self.cert_pem,
+            acceptor_factory: self.acceptor_factory,
+            cert_provider: self.cert_provider,
+            config: self.config,
+            tcp: self.tcp,
+            addrs: self.addrs,
+            #[cfg(feature = "unix")]
+            unix_path: self.unix_path,
+            tcp_options: self.tcp_options,
+            acceptor_options: self.acceptor_options,
+            connection_options: self.connection_options,
+            invalidation_check: self.invalidation_check,
+            health_check: self.health_check,
+            #[cfg(feature = "remote-config")]
+            remote_config: self.remote_config,
+            _state: PhantomData,
+            _required: PhantomData,
+        }
+    }
 
     /// Provide a path to a key file, in either pkcs8 or rsa
     /// formats. This is mutually exclusive with providing a server
     /// config with [`TlsListenerBuilder::config`], but must be used
     /// in conjunction with [`TlsListenerBuilder::cert`]
-    pub fn key(mut self, path: impl AsRef<Path>) -> Self {
+    pub fn key(mut self, path: impl AsRef<Path>) -> TlsListenerBuilder<State, C, Yes, A> {
         self.key = Some(path.as_ref().into());
-        self
+        self.mark()
     }
 
     /// Provide a path to a certificate chain file. This is mutually exclusive with
     /// providing a server config with [`TlsListenerBuilder::config`],
     /// but must be used in conjunction with
     /// [`TlsListenerBuilder::key`]
-    pub fn cert(mut self, path: impl AsRef<Path>) -> Self {
+    pub fn cert(mut self, path: impl AsRef<Path>) -> TlsListenerBuilder<State, Yes, K, A> {
         self.cert = Some(path.as_ref().into());
+        self.mark()
+    }
+
+    /// Provide a path to a PEM file holding the intermediate
+    /// certificates, for when [`TlsListenerBuilder::cert`] contains
+    /// only the leaf certificate. Each certificate in this file is
+    /// sent to clients after the leaf, in file order. This can only be
+    /// used in conjunction with [`TlsListenerBuilder::cert`]
+    pub fn cert_chain(mut self, path: impl AsRef<Path>) -> Self {
+        self.cert_chain = Some(path.as_ref().into());
+        self
+    }
+
+    /// Provide the contents of a PEM encoded private key. This is
+    /// handy when the key is injected through an environment variable
+    /// rather than a file. This is mutually exclusive with
+    /// [`TlsListenerBuilder::key`], but must be used in conjunction
+    /// with [`TlsListenerBuilder::cert_pem`]
+    ///
+    /// ```rust
+    /// # use tide_openssl::TlsListener;
+    /// # std::env::set_var("TLS_CERT", "");
+    /// # std::env::set_var("TLS_KEY", "");
+    /// let listener = TlsListener::<()>::build()
+    ///     .addrs("localhost:4433")
+    ///     .cert_pem(std::env::var("TLS_CERT").unwrap())
+    ///     .key_pem(std::env::var("TLS_KEY").unwrap())
+    ///     .finish();
+    /// ```
+    pub fn key_pem(mut self, pem: impl Into<String>) -> TlsListenerBuilder<State, C, Yes, A> {
+        self.key_pem = Some(pem.into());
+        self.mark()
+    }
+
+    /// Provide the contents of a PEM encoded certificate chain, leaf
+    /// certificate first. This is mutually exclusive with
+    /// [`TlsListenerBuilder::cert`], but must be used in conjunction
+    /// with [`TlsListenerBuilder::key_pem`]
+    pub fn cert_pem(mut self, pem: impl Into<String>) -> TlsListenerBuilder<State, Yes, K, A> {
+        self.cert_pem = Some(pem.into());
+        self.mark()
+    }
+
+    /// Provide the key material as a [`TlsListenerConfig`], for
+    /// instance one deduplicated from many listeners' configs. This is
+    /// mutually exclusive with [`TlsListenerBuilder::cert`],
+    /// [`TlsListenerBuilder::cert_pem`] and
+    /// [`TlsListenerBuilder::async_configure`].
+    ///
+    /// ```rust
+    /// # use tide_openssl::{TlsListener, TlsListenerConfig};
+    /// let config = TlsListenerConfig::Paths {
+    ///     cert: "./tls/localhost-4433.cert".into(),
+    ///     key: "./tls/localhost-4433.key".into(),
+    ///     chain: None,
+    /// };
+    /// let listener = TlsListener::<()>::build()
+    ///     .addrs("localhost:4433")
+    ///     .config(config)
+    ///     .finish();
+    /// ```
+    pub fn config(mut self, config: TlsListenerConfig) -> TlsListenerBuilder<State, Yes, Yes, A> {
+        self.config = Some(config);
+        self.mark()
+    }
+
+    /// Provide a path to a PEM encoded certificate of a different key
+    /// type than the primary certificate, for example an ECDSA
+    /// certificate alongside an RSA one. OpenSSL picks whichever of the
+    /// two matches the signature algorithms the client supports, so
+    /// modern clients get the smaller ECDSA handshake while older ones
+    /// still get RSA. This must be used in conjunction with
+    /// [`TlsListenerBuilder::secondary_key`] or one of its variants.
+    ///
+    /// ```rust
+    /// # use tide_openssl::TlsListener;
+    /// let listener = TlsListener::<()>::build()
+    ///     .addrs("localhost:4433")
+    ///     .cert("./tls/localhost-4433-rsa.cert")
+    ///     .key("./tls/localhost-4433-rsa.key")
+    ///     .secondary_cert("./tls/localhost-4433-ecdsa.cert")
+    ///     .secondary_key("./tls/localhost-4433-ecdsa.key")
+    ///     .finish();
+    /// ```
+    pub fn secondary_cert(mut self, path: impl AsRef<Path>) -> Self {
+        self.acceptor_options.secondary_cert = Some(Material::Path(path.as_ref().into()));
+        self
+    }
+
+    /// Provide the contents of a PEM encoded secondary certificate,
+    /// see [`TlsListenerBuilder::secondary_cert`]
+    pub fn secondary_cert_pem(mut self, pem: impl Into<String>) -> Self {
+        self.acceptor_options.secondary_cert = Some(Material::Pem(pem.into().into_bytes()));
+        self
+    }
+
+    /// Provide a DER encoded secondary certificate, see
+    /// [`TlsListenerBuilder::secondary_cert`]
+    pub fn secondary_cert_der(mut self, der: impl Into<Vec<u8>>) -> Self {
+        self.acceptor_options.secondary_cert = Some(Material::Der(der.into()));
         self
     }
 
-    /// Provide a prebuilt
-    /// [`rustls::ServerConfig`](::rustls::ServerConfig) with any
-    /// options. This is mutually exclusive with both
+    /// Provide a path to the PEM encoded private key for
+    /// [`TlsListenerBuilder::secondary_cert`]
+    pub fn secondary_key(mut self, path: impl AsRef<Path>) -> Self {
+        self.acceptor_options.secondary_key = Some(Material::Path(path.as_ref().into()));
+        self
+    }
+
+    /// Provide the contents of a PEM encoded private key for
+    /// [`TlsListenerBuilder::secondary_cert`]
+    pub fn secondary_key_pem(mut self, pem: impl Into<String>) -> Self {
+        self.acceptor_options.secondary_key = Some(Material::Pem(pem.into().into_bytes()));
+        self
+    }
+
+    /// Provide a DER encoded private key for
+    /// [`TlsListenerBuilder::secondary_cert`]
+    pub fn secondary_key_der(mut self, der: impl Into<Vec<u8>>) -> Self {
+        self.acceptor_options.secondary_key = Some(Material::Der(der.into()));
+        self
+    }
+
+    /// Provides an async factory for a fully configured
+    /// [`SslAcceptor`]. This is mutually exclusive with
     /// [`TlsListenerBuilder::key`] and [`TlsListenerBuilder::cert`],
-    /// but provides the opportunity for more configuration choices.
-    // pub fn config(mut self, config: ServerConfig) -> Self {
-    //     self.config = Some(config);
-    //     self
-    // }
-
-    /// Provides a custom acceptor for TLS connections.  This is mutually
-    /// exclusive with any of [`TlsListenerBuilder::key`],
-    /// [`TlsListenerBuilder::cert`], and [`TlsListenerBuilder::config`], but
-    /// gives total control over accepting TLS connections, including
-    /// multiplexing other streams or ALPN negotiations on the same TLS
-    /// connection that tide should ignore.
-    // pub fn tls_acceptor(mut self, acceptor: Arc<dyn CustomTlsAcceptor>) -> Self {
-    //     self.tls_acceptor = Some(acceptor);
-    //     self
-    // }
+    /// and is useful when the key material has to be fetched
+    /// asynchronously, for example from a secrets store.
+    ///
+    /// The factory is called when the listener is bound, so errors
+    /// surface from [`tide::Server::listen`]. Since the acceptor is
+    /// built by the factory, acceptor settings on this builder such
+    /// as [`TlsListenerBuilder::alpn_protocols`] are not applied to it.
+    ///
+    /// ```rust
+    /// # use tide_openssl::TlsListener;
+    /// use openssl::ssl::{SslAcceptor, SslFiletype, SslMethod};
+    ///
+    /// let listener = TlsListener::<()>::build()
+    ///     .addrs("localhost:4433")
+    ///     .async_configure(|| {
+    ///         Box::pin(async {
+    ///             let mut acceptor = SslAcceptor::mozilla_modern_v5(SslMethod::tls())?;
+    ///             acceptor.set_private_key_file("./tls/localhost-4433.key", SslFiletype::PEM)?;
+    ///             acceptor.set_certificate_chain_file("./tls/localhost-4433.cert")?;
+    ///             Ok(acceptor.build())
+    ///         })
+    ///     })
+    ///     .finish();
+    /// ```
+    pub fn async_configure(
+        mut self,
+        f: impl Fn() -> Pin<Box<dyn Future<Output = io::Result<SslAcceptor>> + Send>>
+            + Send
+            + Sync
+            + 'static,
+    ) -> TlsListenerBuilder<State, Yes, Yes, A> {
+        self.acceptor_factory = Some(Arc::new(f));
+        self.mark()
+    }
+
+    /// Serves the certificate and key from `provider`, for
+    /// certificates that are renewed without a restart, such as by
+    /// cert-manager or an ACME client. This is mutually exclusive with
+    /// the other ways of providing key material.
+    ///
+    /// The provider is first called when the listener is bound, so
+    /// errors surface from [`tide::Server::listen`]. From then on it
+    /// is called every [`CertProvider::refresh_interval`], and the
+    /// acceptor is rebuilt with the builder's options whenever the
+    /// certificate has changed. The new acceptor is swapped in as with
+    /// [`TlsListenerBuilder::invalidation_check`], and a failed fetch
+    /// is logged while the current certificate is kept. Wrap a
+    /// provider that should not be called that often in a
+    /// [`CachingCertProvider`](crate::CachingCertProvider).
+    ///
+    /// ```rust
+    /// # use std::future::Future;
+    /// # use std::pin::Pin;
+    /// # use tide_openssl::{CertAndKey, CertProvider, TlsListener};
+    /// # #[derive(Debug)]
+    /// # struct SecretStore;
+    /// # impl CertProvider for SecretStore {
+    /// #     fn fetch(&self) -> Pin<Box<dyn Future<Output = std::io::Result<CertAndKey>> + Send + '_>> {
+    /// #         Box::pin(async { Ok((Vec::new(), Vec::new())) })
+    /// #     }
+    /// # }
+    /// let listener = TlsListener::<()>::build()
+    ///     .addrs("localhost:4433")
+    ///     .cert_provider(SecretStore)
+    ///     .finish();
+    /// ```
+    pub fn cert_provider(
+        mut self,
+        provider: impl CertProvider + Send + Sync + 'static,
+    ) -> TlsListenerBuilder<State, Yes, Yes, A> {
+        self.cert_provider = Some(Arc::new(provider));
+        self.mark()
+    }
 
     /// Provides a bound tcp listener (either async-std or std) to
     /// build this tls listener on. This is mutually exclusive with
     /// [`TlsListenerBuilder::addrs`], but one of them is mandatory.
-    pub fn tcp(mut self, tcp: impl Into<TcpListener>) -> Self {
-        self.tcp = Some(tcp.into());
-        self
+    pub fn tcp(mut self, tcp: impl Into<TcpListener>) -> TlsListenerBuilder<State, C, K, Yes> {
+        self.tcp = Some(Arc::new(tcp.into()));
+        self.mark()
     }
 
     /// Provides a [`std::net::ToSocketAddrs`] specification for this
@@ -153,74 +603,1218 @@ impl<State> TlsListenerBuilder<State> {
     /// each of the addresses until one succeeds and returns the listener.
     /// If none of the addresses succeed in creating a listener, the error
     /// returned from the last attempt (the last address) is returned.
-    pub fn addrs(mut self, addrs: impl ToSocketAddrs) -> Self {
+    pub fn addrs(mut self, addrs: impl ToSocketAddrs) -> TlsListenerBuilder<State, C, K, Yes> {
         if let Ok(socket_addrs) = addrs.to_socket_addrs() {
             self.addrs = Some(socket_addrs.collect());
         }
+        self.mark()
+    }
+
+    /// Serves tls over a unix domain socket bound at `path` rather
+    /// than over tcp. This is mutually exclusive with
+    /// [`TlsListenerBuilder::tcp`] and [`TlsListenerBuilder::addrs`].
+    /// Nothing may exist at `path` yet, the socket file is created when
+    /// the listener is bound and is not removed afterwards.
+    ///
+    /// Options that only apply to tcp, like
+    /// [`TlsListenerBuilder::tcp_nodelay`] and
+    /// [`TlsListenerBuilder::allow_ips`], are ignored, and requests have
+    /// no peer address.
+    #[cfg(feature = "unix")]
+    pub fn unix_path(mut self, path: impl AsRef<Path>) -> TlsListenerBuilder<State, C, K, Yes> {
+        self.unix_path = Some(path.as_ref().to_path_buf());
+        self.mark()
+    }
+
+    /// Runs `n` accept loops in parallel tasks, all accepting from the
+    /// same socket, instead of a single loop on the task that calls
+    /// `accept`. This can help at high connection rates on machines
+    /// with many cores. The default is 1.
+    ///
+    /// To spread connections over several sockets instead, bind one
+    /// listener per process with [`TlsListenerBuilder::reuse_port`].
+    pub fn accept_threads(mut self, n: NonZeroUsize) -> Self {
+        self.connection_options.accept_tasks = n;
         self
     }
 
     /// Provides a TCP_NODELAY option for this tls listener.
     pub fn tcp_nodelay(mut self, nodelay: bool) -> Self {
-        self.tcp_nodelay = Some(nodelay);
+        self.tcp_options.nodelay = Some(nodelay);
         self
     }
 
     /// Provides a TTL option for this tls listener, in seconds.
     pub fn tcp_ttl(mut self, ttl: u32) -> Self {
-        self.tcp_ttl = Some(ttl);
+        self.tcp_options.ttl = Some(ttl);
+        self
+    }
+
+    /// Provides a SO_RCVBUF option for each accepted stream, in bytes.
+    ///
+    /// The kernel treats this as a hint: Linux doubles the value to
+    /// leave room for bookkeeping and clamps it to
+    /// `net.core.rmem_max`, while macOS and Windows use it as given up
+    /// to their own limits. Setting it also turns off Linux's receive
+    /// buffer autotuning for that socket.
+    pub fn tcp_recv_buffer_size(mut self, size: usize) -> Self {
+        self.tcp_options.recv_buffer_size = Some(size);
+        self
+    }
+
+    /// Provides a SO_SNDBUF option for each accepted stream, in bytes.
+    /// The same platform caveats as for
+    /// [`TlsListenerBuilder::tcp_recv_buffer_size`] apply, with
+    /// `net.core.wmem_max` as the Linux limit.
+    pub fn tcp_send_buffer_size(mut self, size: usize) -> Self {
+        self.tcp_options.send_buffer_size = Some(size);
+        self
+    }
+
+    /// Filters connections by peer ip address before the TLS handshake
+    /// is attempted. Streams for which `filter` returns false are
+    /// dropped right after being accepted, without logging. By default
+    /// every address is allowed.
+    ///
+    /// ```rust
+    /// # use tide_openssl::TlsListener;
+    /// let listener = TlsListener::<()>::build()
+    ///     .addrs("localhost:4433")
+    ///     .cert("./tls/localhost-4433.cert")
+    ///     .key("./tls/localhost-4433.key")
+    ///     .allow_ips(|ip| ip.is_loopback())
+    ///     .finish();
+    /// ```
+    pub fn allow_ips(mut self, filter: impl Fn(IpAddr) -> bool + Send + Sync + 'static) -> Self {
+        self.connection_options.ip_filter = Arc::new(filter);
+        self
+    }
+
+    /// Filters connections by the subject distinguished name of the
+    /// client certificate, after the TLS handshake. The name is passed
+    /// to `filter` in OpenSSL's one line format, as in
+    /// `/C=US/O=Acme Corp/CN=client01.acme.com`, and connections for
+    /// which it returns false are closed with a warning. Connections
+    /// without a client certificate are closed as well.
+    ///
+    /// This complements the CA-level verification done by OpenSSL, so
+    /// it is only useful with an acceptor that requests client
+    /// certificates, as built with
+    /// [`TlsListenerBuilder::async_configure`]. A `regex::Regex` can be
+    /// used through its `is_match` method.
+    ///
+    /// ```rust
+    /// # use tide_openssl::TlsListener;
+    /// let listener = TlsListener::<()>::build()
+    ///     .addrs("localhost:4433")
+    ///     .cert("./tls/localhost-4433.cert")
+    ///     .key("./tls/localhost-4433.key")
+    ///     .peer_cert_dn_required(|dn| dn.starts_with("/C=US/O=Acme Corp/"))
+    ///     .finish();
+    /// ```
+    pub fn peer_cert_dn_required(
+        mut self,
+        filter: impl Fn(&str) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.connection_options.peer_dn_filter = Some(Arc::new(filter));
+        self
+    }
+
+    /// Provides a SO_REUSEADDR option for this tls listener, allowing
+    /// it to bind while old connections on the address are still in
+    /// TIME_WAIT. This is applied when binding to
+    /// [`TlsListenerBuilder::addrs`], so it has no effect on a
+    /// listener provided with [`TlsListenerBuilder::tcp`].
+    pub fn reuse_addr(mut self, reuse_addr: bool) -> Self {
+        self.tcp_options.reuse_addr = Some(reuse_addr);
+        self
+    }
+
+    /// Provides a SO_REUSEPORT option for this tls listener, allowing
+    /// several processes to bind the same address while the kernel
+    /// balances incoming connections between them, as in a pre-fork
+    /// deployment. Like [`TlsListenerBuilder::reuse_addr`], this only
+    /// applies when binding to [`TlsListenerBuilder::addrs`].
+    #[cfg(unix)]
+    pub fn reuse_port(mut self, reuse_port: bool) -> Self {
+        self.tcp_options.reuse_port = Some(reuse_port);
+        self
+    }
+
+    /// Provides the list of protocols advertised during ALPN
+    /// negotiation, in order of preference. The first protocol that
+    /// the client also supports is selected.
+    ///
+    /// When the `h2` feature is enabled and this is not called, both
+    /// `h2` and `http/1.1` are advertised, preferring `h2`.
+    pub fn alpn_protocols<P: AsRef<[u8]>>(
+        mut self,
+        protocols: impl IntoIterator<Item = P>,
+    ) -> Self {
+        self.acceptor_options.alpn_protocols = Some(
+            protocols
+                .into_iter()
+                .map(|protocol| protocol.as_ref().to_vec())
+                .collect(),
+        );
+        self
+    }
+
+    /// Serves `config` to clients that ask for `hostname` through SNI,
+    /// with its own certificate, ALPN protocols and minimum TLS
+    /// version, so that virtual hosts on one listener can differ. The
+    /// host name is matched exactly, ignoring case. Clients asking for
+    /// any other host name, or for none, are served the listener's own
+    /// certificate and settings, which act as the default.
+    ///
+    /// Each host gets its own context, starting out from the
+    /// listener's [`TlsListenerBuilder::tls_profile`], or from
+    /// [`TlsProfile::MozillaModernV5`](crate::TlsProfile::MozillaModernV5)
+    /// with a custom profile, with the listener's DH parameters, client
+    /// CRL, ALPN protocols (unless the host has its own) and
+    /// [`TlsListenerBuilder::ssl_context_hook`] applied to it, and the
+    /// host's certificate, key and chain in place of the listener's.
+    ///
+    /// Settings that are fixed when a connection is accepted, before
+    /// the host name is known, stay those of the listener: the cipher
+    /// suites, protocol versions and groups on offer, the renegotiation
+    /// and record split options, the client certificate verification
+    /// mode, and the session cache and tickets. A custom profile's own
+    /// context settings are not carried over, as the builder only
+    /// makes one context: its trusted CAs and list of CA names for
+    /// client certificates, and its security level. A listener that
+    /// verifies client certificates against a custom profile's trusted
+    /// CAs therefore refuses every client certificate on a host,
+    /// unless its verify callback accepts them. Adding the same host
+    /// name twice replaces the earlier config.
+    ///
+    /// ```rust
+    /// # use tide_openssl::{HostConfig, TlsListener};
+    /// # fn main() -> std::io::Result<()> {
+    /// let grpc = HostConfig::build()
+    ///     .cert("./tls/localhost-4433.cert")
+    ///     .key("./tls/localhost-4433.key")
+    ///     .alpn_protocols(["h2"])
+    ///     .finish()?;
+    /// let api = HostConfig::build()
+    ///     .cert("./tls/localhost-4434.cert")
+    ///     .key("./tls/localhost-4434.key")
+    ///     .alpn_protocols(["http/1.1"])
+    ///     .finish()?;
+    ///
+    /// let listener = TlsListener::<()>::build()
+    ///     .addrs("localhost:4433")
+    ///     .cert("./tls/localhost-4433.cert")
+    ///     .key("./tls/localhost-4433.key")
+    ///     .add_host_config("grpc.example.com", grpc)
+    ///     .add_host_config("api.example.com", api)
+    ///     .finish();
+    /// # Ok(()) }
+    /// ```
+    pub fn add_host_config(mut self, hostname: &str, config: HostConfig) -> Self {
+        let hostname = hostname.to_ascii_lowercase();
+        let hosts = &mut self.acceptor_options.host_configs;
+        hosts.retain(|(existing, _)| *existing != hostname);
+        hosts.push((hostname, config));
+        self
+    }
+
+    /// Chooses whether the client's or the server's group ordering is
+    /// used when negotiating the key exchange curve. This is the
+    /// counterpart of server cipher preference for TLS 1.3 key shares.
+    ///
+    /// OpenSSL has a single server preference option for groups and
+    /// cipher suites, so [`CurvesPreference::Server`] also makes the
+    /// server's cipher suite ordering win. In TLS 1.3, depending on the
+    /// OpenSSL version, a key share the client already sent for a
+    /// supported group may be accepted rather than asking for the
+    /// server's preferred one.
+    ///
+    /// ```rust
+    /// # use tide_openssl::{CurvesPreference, TlsListener};
+    /// let listener = TlsListener::<()>::build()
+    ///     .addrs("localhost:4433")
+    ///     .cert("./tls/localhost-4433.cert")
+    ///     .key("./tls/localhost-4433.key")
+    ///     .ssl_curves_preference(CurvesPreference::Server)
+    ///     .ssl_groups("X25519:P-256")
+    ///     .finish();
+    /// ```
+    pub fn ssl_curves_preference(mut self, preference: CurvesPreference) -> Self {
+        self.acceptor_options.curves_preference = preference;
+        self
+    }
+
+    /// Provides the supported key exchange groups as a colon separated
+    /// OpenSSL group list, for example `"X25519:P-256"`.
+    pub fn ssl_groups(mut self, groups: impl Into<String>) -> Self {
+        self.acceptor_options.groups = Some(groups.into());
+        self
+    }
+
+    /// Selects the configuration the acceptor starts out from, before
+    /// the key material and the other options of this builder are
+    /// applied. By default this is [`TlsProfile::MozillaModernV5`].
+    ///
+    /// [`TlsProfile::Custom`] takes a pre-configured
+    /// [`SslAcceptorBuilder`](openssl::ssl::SslAcceptorBuilder), for
+    /// settings this builder does not cover, while keeping the tcp
+    /// options, cert and key handling and everything else here. It
+    /// only builds a single acceptor, so [`TlsListenerBuilder::finish`]
+    /// refuses it together with [`TlsListenerBuilder::invalidation_check`],
+    /// [`TlsListenerBuilder::cert_provider`] or
+    /// `TlsListenerBuilder::remote_configuration`, which rebuild the
+    /// acceptor.
+    ///
+    /// ```rust
+    /// # use openssl::ssl::{SslAcceptor, SslMethod, SslVersion};
+    /// # use tide_openssl::{TlsListener, TlsProfile};
+    /// let mut acceptor = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls()).unwrap();
+    /// acceptor.set_min_proto_version(Some(SslVersion::TLS1_2)).unwrap();
+    ///
+    /// let listener = TlsListener::<()>::build()
+    ///     .addrs("localhost:4433")
+    ///     .cert("./tls/localhost-4433.cert")
+    ///     .key("./tls/localhost-4433.key")
+    ///     .tls_profile(TlsProfile::Custom(acceptor))
+    ///     .finish();
+    /// ```
+    pub fn tls_profile(mut self, profile: TlsProfile) -> Self {
+        self.acceptor_options.profile = profile.into();
+        self
+    }
+
+    /// Builds the acceptor from Mozilla's intermediate profile instead
+    /// of the modern one, for compatibility with older browsers and
+    /// clients. This adds TLS 1.2 alongside TLS 1.3, together with a
+    /// wider list of cipher suites. See Mozilla's
+    /// [server side TLS guidelines](https://wiki.mozilla.org/Security/Server_Side_TLS)
+    /// for which clients need this.
+    ///
+    /// This reduces security, so a warning is logged whenever the
+    /// acceptor is built with it. Shorthand for
+    /// `tls_profile(TlsProfile::MozillaIntermediateV5)`.
+    pub fn mozilla_intermediate_v5(self) -> Self {
+        self.tls_profile(TlsProfile::MozillaIntermediateV5)
+    }
+
+    /// Switches between [`TlsProfile::MozillaIntermediateV5`] when
+    /// `enabled` and the default [`TlsProfile::MozillaModernV5`], see
+    /// [`TlsListenerBuilder::mozilla_intermediate_v5`]. A
+    /// [`TlsProfile::Custom`] profile is left in place when `enabled`
+    /// is `false`.
+    pub fn legacy_client_support(self, enabled: bool) -> Self {
+        match (&self.acceptor_options.profile, enabled) {
+            (_, true) => self.tls_profile(TlsProfile::MozillaIntermediateV5),
+            (BaseProfile::Custom(_), false) => self,
+            (_, false) => self.tls_profile(TlsProfile::MozillaModernV5),
+        }
+    }
+
+    /// Rejects clients whose ClientHello carries any extension whose
+    /// type is not in `allowed_extensions`, with an `illegal_parameter`
+    /// alert. This is meant for tightly controlled deployments where
+    /// the set of clients, and with it the extensions they send, is
+    /// known in advance. Anything else will break as soon as a client
+    /// library starts sending a new extension.
+    ///
+    /// GREASE values (RFC 8701) are always allowed, since clients pick
+    /// them at random. A ClientHello that cannot be parsed is rejected
+    /// with a `decode_error` alert. This does not apply to acceptors
+    /// built with [`TlsListenerBuilder::async_configure`].
+    ///
+    /// ```rust
+    /// # use tide_openssl::TlsListener;
+    /// let listener = TlsListener::<()>::build()
+    ///     .addrs("localhost:4433")
+    ///     .cert("./tls/localhost-4433.cert")
+    ///     .key("./tls/localhost-4433.key")
+    ///     // server_name, supported_groups, signature_algorithms,
+    ///     // supported_versions and key_share
+    ///     .tls_extension_filter(vec![0, 10, 13, 43, 51])
+    ///     .finish();
+    /// ```
+    pub fn tls_extension_filter(mut self, allowed_extensions: Vec<u16>) -> Self {
+        self.acceptor_options.allowed_tls_extensions = Some(allowed_extensions);
+        self.connection_options.record_client_hello = true;
+        self
+    }
+
+    /// Rejects clients whose [JA3](https://github.com/salesforce/ja3)
+    /// fingerprint is not in `fingerprints`, with an `illegal_parameter`
+    /// alert, as the `openssl` crate cannot send `handshake_failure`
+    /// from a ClientHello callback. The fingerprint identifies the TLS
+    /// library and settings a client uses, which suits internal APIs
+    /// where every client, such as a given release of a mobile app, is
+    /// known in advance.
+    ///
+    /// Fingerprints are given as lower case hex MD5 digests, the usual
+    /// way of writing them. Like with
+    /// [`TlsListenerBuilder::tls_extension_filter`], a ClientHello that
+    /// cannot be parsed is rejected with a `decode_error` alert, and
+    /// this does not apply to acceptors built with
+    /// [`TlsListenerBuilder::async_configure`].
+    ///
+    /// ```rust
+    /// # use tide_openssl::TlsListener;
+    /// let listener = TlsListener::<()>::build()
+    ///     .addrs("localhost:4433")
+    ///     .cert("./tls/localhost-4433.cert")
+    ///     .key("./tls/localhost-4433.key")
+    ///     .tls_fingerprint_allowlist(vec!["e7d705a3286e19ea42f587b344ee6865".into()])
+    ///     .finish();
+    /// ```
+    pub fn tls_fingerprint_allowlist(mut self, fingerprints: Vec<String>) -> Self {
+        let fingerprints = fingerprints
+            .into_iter()
+            .map(|fingerprint| fingerprint.to_ascii_lowercase())
+            .collect();
+        self.acceptor_options.allowed_ja3_fingerprints = Some(fingerprints);
+        self.connection_options.record_client_hello = true;
+        self
+    }
+
+    /// Writes the raw bytes each side sends during the TLS handshake to
+    /// a new file in `dir` for every connection, whether or not the
+    /// handshake succeeds. This is meant for debugging interoperability
+    /// problems with particular clients, and requires the `debug` cargo
+    /// feature.
+    ///
+    /// Files are named `connection-<pid>-<n>.hex`, numbering the
+    /// connections of the process, and hold the bytes of the client
+    /// and then the server as hex, which `xxd -r -p` turns back into
+    /// TLS records. At most the first 16 KiB in either direction are
+    /// kept. Files are written in the background, so the handshake is
+    /// not held up, and `dir` must already exist.
+    ///
+    /// ```rust
+    /// # use tide_openssl::TlsListener;
+    /// let listener = TlsListener::<()>::build()
+    ///     .addrs("localhost:4433")
+    ///     .cert("./tls/localhost-4433.cert")
+    ///     .key("./tls/localhost-4433.key")
+    ///     .debug_handshake_dump("/tmp/handshakes")
+    ///     .finish();
+    /// ```
+    #[cfg(feature = "debug")]
+    pub fn debug_handshake_dump(mut self, dir: impl AsRef<Path>) -> Self {
+        self.connection_options.handshake_dump_dir = Some(dir.as_ref().into());
+        self
+    }
+
+    /// Refuses client-initiated TLS 1.2 renegotiation, which has been
+    /// the basis of several attacks, such as the triple handshake. By
+    /// default OpenSSL allows secure renegotiation.
+    ///
+    /// TLS 1.3 has no renegotiation, so this only matters together with
+    /// [`TlsListenerBuilder::legacy_client_support`] or a
+    /// [`TlsProfile`] that enables TLS 1.2. A warning is logged when the
+    /// acceptor is built for TLS 1.3 only.
+    pub fn disable_renegotiation(mut self, disabled: bool) -> Self {
+        self.acceptor_options.disable_renegotiation = disabled;
+        self
+    }
+
+    /// Mitigates the BEAST attack on CBC cipher suites in TLS 1.0 by
+    /// having OpenSSL send an empty record ahead of each record of
+    /// application data, which keeps the attacker from predicting the
+    /// IV. This has the same effect as a 1/n-1 split. It is on by
+    /// default, as the openssl crate turns it on for every context, and
+    /// `false` turns it off for the rare old client that cannot handle
+    /// empty records. Unless this is called, a
+    /// [`TlsProfile::Custom`] decides.
+    ///
+    /// OpenSSL only does this for CBC suites on TLS 1.0 and older, so
+    /// this has no effect unless a [`TlsProfile::Custom`] enables
+    /// TLS 1.0. Neither [`TlsListenerBuilder::legacy_client_support`]
+    /// nor the modern profile do.
+    pub fn enable_tls_record_split(mut self, enabled: bool) -> Self {
+        self.acceptor_options.tls_record_split = Some(enabled);
+        self
+    }
+
+    /// Refuses to serve a certificate chain with more than `depth`
+    /// intermediate certificates, failing when the acceptor is built
+    /// instead. Long chains are valid, but slow down every client
+    /// that verifies them, and usually point at a misconfigured CA.
+    /// The default is 5.
+    ///
+    /// This checks the server's own chain, as loaded from
+    /// [`TlsListenerBuilder::cert`], [`TlsListenerBuilder::cert_chain`]
+    /// or [`TlsListenerBuilder::cert_pem`]. Acceptors that are built
+    /// elsewhere, with [`TlsListenerBuilder::async_configure`] or as a
+    /// [`TlsListenerConfig::PrebuiltAcceptor`], are not checked.
+    pub fn max_cert_chain_depth_validate(mut self, depth: u32) -> Self {
+        self.acceptor_options.max_cert_chain_depth = Some(depth);
+        self
+    }
+
+    /// Provide a path to a PEM file with Diffie-Hellman parameters,
+    /// as generated by `openssl dhparam`, for the DHE cipher suites of
+    /// TLS 1.2. [`TlsListenerBuilder::legacy_client_support`] already
+    /// uses the 2048-bit ffdhe2048 group from RFC 7919, which these
+    /// replace. They have no effect on TLS 1.3, which the default
+    /// Mozilla modern profile is limited to, and a warning is logged if
+    /// its minimum version is lowered without them, as DHE cipher
+    /// suites are then not negotiated.
+    pub fn dh_params(mut self, path: impl AsRef<Path>) -> Self {
+        self.acceptor_options.dh_params =
+            Some(DhParams::Custom(Material::Path(path.as_ref().into())));
+        self
+    }
+
+    /// Provide PEM encoded Diffie-Hellman parameters, see
+    /// [`TlsListenerBuilder::dh_params`]
+    pub fn dh_params_from_pem(mut self, pem: &[u8]) -> Self {
+        self.acceptor_options.dh_params = Some(DhParams::Custom(Material::Pem(pem.to_vec())));
+        self
+    }
+
+    /// Use the built-in 2048-bit MODP group from RFC 3526 as
+    /// Diffie-Hellman parameters, see [`TlsListenerBuilder::dh_params`]
+    pub fn use_default_dh_params(mut self) -> Self {
+        self.acceptor_options.dh_params = Some(DhParams::Rfc3526);
+        self
+    }
+
+    /// Provide a path to a PEM or DER file with a certificate
+    /// revocation list, against which client certificates are checked.
+    /// Revoked certificates, and certificates whose issuer has no CRL
+    /// loaded, fail verification during the handshake.
+    ///
+    /// This only matters for an acceptor that verifies client
+    /// certificates, as set up with [`TlsListenerBuilder::tls_profile`],
+    /// and does not apply to [`TlsListenerBuilder::async_configure`].
+    /// The file is read once, when the acceptor is built: a custom
+    /// profile only builds a single acceptor, so a replaced CRL is not
+    /// picked up until the listener is rebuilt.
+    pub fn client_crl(mut self, path: impl AsRef<Path>) -> Self {
+        self.acceptor_options.client_crl = Some(path.as_ref().into());
+        self
+    }
+
+    /// Checks the whole client certificate chain against the CRLs
+    /// given with [`TlsListenerBuilder::client_crl`], not only the
+    /// leaf certificate. Every CA in the chain then needs a CRL.
+    pub fn client_crl_check_all(mut self, enabled: bool) -> Self {
+        self.acceptor_options.client_crl_check_all = enabled;
+        self
+    }
+
+    /// Calls `f` with the context of every acceptor this listener
+    /// builds, once all configuration has been applied, to log or
+    /// check the settings that end up in effect, such as the served
+    /// certificate, the verify mode or the security level. This includes
+    /// acceptors from [`TlsListenerBuilder::async_configure`], those
+    /// rebuilt by [`TlsListenerBuilder::invalidation_check`] and the
+    /// contexts of hosts from [`TlsListenerBuilder::add_host_config`]. It runs
+    /// only while building, not per connection.
+    ///
+    /// ```rust
+    /// # use tide_openssl::TlsListener;
+    /// let listener = TlsListener::<()>::build()
+    ///     .addrs("localhost:4433")
+    ///     .cert("./tls/localhost-4433.cert")
+    ///     .key("./tls/localhost-4433.key")
+    ///     .ssl_context_hook(|context| {
+    ///         tide::log::info!("tls context built", {
+    ///             security_level: context.security_level(),
+    ///             verify_mode: format!("{:?}", context.verify_mode())
+    ///         });
+    ///     })
+    ///     .finish();
+    /// ```
+    pub fn ssl_context_hook(mut self, f: impl Fn(&SslContextRef) + Send + Sync + 'static) -> Self {
+        self.acceptor_options.context_hook = Some(ContextHook(Arc::new(f)));
+        self
+    }
+
+    /// Calls `f` every `check_interval`, and whenever it returns true
+    /// rebuilds the acceptor from the configured cert and key, which
+    /// are read again from disk. This is meant for incident response:
+    /// once a compromised key has been replaced on disk, flipping the
+    /// check makes the listener switch to the new material without a
+    /// restart.
+    ///
+    /// The new acceptor is swapped in atomically. Connections that are
+    /// already in flight finish on the old one, but no new handshake
+    /// uses it, and sessions cached by the old context cannot be
+    /// resumed against the new one.
+    ///
+    /// ```rust
+    /// # use tide_openssl::TlsListener;
+    /// use std::path::Path;
+    /// use std::time::Duration;
+    ///
+    /// let listener = TlsListener::<()>::build()
+    ///     .addrs("localhost:4433")
+    ///     .cert("./tls/localhost-4433.cert")
+    ///     .key("./tls/localhost-4433.key")
+    ///     .invalidation_check(
+    ///         || Path::new("./tls/invalidate").exists(),
+    ///         Duration::from_secs(5),
+    ///     )
+    ///     .finish();
+    /// ```
+    pub fn invalidation_check(
+        mut self,
+        f: impl Fn() -> bool + Send + Sync + 'static,
+        check_interval: Duration,
+    ) -> Self {
+        self.invalidation_check = Some((Arc::new(f), check_interval));
+        self
+    }
+
+    /// Fetches a JSON document from `url` every `poll_interval`, and
+    /// whenever it changes rebuilds the acceptor with the settings it
+    /// holds, for listeners managed by a central configuration
+    /// service. Requires the `remote-config` feature.
+    ///
+    /// The url must be `https`, verified against the system trust
+    /// store, as the document can replace the served key and lower the
+    /// protocol version. Plain `http` is only accepted for a loopback
+    /// host, such as a configuration agent on the same machine, and
+    /// [`TlsListenerBuilder::finish`] returns an error for any other
+    /// url. Up to five redirects are followed, each held to the same
+    /// rule. Proxies are not used. Each fetch must complete within ten
+    /// seconds, and documents larger than 1 MiB are refused.
+    ///
+    /// Every field of the document is optional:
+    ///
+    /// * `cert`, `key` and `cert_chain`: paths to PEM files, as with
+    ///   [`TlsListenerBuilder::cert`], [`TlsListenerBuilder::key`] and
+    ///   [`TlsListenerBuilder::cert_chain`]
+    /// * `cert_pem` and `key_pem`: PEM contents, as with
+    ///   [`TlsListenerBuilder::cert_pem`] and [`TlsListenerBuilder::key_pem`]
+    /// * `cipher_list` and `ciphersuites`: the TLS 1.2 and TLS 1.3
+    ///   cipher suites, in OpenSSL's format
+    /// * `min_protocol_version` and `max_protocol_version`: one of
+    ///   `TLSv1`, `TLSv1.1`, `TLSv1.2` and `TLSv1.3`
+    ///
+    /// Key material that is left out is the listener's own, and the
+    /// cipher and version settings default to those of the
+    /// [`TlsListenerBuilder::tls_profile`]. The acceptor is swapped in
+    /// as with [`TlsListenerBuilder::invalidation_check`], so
+    /// connections in flight are not interrupted. Documents that cannot
+    /// be fetched, parsed or applied are logged as errors, and the
+    /// listener keeps serving with the settings it has. An applied
+    /// document stays in place when the acceptor is rebuilt for another
+    /// reason, such as an invalidation check or a renewed certificate
+    /// from a [`TlsListenerBuilder::cert_provider`].
+    ///
+    /// ```rust
+    /// # use tide_openssl::TlsListener;
+    /// use std::time::Duration;
+    ///
+    /// let listener = TlsListener::<()>::build()
+    ///     .addrs("localhost:4433")
+    ///     .cert("./tls/localhost-4433.cert")
+    ///     .key("./tls/localhost-4433.key")
+    ///     .remote_configuration("https://config.internal/tls.json", Duration::from_secs(30))
+    ///     .finish();
+    /// ```
+    #[cfg(feature = "remote-config")]
+    pub fn remote_configuration(mut self, url: impl Into<String>, poll_interval: Duration) -> Self {
+        self.remote_config = Some(RemoteConfig {
+            url: url.into(),
+            interval: poll_interval,
+        });
+        self
+    }
+
+    /// Makes a TLS connection from the listener to itself every
+    /// `interval`, checking that the handshake succeeds and that the
+    /// served certificate is within its validity period. Failures are
+    /// logged as errors and passed to
+    /// [`TlsListenerBuilder::on_health_check_failure`]. This catches an
+    /// expired certificate or a broken acceptor before clients do.
+    ///
+    /// Listeners bound to all interfaces are reached through the
+    /// loopback interface. The check does not verify the chain, as the
+    /// issuing CA need not be trusted by the system. It goes through
+    /// the regular accept loop, so the loopback address must pass
+    /// [`TlsListenerBuilder::allow_ips`], but is left out of the TLS
+    /// stats, the audit log, slow handshake logging and
+    /// [`TlsListenerBuilder::session_cache_hit_callback`]. A check that
+    /// takes longer than `interval`, or ten seconds, fails.
+    ///
+    /// ```rust
+    /// # use tide_openssl::TlsListener;
+    /// use std::time::Duration;
+    ///
+    /// let listener = TlsListener::<()>::build()
+    ///     .addrs("localhost:4433")
+    ///     .cert("./tls/localhost-4433.cert")
+    ///     .key("./tls/localhost-4433.key")
+    ///     .health_tls_check(Duration::from_secs(60))
+    ///     .on_health_check_failure(|error| eprintln!("tls is down: {}", error))
+    ///     .finish();
+    /// ```
+    pub fn health_tls_check(mut self, interval: Duration) -> Self {
+        self.health_check.interval = Some(interval);
+        self
+    }
+
+    /// Calls `f` whenever a check enabled with
+    /// [`TlsListenerBuilder::health_tls_check`] fails.
+    pub fn on_health_check_failure(
+        mut self,
+        f: impl Fn(&io::Error) + Send + Sync + 'static,
+    ) -> Self {
+        let on_failure: HealthCheckFailure = Arc::new(f);
+        self.health_check.on_failure = Some(on_failure);
+        self
+    }
+
+    /// Logs a warning with the peer's IP address, the negotiated cipher
+    /// and TLS version, and the duration of every handshake that takes
+    /// longer than `threshold`. The time runs from accepting the tcp
+    /// connection to the completed handshake, so it includes network
+    /// round trips as well as certificate verification and crypto.
+    /// Handshakes that fail are logged as errors regardless.
+    pub fn log_slow_handshakes(mut self, threshold: Duration) -> Self {
+        self.connection_options.slow_handshake_threshold = Some(threshold);
+        self
+    }
+
+    /// Logs statistics aggregated over all connections every
+    /// `interval`, as an info level `tls stats` record with these
+    /// key-values:
+    ///
+    /// * `accepted`: connections accepted off the socket
+    /// * `rejected`: accepted connections that were closed by a filter
+    ///   such as [`TlsListenerBuilder::allow_ips`] rather than served
+    /// * `active`: connections currently being served
+    /// * `handshakes_succeeded` and `handshakes_failed`
+    /// * `handshake_success_rate`: the fraction of handshakes that
+    ///   succeeded
+    /// * `session_hit_rate`: the fraction of successful handshakes
+    ///   that resumed a cached session
+    ///
+    /// The counts are totals since the listener was bound. Logging
+    /// stops once the listener is dropped.
+    ///
+    /// The counts are not broken down by tag. There are no tags per
+    /// connection: [`TlsListenerBuilder::request_tagger`] tags each
+    /// request once it is parsed, which is after the connection has
+    /// been counted and its handshake completed, and one connection
+    /// can carry requests with different tags.
+    pub fn tls_stats_interval(mut self, interval: Duration) -> Self {
+        self.connection_options.stats_interval = Some(interval);
+        self
+    }
+
+    /// Sets how errors that end a connection, such as a failed
+    /// handshake, are logged. The default,
+    /// [`ErrorLogFormat::Structured`], logs the error as a key-value.
+    /// [`ErrorLogFormat::Json`] logs a JSON object with the error, the
+    /// peer address and a connection id as the message instead, for
+    /// log aggregation like ELK or Splunk.
+    pub fn error_log_format(mut self, format: ErrorLogFormat) -> Self {
+        self.connection_options.error_log_format = format;
+        self
+    }
+
+    /// Bounds how long tide may take to respond to a single request.
+    /// This only covers the handler, not the TLS handshake or reading
+    /// the request head. When it elapses, the client gets a
+    /// `503 Service Unavailable` with a `Retry-After` header and the
+    /// connection is closed.
+    pub fn handler_timeout(mut self, duration: Duration) -> Self {
+        self.connection_options.handler_timeout = Some(duration);
+        self
+    }
+
+    /// Rejects HTTP/1.1 requests with ambiguous framing or target with
+    /// a `400 Bad Request`, closing the connection. This covers
+    /// duplicate `Content-Length` or `Host` headers, a missing `Host`
+    /// and any `Transfer-Encoding` other than a single `chunked`.
+    /// Defaults to false. HTTP/2 framing is not ambiguous, so h2
+    /// connections are unaffected.
+    ///
+    /// # Limitations
+    ///
+    /// async-h1 does not offer a strict parsing mode, so these checks
+    /// run on the request it has already parsed, and anything it
+    /// normalizes away cannot be seen here. Lenient parsing matters
+    /// most behind a proxy: if the proxy and this server disagree on
+    /// where a request ends, an attacker can smuggle a second request
+    /// past the proxy. async-h1 already refuses a request carrying
+    /// both `Content-Length` and `Transfer-Encoding`. Finer control,
+    /// such as rejecting obsolete line folding or bare `LF` line
+    /// endings, would need support in async-h1 itself.
+    pub fn strict_http_parsing(mut self, enabled: bool) -> Self {
+        self.connection_options.strict_http_parsing = enabled;
+        self
+    }
+
+    /// Limits how many requests a client can send over a single
+    /// HTTP/1.1 keep-alive connection, pipelined or not. The response
+    /// to the `n`-th request carries `Connection: close`, and the
+    /// connection is closed once it has been written, so any requests
+    /// pipelined behind it are dropped. A client always gets at least
+    /// one response. There is no limit by default, and h2 connections
+    /// are unaffected.
+    ///
+    /// This counts every request over the lifetime of the connection,
+    /// not the requests in flight: async-h1 answers pipelined requests
+    /// one at a time, so a single connection never has more than one
+    /// request in the handler anyway.
+    pub fn max_requests_per_connection(mut self, n: usize) -> Self {
+        self.connection_options.max_requests_per_connection = Some(n);
+        self
+    }
+
+    /// Adds the attributes in `options` to every `Set-Cookie` header
+    /// a handler sends, so a cookie served over TLS is not leaked over
+    /// plain http even when the handler forgot to mark it. Cookies
+    /// that are already `Secure` are left as they are, and an existing
+    /// `SameSite` attribute is never overridden.
+    ///
+    /// ```rust
+    /// # use tide_openssl::{CookieSecurityOptions, TlsListener};
+    /// let listener = TlsListener::<()>::build()
+    ///     .addrs("localhost:4433")
+    ///     .cert("./tls/localhost-4433.cert")
+    ///     .key("./tls/localhost-4433.key")
+    ///     .cookie_security(CookieSecurityOptions::default());
+    /// ```
+    pub fn cookie_security(mut self, options: CookieSecurityOptions) -> Self {
+        self.connection_options.cookie_security = Some(options);
+        self
+    }
+
+    /// Reads the decrypted HTTP/1.1 stream through a buffer of `size`
+    /// bytes. OpenSSL hands out at most one TLS record per read, and a
+    /// record carries up to 16384 bytes, so a buffer of that size
+    /// takes in a full record at once where async-h1's own smaller
+    /// buffer would need several reads. Reads at least as large as the
+    /// buffer bypass it. There is no extra buffer by default, and h2
+    /// connections are unaffected.
+    ///
+    /// The size is fixed: the buffer is not sized from the record size
+    /// negotiated with each client, such as a smaller one agreed on
+    /// through the `max_fragment_length` extension.
+    pub fn protocol_buffer(mut self, size: usize) -> Self {
+        self.connection_options.protocol_buffer = Some(size);
+        self
+    }
+
+    /// Answers h2 requests whose body is larger than `bytes` with a
+    /// `413 Payload Too Large`. A request that declares a larger
+    /// `Content-Length` is refused before it reaches tide. Otherwise
+    /// the body is streamed to tide as it arrives, and once it goes
+    /// over the limit, reading it fails and the client gets a 413
+    /// whatever the handler answered. The default is 10 MiB. HTTP/1.1
+    /// bodies are unaffected.
+    #[cfg(feature = "h2")]
+    pub fn http2_max_request_body_size(mut self, bytes: usize) -> Self {
+        self.connection_options.http2_max_request_body_size = bytes;
+        self
+    }
+
+    /// Answers requests using any of `methods`, such as `TRACE`, with
+    /// a `405 Method Not Allowed` before they reach tide, so they are
+    /// refused whatever routes and middleware the app has. Methods are
+    /// matched case-insensitively, and no `Allow` header is sent since
+    /// the listener does not know the app's routes.
+    ///
+    /// ```rust
+    /// # use tide_openssl::TlsListener;
+    /// let listener = TlsListener::<()>::build()
+    ///     .addrs("localhost:4433")
+    ///     .cert("./tls/localhost-4433.cert")
+    ///     .key("./tls/localhost-4433.key")
+    ///     .disable_http_methods(vec!["TRACE", "OPTIONS"]);
+    /// ```
+    pub fn disable_http_methods(
+        mut self,
+        methods: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.connection_options.disabled_http_methods = methods
+            .into_iter()
+            .map(|method| method.into().to_ascii_uppercase())
+            .collect();
+        self
+    }
+
+    /// Requires every request to carry an HMAC of its body, made with
+    /// `key`, as hex in an `X-Signature` header. Requests with a
+    /// missing or wrong signature are answered with a
+    /// `401 Unauthorized` before they reach tide. The HMAC uses SHA-256
+    /// unless [`TlsListenerBuilder::request_signing_digest`] is set,
+    /// and the header can be changed with
+    /// [`TlsListenerBuilder::request_signing_header`].
+    ///
+    /// The header is checked before the body is read. The body is then
+    /// read into memory to be verified, so this is meant for APIs with
+    /// small request bodies: bodies over 1 MiB, or the size set with
+    /// [`TlsListenerBuilder::request_signing_max_body_size`], are
+    /// answered with a `413 Payload Too Large`.
+    ///
+    /// ```rust
+    /// # use tide_openssl::{SigningDigest, TlsListener};
+    /// let listener = TlsListener::<()>::build()
+    ///     .addrs("localhost:4433")
+    ///     .cert("./tls/localhost-4433.cert")
+    ///     .key("./tls/localhost-4433.key")
+    ///     .request_signing_key(&[7; 32])
+    ///     .request_signing_digest(SigningDigest::Sha512);
+    /// ```
+    pub fn request_signing_key(mut self, key: &[u8; 32]) -> Self {
+        self.connection_options.request_signing.key = Some(key.to_vec());
+        self
+    }
+
+    /// The header holding the signature checked with
+    /// [`TlsListenerBuilder::request_signing_key`]. Defaults to
+    /// `X-Signature`.
+    pub fn request_signing_header(mut self, header_name: impl Into<String>) -> Self {
+        self.connection_options.request_signing.header = header_name.into();
+        self
+    }
+
+    /// The hash function of the HMAC checked with
+    /// [`TlsListenerBuilder::request_signing_key`]. Defaults to SHA-256.
+    pub fn request_signing_digest(mut self, digest: SigningDigest) -> Self {
+        self.connection_options.request_signing.digest = digest;
+        self
+    }
+
+    /// The largest request body read to check the signature required
+    /// by [`TlsListenerBuilder::request_signing_key`], in bytes.
+    /// Requests whose `Content-Length`, or body, is larger are answered
+    /// with a `413 Payload Too Large`. Defaults to 1 MiB.
+    pub fn request_signing_max_body_size(mut self, bytes: usize) -> Self {
+        self.connection_options.request_signing.max_body_size = bytes;
+        self
+    }
+
+    /// Writes an [`AuditEntry`](crate::AuditEntry) for every accepted
+    /// connection to `writer` once it closes, including connections
+    /// whose handshake failed. Each entry lists the negotiated
+    /// protocol and cipher, the client certificate subject if there
+    /// was one, the requests served and the bytes transferred. See
+    /// [`JsonLineAuditWriter`](crate::JsonLineAuditWriter) for a
+    /// ready-made writer.
+    pub fn connection_audit_log(
+        mut self,
+        writer: Arc<dyn ConnectionAuditWriter + Send + Sync>,
+    ) -> Self {
+        self.connection_options.audit_writer = Some(writer);
+        self
+    }
+
+    /// Refuses to serve a connection if, after the handshake, the
+    /// ServerHello turns out to have selected TLS compression.
+    ///
+    /// Compression would expose clients to CRIME style attacks. The
+    /// acceptor already disables it, so this should never trigger.
+    /// It is a defense in depth check for the case where that
+    /// option is not honored, for example because of an OpenSSL build
+    /// or version change. If the ServerHello cannot be inspected, the
+    /// connection is closed as well.
+    pub fn assert_no_compression(mut self, enabled: bool) -> Self {
+        self.connection_options.assert_no_compression = enabled;
+        self
+    }
+
+    /// Computes tags for every request, such as a tenant or API
+    /// version, as soon as it has been parsed and before it reaches
+    /// tide. The tags are attached to the request as a
+    /// [`RequestTags`](crate::RequestTags) extension so that logging
+    /// middleware and endpoints can include them in their records.
+    ///
+    /// `tide::log` has no scoped context, so the tags are not added to
+    /// log records automatically.
+    ///
+    /// ```rust
+    /// # use tide_openssl::{RequestTags, TlsListener};
+    /// let mut app = tide::new();
+    /// app.at("/").get(|req: tide::Request<()>| async move {
+    ///     let tags = req.ext::<RequestTags>().cloned().unwrap_or_default();
+    ///     Ok(format!("hello {}", tags.get("tenant").unwrap_or("stranger")))
+    /// });
+    ///
+    /// let listener = TlsListener::<()>::build()
+    ///     .addrs("localhost:4433")
+    ///     .cert("./tls/localhost-4433.cert")
+    ///     .key("./tls/localhost-4433.key")
+    ///     .request_tagger(|req| {
+    ///         req.header("x-tenant")
+    ///             .map(|tenant| vec![("tenant".into(), tenant.as_str().into())])
+    ///             .unwrap_or_default()
+    ///     })
+    ///     .finish();
+    /// ```
+    pub fn request_tagger(
+        mut self,
+        f: impl Fn(&tide::http::Request) -> Vec<(String, String)> + Send + Sync + 'static,
+    ) -> Self {
+        let tagger: RequestTagger = Arc::new(f);
+        self.connection_options.request_tagger = Some(tagger);
+        self
+    }
+
+    /// Calls `f` after every successful handshake with the peer address
+    /// and whether the session was resumed, rather than negotiated from
+    /// scratch. Tracking the share of resumed sessions shows whether
+    /// the session cache is working as intended.
+    ///
+    /// `f` runs on the connection's task before any request is read,
+    /// so it should return quickly.
+    ///
+    /// ```rust
+    /// # use tide_openssl::TlsListener;
+    /// use std::sync::atomic::{AtomicU64, Ordering};
+    /// use std::sync::Arc;
+    ///
+    /// let resumed = Arc::new(AtomicU64::new(0));
+    /// let counter = resumed.clone();
+    /// let listener = TlsListener::<()>::build()
+    ///     .addrs("localhost:4433")
+    ///     .cert("./tls/localhost-4433.cert")
+    ///     .key("./tls/localhost-4433.key")
+    ///     .session_cache_hit_callback(move |_, reused| {
+    ///         if reused {
+    ///             counter.fetch_add(1, Ordering::Relaxed);
+    ///         }
+    ///     })
+    ///     .finish();
+    /// ```
+    pub fn session_cache_hit_callback(
+        mut self,
+        f: impl Fn(SocketAddr, bool) + Send + Sync + 'static,
+    ) -> Self {
+        self.connection_options.session_reuse_callback = Some(Arc::new(f));
         self
     }
+}
 
+impl<State> TlsListenerBuilder<State, Yes, Yes, Yes> {
     /// finishes building a TlsListener from this TlsListenerBuilder.
+    /// This is only available once a certificate, a key and an address
+    /// have been provided.
     ///
     /// # Errors
     ///
     /// this will return an error unless all of the following conditions are met:
-    /// * either of these is provided, but not both
+    /// * exactly one of these is provided
     ///   * [`TlsListenerBuilder::tcp`]
     ///   * [`TlsListenerBuilder::addrs`]
+    ///   * `TlsListenerBuilder::unix_path`, with the `unix` feature
     /// * exactly one of these is provided
     ///   * both [`TlsListenerBuilder::cert`] AND [`TlsListenerBuilder::key`]
+    ///   * both [`TlsListenerBuilder::cert_pem`] AND [`TlsListenerBuilder::key_pem`]
     ///   * [`TlsListenerBuilder::config`]
-    ///   * [`TlsListenerBuilder::tls_acceptor`]
-    pub fn finish(self) -> io::Result<TlsListener<State>> {
+    ///   * [`TlsListenerBuilder::async_configure`]
+    ///   * [`TlsListenerBuilder::cert_provider`]
+    /// * the certificate, key and any other key material can be read
+    ///   and the key matches the certificate
+    /// * a [`TlsProfile::Custom`] is not combined with an option that
+    ///   rebuilds the acceptor, see [`TlsListenerBuilder::tls_profile`]
+    ///
+    /// Except with [`TlsListenerBuilder::async_configure`] and
+    /// [`TlsListenerBuilder::cert_provider`], which only run once the
+    /// listener is bound, the acceptor is built here, so configuration
+    /// mistakes surface at startup rather than on the first connection.
+    ///
+    /// Calling this on a builder that is missing any of the three does
+    /// not compile:
+    ///
+    /// ```rust,compile_fail
+    /// # use tide_openssl::TlsListener;
+    /// let listener = TlsListener::<()>::build().finish();
+    /// ```
+    pub fn finish(mut self) -> io::Result<TlsListener<State>> {
+        let config = self.take_config()?;
+        let connection = self.take_connection()?;
         let Self {
-            key,
-            cert,
-            // config,
             // tls_acceptor,
-            tcp,
-            addrs,
-            tcp_nodelay,
-            tcp_ttl,
+            tcp_options,
+            acceptor_options,
+            connection_options,
+            invalidation_check,
+            health_check,
+            #[cfg(feature = "remote-config")]
+            remote_config,
             ..
         } = self;
 
-        // For now, support configuration with key and cert files only
-        let config = match (key, cert) {
-            (Some(key), Some(cert)) => TlsListenerConfig::Paths { key, cert },
-            // (None, None, Some(config), None) => TlsListenerConfig::ServerConfig(config),
-            // (None, None, None, Some(tls_acceptor)) => TlsListenerConfig::Acceptor(tls_acceptor),
-            _ => {
-                return Err(io::Error::new(
-                    io::ErrorKind::InvalidInput,
-                    "need exactly one of cert + key, ServerConfig, or TLS acceptor",
-                ))
-            }
+        #[cfg(feature = "remote-config")]
+        if let Some(remote_config) = &remote_config {
+            remote_config.check_url()?;
+        }
+        let rebuilds =
+            invalidation_check.is_some() || matches!(config, AcceptorSource::Provider(_));
+        #[cfg(feature = "remote-config")]
+        let rebuilds = rebuilds || remote_config.is_some();
+        let uses_profile = !matches!(
+            config,
+            AcceptorSource::AsyncFactory(_)
+                | AcceptorSource::Config(TlsListenerConfig::PrebuiltAcceptor(_))
+        );
+        if rebuilds && uses_profile && acceptor_options.builds_once() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "a custom tls profile cannot be combined with invalidation_check, cert_provider or remote_configuration, which rebuild the acceptor",
+            ));
+        }
+        let acceptor = match &config {
+            AcceptorSource::Config(config) => Some(config.build_acceptor(&acceptor_options)?),
+            AcceptorSource::AsyncFactory(_) | AcceptorSource::Provider(_) => None,
         };
 
-        let connection = match (tcp, addrs) {
-            (Some(tcp), None) => TcpConnection::Connected(tcp),
-            (None, Some(addrs)) => TcpConnection::Addrs(addrs),
-            _ => {
-                return Err(io::Error::new(
-                    io::ErrorKind::InvalidInput,
-                    "either tcp or addrs are required",
-                ))
-            }
-        };
+        let listener = TlsListener::new(
+            connection,
+            config,
+            acceptor,
+            tcp_options,
+            acceptor_options,
+            connection_options,
+            invalidation_check,
+        )
+        .with_health_check(health_check);
+        #[cfg(feature = "remote-config")]
+        let listener = listener.with_remote_config(remote_config);
+        Ok(listener)
+    }
+}
+
+impl<State, A: Marker> TlsListenerBuilder<State, Yes, Yes, A> {
+    /// Builds the acceptor this builder would serve with, without
+    /// binding or even requiring an address. This is meant for using
+    /// the acceptor outside of tide, or for testing its configuration
+    /// in isolation. Options that act on connections rather than on
+    /// the acceptor, such as [`TlsListenerBuilder::allow_ips`], have
+    /// no effect here.
+    ///
+    /// # Errors
+    ///
+    /// The same as for [`TlsListenerBuilder::finish`], except that no
+    /// address is needed. Acceptors from
+    /// [`TlsListenerBuilder::async_configure`] are built by awaiting
+    /// the factory, so this returns an error for them.
+    ///
+    /// ```rust,no_run
+    /// # use tide_openssl::TlsListener;
+    /// # fn main() -> std::io::Result<()> {
+    /// let acceptor = TlsListener::<()>::build()
+    ///     .cert("./tls/localhost-4433.cert")
+    ///     .key("./tls/localhost-4433.key")
+    ///     .build_acceptor()?;
+    /// assert!(acceptor.context().certificate().is_some());
+    /// # Ok(()) }
+    /// ```
+    pub fn build_acceptor(mut self) -> io::Result<SslAcceptor> {
+        match self.take_config()? {
+            AcceptorSource::AsyncFactory(_) => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "acceptors from async_configure cannot be built synchronously",
+            )),
+            AcceptorSource::Provider(_) => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "acceptors from cert_provider cannot be built synchronously",
+            )),
+            AcceptorSource::Config(config) => config.build_acceptor(&self.acceptor_options),
+        }
+    }
 
-        Ok(TlsListener::new(connection, config, tcp_nodelay, tcp_ttl))
+    /// Checks that the private key belongs to the certificate, which
+    /// [`TlsListenerBuilder::finish`] also checks.
+    ///
+    /// Only the certificate and key are loaded, so this can be called
+    /// before the rest of the builder is set up. Key material from
+    /// [`TlsListenerBuilder::async_configure`] and
+    /// [`TlsListenerBuilder::cert_provider`] is only available once the
+    /// listener is bound, and is not checked.
+    ///
+    /// ```rust,no_run
+    /// # use tide_openssl::TlsListener;
+    /// # fn main() -> std::io::Result<()> {
+    /// let listener = TlsListener::<()>::build()
+    ///     .cert("./tls/localhost-4433.cert")
+    ///     .key("./tls/localhost-4433.key")
+    ///     .verify_cert_and_key()?
+    ///     .addrs("localhost:4433")
+    ///     .finish()?;
+    /// # Ok(()) }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// The same as [`TlsListenerBuilder::finish`] for missing or
+    /// unreadable key material, and an error of kind
+    /// [`InvalidInput`](io::ErrorKind::InvalidInput) when the key does
+    /// not match the certificate.
+    pub fn verify_cert_and_key(self) -> io::Result<Self> {
+        if let AcceptorSource::Config(config) = self.clone().take_config()? {
+            config.check_private_key()?;
+        }
+        Ok(self)
+    }
+}
+
+impl<State, C: Marker, K: Marker, A: Marker> TlsListenerBuilder<State, C, K, A> {
+    /// Takes the socket out of the builder, checking that exactly one
+    /// was provided.
+    fn take_connection(&mut self) -> io::Result<Connection> {
+        #[cfg(feature = "unix")]
+        let unix_path = self.unix_path.take();
+        #[cfg(not(feature = "unix"))]
+        let unix_path: Option<PathBuf> = None;
+
+        match (self.tcp.take(), self.addrs.take(), unix_path) {
+            (Some(tcp), None, None) => Ok(Connection::Tcp(tcp)),
+            (None, Some(addrs), None) => Ok(Connection::Addrs(addrs)),
+            #[cfg(feature = "unix")]
+            (None, None, Some(path)) => Ok(Connection::UnixPath(path)),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "either tcp or addrs are required",
+            )),
+        }
+    }
+
+    /// Takes the key material out of the builder, checking that
+    /// exactly one way of providing it was used.
+    fn take_config(&mut self) -> io::Result<AcceptorSource> {
+        let config = (
+            self.key.take(),
+            self.cert.take(),
+            self.cert_chain.take(),
+            self.key_pem.take(),
+            self.cert_pem.take(),
+            self.acceptor_factory.take(),
+            self.cert_provider.take(),
+            self.config.take(),
+        );
+        match config {
+            (Some(key), Some(cert), chain, None, None, None, None, None) => {
+                Ok(AcceptorSource::Config(TlsListenerConfig::Paths {
+                    key,
+                    cert,
+                    chain,
+                }))
+            }
+            (None, None, None, Some(key), Some(cert), None, None, None) => {
+                Ok(AcceptorSource::Config(TlsListenerConfig::Pem { key, cert }))
+            }
+            (None, None, None, None, None, Some(factory), None, None) => {
+                Ok(AcceptorSource::AsyncFactory(factory))
+            }
+            (None, None, None, None, None, None, Some(provider), None) => {
+                Ok(AcceptorSource::Provider(ProviderSource::new(provider)))
+            }
+            (None, None, None, None, None, None, None, Some(config)) => {
+                Ok(AcceptorSource::Config(config))
+            }
+            // (None, None, Some(config), None) => TlsListenerConfig::ServerConfig(config),
+            // (None, None, None, Some(tls_acceptor)) => TlsListenerConfig::Acceptor(tls_acceptor),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "need exactly one of cert + key, cert_pem + key_pem, config, async_configure or cert_provider",
+            )),
+        }
     }
 }
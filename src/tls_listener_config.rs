@@ -1,30 +1,342 @@
+use crate::{ProviderSource, TlsAcceptorOptions};
+use async_std::io;
+use openssl::pkey::PKey;
+use openssl::ssl::{SslAcceptor, SslAcceptorBuilder, SslContext, SslFiletype, SslMethod};
+use openssl::x509::X509;
+
 use std::fmt::{self, Debug, Formatter};
+use std::future::Future;
+use std::hash::{Hash, Hasher};
 
 use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
 
-impl Default for TlsListenerConfig {
-    fn default() -> Self {
-        Self::Unconfigured
-    }
-}
-pub(crate) enum TlsListenerConfig {
-    Unconfigured,
+/// A factory producing a fully configured [`SslAcceptor`], see
+/// [`TlsListenerBuilder::async_configure`](crate::TlsListenerBuilder::async_configure).
+pub(crate) type AcceptorFactory = Arc<
+    dyn Fn() -> Pin<Box<dyn Future<Output = io::Result<SslAcceptor>> + Send>>
+        + Send
+        + Sync
+        + 'static,
+>;
+
+/// See [`TlsListenerBuilder::invalidation_check`](crate::TlsListenerBuilder::invalidation_check).
+pub(crate) type InvalidationCheck = Arc<dyn Fn() -> bool + Send + Sync + 'static>;
+
+/// The key material a listener serves, see
+/// [`TlsListenerBuilder::config`](crate::TlsListenerBuilder::config).
+///
+/// Configs compare and hash by what they hold, so that listeners built
+/// from the same material can be deduplicated, for instance as the key
+/// of a `HashMap`. Paths are compared as paths, not by the contents of
+/// the files they point to. A prebuilt acceptor is only equal to a
+/// clone of itself.
+#[derive(Clone)]
+pub enum TlsListenerConfig {
     // Acceptor(Arc<dyn CustomTlsAcceptor>),
     // ServerConfig(ServerConfig),
-    Paths { cert: PathBuf, key: PathBuf },
+    /// Paths to PEM files, as with [`TlsListenerBuilder::cert`](crate::TlsListenerBuilder::cert),
+    /// [`TlsListenerBuilder::key`](crate::TlsListenerBuilder::key) and
+    /// [`TlsListenerBuilder::cert_chain`](crate::TlsListenerBuilder::cert_chain).
+    Paths {
+        /// The certificate, followed by its chain unless `chain` is set
+        cert: PathBuf,
+        /// The private key
+        key: PathBuf,
+        /// The intermediate certificates
+        chain: Option<PathBuf>,
+    },
+    /// PEM contents, as with [`TlsListenerBuilder::cert_pem`](crate::TlsListenerBuilder::cert_pem)
+    /// and [`TlsListenerBuilder::key_pem`](crate::TlsListenerBuilder::key_pem).
+    Pem {
+        /// The certificate chain, leaf first
+        cert: String,
+        /// The private key
+        key: String,
+    },
+    /// A single DER encoded certificate and its DER encoded private
+    /// key, for material that is not kept as PEM, such as from a key
+    /// store.
+    Der {
+        /// The certificate
+        cert: Vec<u8>,
+        /// The private key
+        key: Vec<u8>,
+    },
+    /// An acceptor that is served as is. Options of the builder that
+    /// configure the acceptor, such as
+    /// [`TlsListenerBuilder::tls_profile`](crate::TlsListenerBuilder::tls_profile),
+    /// are not applied to it.
+    PrebuiltAcceptor(SslAcceptor),
 }
 
 impl Debug for TlsListenerConfig {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
-            Self::Unconfigured => write!(f, "TlsListenerConfig::Unconfigured"),
             // Self::Acceptor(_) => write!(f, "TlsListenerConfig::Acceptor(..)"),
             // Self::ServerConfig(_) => write!(f, "TlsListenerConfig::ServerConfig(..)"),
-            Self::Paths { cert, key } => f
+            Self::Paths { cert, key, chain } => f
                 .debug_struct("TlsListenerConfig::Paths")
                 .field("cert", cert)
                 .field("key", key)
+                .field("chain", chain)
+                .finish(),
+            Self::Pem { .. } => f
+                .debug_struct("TlsListenerConfig::Pem")
+                .field("cert", &"..")
+                .field("key", &"<redacted>")
                 .finish(),
+            Self::Der { .. } => f
+                .debug_struct("TlsListenerConfig::Der")
+                .field("cert", &"..")
+                .field("key", &"<redacted>")
+                .finish(),
+            Self::PrebuiltAcceptor(_) => write!(f, "TlsListenerConfig::PrebuiltAcceptor(..)"),
+        }
+    }
+}
+
+impl PartialEq for TlsListenerConfig {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                Self::Paths { cert, key, chain },
+                Self::Paths {
+                    cert: other_cert,
+                    key: other_key,
+                    chain: other_chain,
+                },
+            ) => cert == other_cert && key == other_key && chain == other_chain,
+            (
+                Self::Pem { cert, key },
+                Self::Pem {
+                    cert: other_cert,
+                    key: other_key,
+                },
+            ) => cert == other_cert && key == other_key,
+            (
+                Self::Der { cert, key },
+                Self::Der {
+                    cert: other_cert,
+                    key: other_key,
+                },
+            ) => cert == other_cert && key == other_key,
+            (Self::PrebuiltAcceptor(acceptor), Self::PrebuiltAcceptor(other)) => {
+                std::ptr::eq(acceptor.context(), other.context())
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Eq for TlsListenerConfig {}
+
+impl Hash for TlsListenerConfig {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Self::Paths { cert, key, chain } => {
+                cert.hash(state);
+                key.hash(state);
+                chain.hash(state);
+            }
+            Self::Pem { cert, key } => {
+                cert.hash(state);
+                key.hash(state);
+            }
+            Self::Der { cert, key } => {
+                cert.hash(state);
+                key.hash(state);
+            }
+            Self::PrebuiltAcceptor(acceptor) => std::ptr::hash(acceptor.context(), state),
+        }
+    }
+}
+
+/// Where a listener gets its acceptor from.
+#[derive(Clone)]
+pub(crate) enum AcceptorSource {
+    Config(TlsListenerConfig),
+    AsyncFactory(AcceptorFactory),
+    Provider(ProviderSource),
+}
+
+impl Debug for AcceptorSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Config(config) => Debug::fmt(config, f),
+            Self::AsyncFactory(_) => write!(f, "AcceptorSource::AsyncFactory(..)"),
+            Self::Provider(_) => write!(f, "AcceptorSource::Provider(..)"),
+        }
+    }
+}
+
+impl AcceptorSource {
+    /// Loads the key material and builds a fresh acceptor. This reads
+    /// any files again, so it also serves to pick up replaced certs.
+    pub(crate) async fn build_acceptor(
+        &self,
+        options: &TlsAcceptorOptions,
+    ) -> io::Result<SslAcceptor> {
+        match self {
+            Self::AsyncFactory(factory) => {
+                let acceptor = factory().await?;
+                options.inspect(&acceptor);
+                Ok(acceptor)
+            }
+            Self::Provider(source) => source.build_acceptor(options).await,
+            Self::Config(config) => config.build_acceptor(options),
+        }
+    }
+}
+
+impl TlsListenerConfig {
+    /// Loads the certificate and private key on their own, without
+    /// the options of an acceptor, and checks that they belong
+    /// together.
+    pub(crate) fn check_private_key(&self) -> io::Result<()> {
+        let (cert, key) = match self {
+            TlsListenerConfig::Paths { cert, key, .. } => (
+                leaf_certificate(&std::fs::read(cert)?)?,
+                PKey::private_key_from_pem(&std::fs::read(key)?).map_err(io::Error::other)?,
+            ),
+            TlsListenerConfig::Pem { cert, key } => (
+                leaf_certificate(cert.as_bytes())?,
+                PKey::private_key_from_pem(key.as_bytes()).map_err(io::Error::other)?,
+            ),
+            TlsListenerConfig::Der { cert, key } => (
+                X509::from_der(cert).map_err(io::Error::other)?,
+                PKey::private_key_from_der(key).map_err(io::Error::other)?,
+            ),
+            TlsListenerConfig::PrebuiltAcceptor(acceptor) => {
+                // openssl drops a key that does not match the certificate
+                let context = acceptor.context();
+                match (context.certificate(), context.private_key()) {
+                    (Some(cert), Some(key)) => (cert.to_owned(), key.to_owned()),
+                    _ => {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            "prebuilt acceptor has no matching certificate and private key",
+                        ))
+                    }
+                }
+            }
+        };
+
+        let mut context = SslContext::builder(SslMethod::tls()).map_err(io::Error::other)?;
+        context
+            .set_certificate(&cert)
+            .and_then(|_| context.set_private_key(&key))
+            .and_then(|_| context.check_private_key())
+            .map_err(|error| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("private key does not match the certificate: {}", error),
+                )
+            })
+    }
+
+    /// Builds the acceptor, reading any files again.
+    pub(crate) fn build_acceptor(&self, options: &TlsAcceptorOptions) -> io::Result<SslAcceptor> {
+        if let TlsListenerConfig::PrebuiltAcceptor(acceptor) = self {
+            options.inspect(acceptor);
+            return Ok(acceptor.clone());
         }
+
+        let mut acceptor = options.base_acceptor()?;
+        self.load_into(&mut acceptor, options)?;
+        options.apply(&mut acceptor)?;
+        let acceptor = acceptor.build();
+        options.inspect(&acceptor);
+        Ok(acceptor)
     }
+
+    /// Sets the certificate, its chain and the private key on
+    /// `acceptor`, reading any files again.
+    pub(crate) fn load_into(
+        &self,
+        acceptor: &mut SslAcceptorBuilder,
+        options: &TlsAcceptorOptions,
+    ) -> io::Result<()> {
+        match self {
+            TlsListenerConfig::Paths { cert, key, chain } => {
+                acceptor
+                    .set_private_key_file(key, SslFiletype::PEM)
+                    .map_err(io::Error::other)?;
+                match chain {
+                    Some(chain) => {
+                        acceptor
+                            .set_certificate_file(cert, SslFiletype::PEM)
+                            .map_err(io::Error::other)?;
+                        let chain = std::fs::read(chain)?;
+                        let chain = X509::stack_from_pem(&chain).map_err(io::Error::other)?;
+                        options.check_chain_depth(chain.len())?;
+                        for cert in chain {
+                            acceptor
+                                .add_extra_chain_cert(cert)
+                                .map_err(io::Error::other)?;
+                        }
+                    }
+                    None => {
+                        let chain = std::fs::read(cert)?;
+                        let chain = X509::stack_from_pem(&chain).map_err(io::Error::other)?;
+                        options.check_chain_depth(chain.len().saturating_sub(1))?;
+                        acceptor
+                            .set_certificate_chain_file(cert)
+                            .map_err(io::Error::other)?
+                    }
+                }
+            }
+            TlsListenerConfig::Pem { cert, key } => {
+                let mut chain = X509::stack_from_pem(cert.as_bytes())
+                    .map_err(io::Error::other)?
+                    .into_iter();
+                let leaf = chain.next().ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidInput, "no certificate in cert pem")
+                })?;
+                options.check_chain_depth(chain.len())?;
+                let key = PKey::private_key_from_pem(key.as_bytes()).map_err(io::Error::other)?;
+                acceptor
+                    .set_private_key(&key)
+                    .and_then(|_| acceptor.set_certificate(&leaf))
+                    .map_err(io::Error::other)?;
+                for cert in chain {
+                    acceptor
+                        .add_extra_chain_cert(cert)
+                        .map_err(io::Error::other)?;
+                }
+            }
+            TlsListenerConfig::Der { cert, key } => {
+                let cert = X509::from_der(cert).map_err(io::Error::other)?;
+                let key = PKey::private_key_from_der(key).map_err(io::Error::other)?;
+                acceptor
+                    .set_private_key(&key)
+                    .and_then(|_| acceptor.set_certificate(&cert))
+                    .map_err(io::Error::other)?;
+            }
+            TlsListenerConfig::PrebuiltAcceptor(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "a prebuilt acceptor has no key material to load",
+                ))
+            }
+        }
+        // openssl drops a key that does not match the certificate
+        acceptor.check_private_key().map_err(|error| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("private key does not match the certificate: {}", error),
+            )
+        })
+    }
+}
+
+/// The first certificate of a PEM encoded chain.
+fn leaf_certificate(pem: &[u8]) -> io::Result<X509> {
+    X509::stack_from_pem(pem)
+        .map_err(io::Error::other)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no certificate in cert pem"))
 }
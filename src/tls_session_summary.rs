@@ -0,0 +1,25 @@
+use openssl::ssl::SslRef;
+
+/// The protocol and cipher negotiated by the most recent handshake,
+/// see [`TlsListener::tls_summary`](crate::TlsListener::tls_summary).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsSessionSummary {
+    /// The protocol version, such as `TLSv1.3`
+    pub tls_version: String,
+    /// The cipher suite, in OpenSSL's naming, such as
+    /// `TLS_AES_256_GCM_SHA384`
+    pub cipher: String,
+}
+
+impl TlsSessionSummary {
+    pub(crate) fn new(ssl: &SslRef) -> Self {
+        Self {
+            tls_version: ssl.version_str().to_string(),
+            cipher: ssl
+                .current_cipher()
+                .map(|cipher| cipher.name())
+                .unwrap_or("(NONE)")
+                .to_string(),
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use crate::logging;
+use async_std::task;
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+/// Counters aggregated over all connections of a listener, see
+/// [`TlsListenerBuilder::tls_stats_interval`](crate::TlsListenerBuilder::tls_stats_interval).
+#[derive(Debug, Default)]
+pub(crate) struct TlsStats {
+    accepted: AtomicU64,
+    rejected: AtomicU64,
+    active: AtomicU64,
+    handshakes_succeeded: AtomicU64,
+    handshakes_failed: AtomicU64,
+    sessions_reused: AtomicU64,
+}
+
+/// Counts a connection as active until dropped.
+#[derive(Debug)]
+pub(crate) struct ActiveConnection(Arc<TlsStats>);
+
+impl Drop for ActiveConnection {
+    fn drop(&mut self) {
+        self.0.active.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl TlsStats {
+    /// A connection was accepted off the socket.
+    pub(crate) fn accepted(&self) {
+        self.accepted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// An accepted connection was closed by a filter rather than
+    /// served, such as [`TlsListenerBuilder::allow_ips`](crate::TlsListenerBuilder::allow_ips).
+    pub(crate) fn rejected(&self) {
+        self.rejected.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn handshake_failed(&self) {
+        self.handshakes_failed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn handshake_succeeded(&self, session_reused: bool) {
+        self.handshakes_succeeded.fetch_add(1, Ordering::Relaxed);
+        if session_reused {
+            self.sessions_reused.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn connection_opened(self: &Arc<Self>) -> ActiveConnection {
+        self.active.fetch_add(1, Ordering::Relaxed);
+        ActiveConnection(self.clone())
+    }
+
+    /// Logs the counters every interval, if one is set. The task ends
+    /// once `alive` can no longer be upgraded, that is once the
+    /// listener is dropped.
+    pub(crate) fn spawn<T: Send + Sync + 'static>(
+        self: &Arc<Self>,
+        interval: Option<Duration>,
+        alive: Weak<T>,
+    ) {
+        let interval = match interval {
+            Some(interval) => interval,
+            None => return,
+        };
+        let stats = self.clone();
+
+        task::spawn(async move {
+            loop {
+                task::sleep(interval).await;
+                if alive.upgrade().is_none() {
+                    break;
+                }
+                stats.log();
+            }
+        });
+    }
+
+    fn log(&self) {
+        let succeeded = self.handshakes_succeeded.load(Ordering::Relaxed);
+        let failed = self.handshakes_failed.load(Ordering::Relaxed);
+        let reused = self.sessions_reused.load(Ordering::Relaxed);
+        logging::info!("tls stats", {
+            accepted: self.accepted.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+            active: self.active.load(Ordering::Relaxed),
+            handshakes_succeeded: succeeded,
+            handshakes_failed: failed,
+            handshake_success_rate: rate(succeeded, succeeded + failed),
+            session_hit_rate: rate(reused, succeeded),
+        });
+    }
+}
+
+/// `part` as a fraction of `total`, or 0 before there is a total.
+fn rate(part: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        part as f64 / total as f64
+    }
+}
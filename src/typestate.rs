@@ -0,0 +1,56 @@
+//! Markers recording which of the required settings a
+//! [`TlsListenerBuilder`](crate::TlsListenerBuilder) has been given,
+//! so that [`TlsListenerBuilder::finish`](crate::TlsListenerBuilder::finish)
+//! only compiles once it has a certificate, a key and an address.
+//!
+//! These only show up in type annotations, such as a function that
+//! returns a partially configured builder:
+//!
+//! ```rust
+//! use tide_openssl::typestate::{No, Yes};
+//! use tide_openssl::{TlsListener, TlsListenerBuilder};
+//!
+//! fn with_cert() -> TlsListenerBuilder<(), Yes, Yes, No> {
+//!     TlsListener::build()
+//!         .cert("./tls/localhost-4433.cert")
+//!         .key("./tls/localhost-4433.key")
+//! }
+//!
+//! let listener = with_cert().addrs("localhost:4433").finish();
+//! ```
+//!
+//! Before 0.2 the builder had no markers, so `TlsListenerBuilder<()>`
+//! is now an empty builder rather than any builder, and a certificate,
+//! key or address provided in only one branch of a conditional gives
+//! the branches different types. Either is a compile error to fix
+//! when upgrading, see the readme.
+
+/// Whether a required setting has been provided, either [`Yes`] or
+/// [`No`]. This is sealed, the builder has no other states.
+///
+/// ```rust,compile_fail
+/// struct Maybe;
+///
+/// impl tide_openssl::typestate::Marker for Maybe {}
+/// ```
+pub trait Marker: sealed::Sealed {}
+
+mod sealed {
+    pub trait Sealed {}
+
+    impl Sealed for super::Yes {}
+
+    impl Sealed for super::No {}
+}
+
+/// The setting has been provided.
+#[derive(Debug, Clone, Copy)]
+pub struct Yes;
+
+/// The setting has not been provided yet.
+#[derive(Debug, Clone, Copy)]
+pub struct No;
+
+impl Marker for Yes {}
+
+impl Marker for No {}
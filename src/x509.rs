@@ -0,0 +1,15 @@
+use openssl::x509::X509NameRef;
+
+/// Formats a name the way `openssl x509 -subject` does by default in
+/// OpenSSL 1.x, as in `/C=US/O=Acme Corp/CN=client01.acme.com`.
+pub(crate) fn distinguished_name(name: &X509NameRef) -> String {
+    name.entries()
+        .map(|entry| {
+            let key = entry.object().nid().short_name().unwrap_or("?");
+            match entry.data().to_string() {
+                Ok(value) => format!("/{}={}", key, value),
+                Err(_) => format!("/{}=?", key),
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,36 @@
+use async_std::task;
+use std::io::{Read, Write};
+use std::num::NonZeroUsize;
+use tide::listener::Listener;
+use tide_openssl::{test_helpers, TlsListener};
+
+#[test]
+fn parallel_accept_loops_serve_requests() {
+    let (cert, key) = test_helpers::self_signed_cert();
+    let tcp = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = tcp.local_addr().unwrap();
+
+    let mut app = tide::new();
+    app.at("/").get(|_| async { Ok("hello") });
+    let mut listener = TlsListener::build()
+        .tcp(tcp)
+        .cert_pem(String::from_utf8(cert.clone()).unwrap())
+        .key_pem(String::from_utf8(key).unwrap())
+        .accept_threads(NonZeroUsize::new(4).unwrap())
+        .finish()
+        .unwrap();
+    task::block_on(listener.bind(app)).unwrap();
+    task::spawn(async move { listener.accept().await });
+
+    let connector = test_helpers::make_test_connector(&cert);
+    for _ in 0..8 {
+        let tcp = std::net::TcpStream::connect(addr).unwrap();
+        let mut stream = connector.connect("localhost", tcp).unwrap();
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.ends_with("\r\n\r\nhello"));
+    }
+}
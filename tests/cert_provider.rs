@@ -0,0 +1,145 @@
+use async_std::task;
+use openssl::ssl::{SslConnector, SslMethod, SslVerifyMode};
+use openssl::x509::X509;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tide::listener::Listener;
+use tide_openssl::{test_helpers, CachingCertProvider, CertAndKey, CertProvider, TlsListener};
+
+#[derive(Debug, Clone, Default)]
+struct Store {
+    current: Arc<Mutex<CertAndKey>>,
+    fetches: Arc<AtomicUsize>,
+}
+
+impl CertProvider for Store {
+    fn fetch(&self) -> Pin<Box<dyn Future<Output = io::Result<CertAndKey>> + Send + '_>> {
+        self.fetches.fetch_add(1, Ordering::SeqCst);
+        let current = self.current.lock().unwrap().clone();
+        Box::pin(async move { Ok(current) })
+    }
+
+    fn refresh_interval(&self) -> Duration {
+        Duration::from_millis(50)
+    }
+}
+
+fn served_cert<State>(listener: &TlsListener<State>) -> Option<Vec<u8>> {
+    let acceptor = listener.clone_acceptor()?;
+    let cert = acceptor.context().certificate()?;
+    Some(cert.to_der().unwrap())
+}
+
+fn der(pem: &[u8]) -> Vec<u8> {
+    X509::from_pem(pem).unwrap().to_der().unwrap()
+}
+
+#[test]
+fn renewed_certificates_are_served() {
+    let store = Store::default();
+    *store.current.lock().unwrap() = test_helpers::self_signed_cert();
+    let first = store.current.lock().unwrap().0.clone();
+
+    let mut listener = TlsListener::build()
+        .addrs("127.0.0.1:0")
+        .cert_provider(store.clone())
+        .finish()
+        .unwrap();
+    assert_eq!(served_cert(&listener), None);
+    task::block_on(listener.bind(tide::new())).unwrap();
+    assert_eq!(served_cert(&listener), Some(der(&first)));
+
+    let renewed = test_helpers::self_signed_cert();
+    let expected = der(&renewed.0);
+    *store.current.lock().unwrap() = renewed;
+    for _ in 0..100 {
+        if served_cert(&listener) == Some(expected.clone()) {
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    panic!("renewed certificate was not served");
+}
+
+#[test]
+fn cached_certificates_are_refreshed_in_the_background() {
+    let store = Store::default();
+    *store.current.lock().unwrap() = (b"first".to_vec(), Vec::new());
+    let provider: Box<dyn CertProvider> = Box::new(CachingCertProvider::new(
+        store.clone(),
+        Duration::from_millis(50),
+    ));
+
+    let first = task::block_on(provider.fetch()).unwrap();
+    let cached = task::block_on(provider.fetch()).unwrap();
+    assert_eq!(first, cached);
+    assert_eq!(store.fetches.load(Ordering::SeqCst), 1);
+
+    *store.current.lock().unwrap() = (b"second".to_vec(), Vec::new());
+    for _ in 0..100 {
+        if task::block_on(provider.fetch()).unwrap().0 == b"second" {
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    panic!("cached certificate was not refreshed");
+}
+
+#[test]
+fn renewed_chains_are_served() {
+    let store = Store::default();
+    let (cert, key) = test_helpers::self_signed_cert();
+    *store.current.lock().unwrap() = (cert.clone(), key.clone());
+    let tcp = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = tcp.local_addr().unwrap();
+
+    let mut listener = TlsListener::build()
+        .tcp(tcp)
+        .cert_provider(store.clone())
+        .finish()
+        .unwrap();
+    task::block_on(listener.bind(tide::new())).unwrap();
+    task::spawn(async move { listener.accept().await });
+
+    // the same leaf, now served with an intermediate
+    let (intermediate, _) = test_helpers::self_signed_cert();
+    *store.current.lock().unwrap() = ([cert, intermediate].concat(), key);
+    let mut connector = SslConnector::builder(SslMethod::tls()).unwrap();
+    connector.set_verify(SslVerifyMode::NONE);
+    let connector = connector.build();
+    for _ in 0..100 {
+        let tcp = std::net::TcpStream::connect(addr).unwrap();
+        let stream = connector.connect("localhost", tcp).unwrap();
+        if stream.ssl().peer_cert_chain().unwrap().len() == 2 {
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    panic!("renewed chain was not served");
+}
+
+#[test]
+fn unchanged_certificates_are_not_rebuilt() {
+    let store = Store::default();
+    *store.current.lock().unwrap() = test_helpers::self_signed_cert();
+    let builds = Arc::new(AtomicUsize::new(0));
+    let counted = builds.clone();
+
+    let mut listener = TlsListener::build()
+        .addrs("127.0.0.1:0")
+        .cert_provider(store.clone())
+        .ssl_context_hook(move |_| {
+            counted.fetch_add(1, Ordering::SeqCst);
+        })
+        .finish()
+        .unwrap();
+    task::block_on(listener.bind(tide::new())).unwrap();
+
+    std::thread::sleep(Duration::from_millis(300));
+    assert!(store.fetches.load(Ordering::SeqCst) > 1);
+    assert_eq!(builds.load(Ordering::SeqCst), 1);
+}
@@ -0,0 +1,119 @@
+use async_std::task;
+use openssl::asn1::Asn1Time;
+use openssl::bn::BigNum;
+use openssl::hash::MessageDigest;
+use openssl::pkey::{PKey, Private};
+use openssl::ssl::{SslAcceptor, SslConnector, SslMethod, SslVerifyMode, SslVersion};
+use openssl::x509::extension::AuthorityKeyIdentifier;
+use openssl::x509::{CrlNumber, X509CrlBuilder, X509RevokedBuilder, X509};
+use std::io::{Read, Write};
+use std::net::SocketAddr;
+use tide::listener::Listener;
+use tide_openssl::{test_helpers, TlsListener, TlsProfile};
+
+fn revoking(ca: &X509, ca_key: &PKey<Private>, serial: u32) -> Vec<u8> {
+    let mut revoked = X509RevokedBuilder::new().unwrap();
+    revoked
+        .set_serial_number(&BigNum::from_u32(serial).unwrap().to_asn1_integer().unwrap())
+        .unwrap();
+    revoked
+        .set_revocation_date(&Asn1Time::days_from_now(0).unwrap())
+        .unwrap();
+
+    let mut crl = X509CrlBuilder::new().unwrap();
+    crl.set_issuer_name(ca.subject_name()).unwrap();
+    crl.set_last_update(&Asn1Time::days_from_now(0).unwrap())
+        .unwrap();
+    crl.set_next_update(&Asn1Time::days_from_now(1).unwrap())
+        .unwrap();
+    let dummy = X509::builder().unwrap();
+    let aki = AuthorityKeyIdentifier::new()
+        .keyid(true)
+        .build(&dummy.x509v3_context(Some(ca), None))
+        .unwrap();
+    crl.append_extension(aki).unwrap();
+    let number = CrlNumber::new(BigNum::from_u32(1).unwrap())
+        .unwrap()
+        .build()
+        .unwrap();
+    crl.append_extension(number).unwrap();
+    crl.add_revoked(revoked.build()).unwrap();
+    crl.sign(ca_key, MessageDigest::sha256()).unwrap();
+    crl.build().unwrap().to_pem().unwrap()
+}
+
+fn spawn_server(ca: &X509, crl_path: &std::path::Path) -> (SocketAddr, Vec<u8>) {
+    let (cert, key) = test_helpers::self_signed_cert();
+    let mut profile = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls()).unwrap();
+    profile.cert_store_mut().add_cert(ca.clone()).unwrap();
+    profile.set_verify(SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT);
+
+    let mut app = tide::new();
+    app.at("/").get(|_| async { Ok("hello") });
+    let tcp = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = tcp.local_addr().unwrap();
+    let mut listener = TlsListener::build()
+        .tcp(tcp)
+        .cert_pem(String::from_utf8(cert.clone()).unwrap())
+        .key_pem(String::from_utf8(key).unwrap())
+        .tls_profile(TlsProfile::Custom(profile))
+        .client_crl(crl_path)
+        .finish()
+        .unwrap();
+    task::block_on(listener.bind(app)).unwrap();
+    task::spawn(async move { listener.accept().await });
+    (addr, cert)
+}
+
+fn connect_as(
+    addr: SocketAddr,
+    server_cert: &[u8],
+    cert: &X509,
+    key: &PKey<Private>,
+) -> Result<String, String> {
+    let mut connector = SslConnector::builder(SslMethod::tls()).unwrap();
+    connector
+        .cert_store_mut()
+        .add_cert(X509::from_pem(server_cert).unwrap())
+        .unwrap();
+    connector.set_certificate(cert).unwrap();
+    connector.set_private_key(key).unwrap();
+    // with TLS 1.3 the client considers the handshake done before the
+    // server has checked its certificate, so use TLS 1.2 to see the
+    // failure as a handshake error
+    connector
+        .set_max_proto_version(Some(SslVersion::TLS1_2))
+        .unwrap();
+    let connector = connector.build();
+
+    let tcp = std::net::TcpStream::connect(addr).unwrap();
+    let mut stream = connector
+        .connect("localhost", tcp)
+        .map_err(|error| error.to_string())?;
+    let mut response = String::new();
+    stream
+        .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .and_then(|_| stream.read_to_string(&mut response))
+        .map_err(|error| error.to_string())?;
+    Ok(response)
+}
+
+#[test]
+fn revoked_client_cert_is_rejected() {
+    let ca_key = test_helpers::generate_key();
+    let ca = test_helpers::issue_cert(&[("CN", "Test CA")], 1, &ca_key, None, 0..1);
+    let (good_key, revoked_key) = (test_helpers::generate_key(), test_helpers::generate_key());
+    let issuer = Some((&ca, &ca_key));
+    let good = test_helpers::issue_cert(&[("CN", "good")], 2, &good_key, issuer, 0..1);
+    let revoked = test_helpers::issue_cert(&[("CN", "revoked")], 3, &revoked_key, issuer, 0..1);
+
+    let crl_path = std::env::temp_dir().join(format!("tide-openssl-{}.crl", std::process::id()));
+    std::fs::write(&crl_path, revoking(&ca, &ca_key, 3)).unwrap();
+    let (addr, server_cert) = spawn_server(&ca, &crl_path);
+    std::fs::remove_file(&crl_path).unwrap();
+
+    let response = connect_as(addr, &server_cert, &good, &good_key).unwrap();
+    assert!(response.ends_with("\r\n\r\nhello"));
+    let error = connect_as(addr, &server_cert, &revoked, &revoked_key).unwrap_err();
+    assert!(error.contains("certificate revoked"), "{}", error);
+}
@@ -0,0 +1,70 @@
+use async_std::task;
+use std::io::{Read, Write};
+use tide::http::cookies::SameSite;
+use tide::listener::Listener;
+use tide::Response;
+use tide_openssl::{test_helpers, CookieSecurityOptions, TlsListener};
+
+fn set_cookie_headers(options: CookieSecurityOptions) -> Vec<String> {
+    let (cert, key) = test_helpers::self_signed_cert();
+    let tcp = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = tcp.local_addr().unwrap();
+
+    let mut app = tide::new();
+    app.at("/").get(|_| async {
+        let mut res = Response::new(200);
+        res.append_header("set-cookie", "session=1; Path=/");
+        res.append_header("set-cookie", "theme=dark; SameSite=Lax");
+        res.append_header("set-cookie", "careful=1; Secure");
+        Ok(res)
+    });
+    let mut listener = TlsListener::build()
+        .tcp(tcp)
+        .cert_pem(String::from_utf8(cert.clone()).unwrap())
+        .key_pem(String::from_utf8(key).unwrap())
+        .cookie_security(options)
+        .finish()
+        .unwrap();
+    task::block_on(listener.bind(app)).unwrap();
+    task::spawn(async move { listener.accept().await });
+
+    let connector = test_helpers::make_test_connector(&cert);
+    let tcp = std::net::TcpStream::connect(addr).unwrap();
+    let mut stream = connector.connect("localhost", tcp).unwrap();
+    stream
+        .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .unwrap();
+    let mut response = String::new();
+    let _ = stream.read_to_string(&mut response);
+    response
+        .lines()
+        .filter_map(|line| line.strip_prefix("set-cookie: "))
+        .map(String::from)
+        .collect()
+}
+
+#[test]
+fn missing_attributes_are_added() {
+    let cookies = set_cookie_headers(CookieSecurityOptions::default());
+    assert!(cookies.contains(&"session=1; Path=/; Secure; HttpOnly; SameSite=Strict".into()));
+    assert!(cookies.contains(&"theme=dark; SameSite=Lax; Secure; HttpOnly".into()));
+    assert!(
+        cookies.contains(&"careful=1; Secure".into()),
+        "{:?}",
+        cookies
+    );
+}
+
+#[test]
+fn only_the_chosen_attributes_are_added() {
+    let cookies = set_cookie_headers(CookieSecurityOptions {
+        secure: true,
+        http_only: false,
+        same_site: Some(SameSite::Lax),
+    });
+    assert!(
+        cookies.contains(&"session=1; Path=/; Secure; SameSite=Lax".into()),
+        "{:?}",
+        cookies
+    );
+}
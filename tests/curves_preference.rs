@@ -0,0 +1,51 @@
+use async_std::task;
+use openssl::nid::Nid;
+use openssl::ssl::{SslConnector, SslMethod, SslVerifyMode, SslVersion};
+use std::net::{SocketAddr, TcpStream};
+use tide::listener::Listener;
+use tide_openssl::{test_helpers, CurvesPreference, TlsListener, TlsProfile};
+
+fn serve(preference: CurvesPreference) -> SocketAddr {
+    let (cert, key) = test_helpers::self_signed_cert();
+    let tcp = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = tcp.local_addr().unwrap();
+    let mut listener = TlsListener::build()
+        .tcp(tcp)
+        .cert_pem(String::from_utf8(cert).unwrap())
+        .key_pem(String::from_utf8(key).unwrap())
+        .tls_profile(TlsProfile::MozillaIntermediateV5)
+        .ssl_curves_preference(preference)
+        .ssl_groups("P-384:P-256")
+        .finish()
+        .unwrap();
+    task::block_on(listener.bind(tide::new())).unwrap();
+    task::spawn(async move { listener.accept().await });
+    addr
+}
+
+/// The curve of the ECDHE key the server sent, over TLS 1.2, where the
+/// group is picked from both lists rather than from the key shares.
+fn negotiated_curve(addr: SocketAddr) -> Nid {
+    let mut connector = SslConnector::builder(SslMethod::tls()).unwrap();
+    connector.set_verify(SslVerifyMode::NONE);
+    connector
+        .set_max_proto_version(Some(SslVersion::TLS1_2))
+        .unwrap();
+    connector.set_groups_list("P-256:P-384").unwrap();
+    let tcp = TcpStream::connect(addr).unwrap();
+    let stream = connector.build().connect("localhost", tcp).unwrap();
+    let key = stream.ssl().peer_tmp_key().unwrap();
+    key.ec_key().unwrap().group().curve_name().unwrap()
+}
+
+#[test]
+fn client_group_preference_wins_by_default() {
+    let addr = serve(CurvesPreference::Client);
+    assert_eq!(negotiated_curve(addr), Nid::X9_62_PRIME256V1);
+}
+
+#[test]
+fn server_group_preference_wins_when_set() {
+    let addr = serve(CurvesPreference::Server);
+    assert_eq!(negotiated_curve(addr), Nid::SECP384R1);
+}
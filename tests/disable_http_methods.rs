@@ -0,0 +1,48 @@
+use async_std::task;
+use std::io::{Read, Write};
+use std::net::SocketAddr;
+use tide::listener::Listener;
+use tide_openssl::{test_helpers, TlsListener};
+
+fn status_line(addr: SocketAddr, cert: &[u8], method: &str) -> String {
+    let connector = test_helpers::make_test_connector(cert);
+    let tcp = std::net::TcpStream::connect(addr).unwrap();
+    let mut stream = connector.connect("localhost", tcp).unwrap();
+    let request = format!(
+        "{} / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+        method
+    );
+    stream.write_all(request.as_bytes()).unwrap();
+    let mut response = String::new();
+    let _ = stream.read_to_string(&mut response);
+    response.lines().next().unwrap_or_default().to_string()
+}
+
+#[test]
+fn disabled_methods_never_reach_the_app() {
+    let (cert, key) = test_helpers::self_signed_cert();
+    let tcp = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = tcp.local_addr().unwrap();
+
+    let mut app = tide::new();
+    app.at("/").all(|_| async { Ok("hello") });
+    let mut listener = TlsListener::build()
+        .tcp(tcp)
+        .cert_pem(String::from_utf8(cert.clone()).unwrap())
+        .key_pem(String::from_utf8(key).unwrap())
+        .disable_http_methods(vec!["trace", "DELETE"])
+        .finish()
+        .unwrap();
+    task::block_on(listener.bind(app)).unwrap();
+    task::spawn(async move { listener.accept().await });
+
+    assert_eq!(status_line(addr, &cert, "GET"), "HTTP/1.1 200 OK");
+    assert_eq!(
+        status_line(addr, &cert, "TRACE"),
+        "HTTP/1.1 405 Method Not Allowed"
+    );
+    assert_eq!(
+        status_line(addr, &cert, "DELETE"),
+        "HTTP/1.1 405 Method Not Allowed"
+    );
+}
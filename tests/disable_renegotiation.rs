@@ -0,0 +1,33 @@
+use foreign_types::ForeignTypeRef;
+use openssl::ssl::{SslContextRef, SslOptions};
+use tide_openssl::{test_helpers, TlsListener};
+
+/// The options of a built context, which the openssl crate only reads
+/// back from a builder.
+fn context_options(context: &SslContextRef) -> SslOptions {
+    // SAFETY: the pointer is to a live SSL_CTX
+    let options = unsafe { openssl_sys::SSL_CTX_get_options(context.as_ptr()) };
+    SslOptions::from_bits_retain(options as _)
+}
+
+fn options(disable_renegotiation: bool) -> SslOptions {
+    let (cert, key) = test_helpers::self_signed_cert();
+    let acceptor = TlsListener::<()>::build()
+        .cert_pem(String::from_utf8(cert).unwrap())
+        .key_pem(String::from_utf8(key).unwrap())
+        .legacy_client_support(true)
+        .disable_renegotiation(disable_renegotiation)
+        .build_acceptor()
+        .unwrap();
+    context_options(acceptor.context())
+}
+
+#[test]
+fn renegotiation_is_refused_when_disabled() {
+    assert!(options(true).contains(SslOptions::NO_RENEGOTIATION));
+}
+
+#[test]
+fn renegotiation_is_left_to_openssl_by_default() {
+    assert!(!options(false).contains(SslOptions::NO_RENEGOTIATION));
+}
@@ -0,0 +1,149 @@
+use async_std::task;
+use std::fmt::Debug;
+use std::io::Write;
+use std::sync::{Mutex, Once};
+use std::time::Duration;
+use tide::listener::Listener;
+use tide_openssl::typestate::Yes;
+use tide_openssl::{test_helpers, ErrorLogFormat, TlsListener, TlsListenerBuilder};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+static EVENTS: Mutex<Vec<Vec<(&'static str, String)>>> = Mutex::new(Vec::new());
+
+/// Held by each test, since they share the recorded events.
+static SERIAL: Mutex<()> = Mutex::new(());
+
+fn record_events() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| tracing::subscriber::set_global_default(Recorder).unwrap());
+}
+
+/// Keeps the fields of every event.
+struct Recorder;
+
+struct Fields(Vec<(&'static str, String)>);
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.0.push((field.name(), format!("{:?}", value)));
+    }
+}
+
+impl Subscriber for Recorder {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, _: &Attributes<'_>) -> Id {
+        Id::from_u64(1)
+    }
+
+    fn record(&self, _: &Id, _: &Record<'_>) {}
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = Fields(Vec::new());
+        event.record(&mut fields);
+        EVENTS.lock().unwrap().push(fields.0);
+    }
+
+    fn enter(&self, _: &Id) {}
+
+    fn exit(&self, _: &Id) {}
+}
+
+/// The events logged for a client that does not speak tls.
+fn failed_handshake_events(format: ErrorLogFormat) -> Vec<Vec<(&'static str, String)>> {
+    failed_handshake_events_with(|builder| builder.error_log_format(format))
+}
+
+fn failed_handshake_events_with(
+    configure: impl FnOnce(
+        TlsListenerBuilder<(), Yes, Yes, Yes>,
+    ) -> TlsListenerBuilder<(), Yes, Yes, Yes>,
+) -> Vec<Vec<(&'static str, String)>> {
+    let (cert, key) = test_helpers::self_signed_cert();
+    let tcp = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = tcp.local_addr().unwrap();
+    let builder = TlsListener::build()
+        .tcp(tcp)
+        .cert_pem(String::from_utf8(cert).unwrap())
+        .key_pem(String::from_utf8(key).unwrap());
+    let mut listener = configure(builder).finish().unwrap();
+    task::block_on(listener.bind(tide::new())).unwrap();
+    task::spawn(async move { listener.accept().await });
+
+    EVENTS.lock().unwrap().clear();
+    let mut tcp = std::net::TcpStream::connect(addr).unwrap();
+    tcp.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+    std::thread::sleep(Duration::from_millis(100));
+
+    EVENTS
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|fields| {
+            fields
+                .iter()
+                .any(|(name, value)| *name == "message" && value.contains("tls error"))
+        })
+        .cloned()
+        .collect()
+}
+
+#[test]
+fn errors_are_logged_in_the_configured_format() {
+    let _serial = SERIAL.lock().unwrap();
+    record_events();
+
+    let structured = failed_handshake_events(ErrorLogFormat::Structured);
+    assert_eq!(structured.len(), 1);
+    assert!(structured[0].contains(&("message", String::from("tls error"))));
+    assert!(structured[0].iter().any(|(name, _)| *name == "error"));
+
+    let json = failed_handshake_events(ErrorLogFormat::Json);
+    assert_eq!(json.len(), 1);
+    let (_, message) = &json[0][0];
+    assert!(
+        message.starts_with('{') && message.ends_with('}'),
+        "{}",
+        message
+    );
+    for field in [
+        "\"timestamp\":",
+        "\"level\":\"error\"",
+        "\"message\":\"tls error\"",
+        "\"error\":",
+        "\"peer_addr\":\"127.0.0.1:",
+        "\"connection_id\":",
+    ] {
+        assert!(message.contains(field), "{} in {}", field, message);
+    }
+}
+
+#[test]
+fn handshake_dumps_are_named_after_the_logged_connection_id() {
+    let _serial = SERIAL.lock().unwrap();
+    record_events();
+    let dir = std::env::temp_dir().join(format!("tide-openssl-ids-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let events = failed_handshake_events_with(|builder| {
+        builder
+            .error_log_format(ErrorLogFormat::Json)
+            .debug_handshake_dump(&dir)
+    });
+    let (_, message) = &events[0][0];
+    let id = message
+        .split("\"connection_id\":")
+        .nth(1)
+        .and_then(|rest| rest.split(|c: char| !c.is_ascii_digit()).next())
+        .unwrap();
+    let dump = dir.join(format!("connection-{}-{}.hex", std::process::id(), id));
+    let exists = dump.exists();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(exists, "{} for {}", dump.display(), message);
+}
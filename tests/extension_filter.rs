@@ -0,0 +1,50 @@
+use async_std::task;
+use openssl::ssl::{SslConnector, SslMethod, SslVerifyMode};
+use std::net::{SocketAddr, TcpStream};
+use tide::listener::Listener;
+use tide_openssl::{test_helpers, TlsListener};
+
+fn serve(allowed_extensions: Vec<u16>) -> SocketAddr {
+    let (cert, key) = test_helpers::self_signed_cert();
+    let tcp = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = tcp.local_addr().unwrap();
+    let mut listener = TlsListener::build()
+        .tcp(tcp)
+        .cert_pem(String::from_utf8(cert).unwrap())
+        .key_pem(String::from_utf8(key).unwrap())
+        .ssl_groups("P-256")
+        .tls_extension_filter(allowed_extensions)
+        .finish()
+        .unwrap();
+    task::block_on(listener.bind(tide::new())).unwrap();
+    task::spawn(async move { listener.accept().await });
+    addr
+}
+
+fn connect(addr: SocketAddr, groups: &str) -> Result<(), String> {
+    let mut connector = SslConnector::builder(SslMethod::tls()).unwrap();
+    connector.set_verify(SslVerifyMode::NONE);
+    connector.set_groups_list(groups).unwrap();
+    let tcp = TcpStream::connect(addr).unwrap();
+    connector
+        .build()
+        .connect("localhost", tcp)
+        .map(|_| ())
+        .map_err(|error| error.to_string())
+}
+
+#[test]
+fn unlisted_extension_is_refused() {
+    // everything but server_name
+    let addr = serve((1..=u16::MAX).collect());
+    let error = connect(addr, "P-256").unwrap_err();
+    assert!(error.contains("illegal parameter"), "{}", error);
+}
+
+#[test]
+fn second_client_hello_after_hello_retry_request_is_checked() {
+    let addr = serve((0..=u16::MAX).collect());
+    // only an X25519 key share is sent, which the server answers with a
+    // HelloRetryRequest for P-256
+    connect(addr, "X25519:P-256").unwrap();
+}
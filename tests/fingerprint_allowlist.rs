@@ -0,0 +1,25 @@
+use async_std::task;
+use tide::listener::Listener;
+use tide_openssl::{test_helpers, TlsListener};
+
+#[test]
+fn unknown_ja3_fingerprint_is_refused() {
+    let (cert, key) = test_helpers::self_signed_cert();
+    let tcp = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = tcp.local_addr().unwrap();
+
+    let mut listener = TlsListener::build()
+        .tcp(tcp)
+        .cert_pem(String::from_utf8(cert.clone()).unwrap())
+        .key_pem(String::from_utf8(key).unwrap())
+        .tls_fingerprint_allowlist(vec!["00000000000000000000000000000000".into()])
+        .finish()
+        .unwrap();
+    task::block_on(listener.bind(tide::new())).unwrap();
+    task::spawn(async move { listener.accept().await });
+
+    let connector = test_helpers::make_test_connector(&cert);
+    let tcp = std::net::TcpStream::connect(addr).unwrap();
+    let error = connector.connect("localhost", tcp).unwrap_err();
+    assert!(error.to_string().contains("illegal parameter"), "{}", error);
+}
@@ -0,0 +1,120 @@
+use std::sync::{Arc, Mutex};
+use tide_openssl::{test_helpers, TlsListener};
+
+#[test]
+fn mixed_key_material_fails_fast() {
+    let result = TlsListener::<()>::build()
+        .addrs("localhost:4433")
+        .cert("./tls/localhost-4433.cert")
+        .key_pem("")
+        .finish();
+    assert!(result.is_err());
+}
+
+#[test]
+fn missing_cert_files_fail_at_finish() {
+    let result = TlsListener::<()>::build()
+        .addrs("localhost:4433")
+        .cert("./does-not-exist.cert")
+        .key("./does-not-exist.key")
+        .finish();
+    assert!(result.is_err());
+}
+
+#[test]
+fn acceptor_builds_without_an_address() {
+    let (cert, key) = test_helpers::self_signed_cert();
+    let acceptor = TlsListener::<()>::build()
+        .cert_pem(String::from_utf8(cert).unwrap())
+        .key_pem(String::from_utf8(key).unwrap())
+        .build_acceptor()
+        .unwrap();
+    assert!(acceptor.context().private_key().is_some());
+}
+
+#[test]
+fn context_hook_sees_the_built_context() {
+    let (cert, key) = test_helpers::self_signed_cert();
+    let subject = Arc::new(Mutex::new(None));
+    let seen = subject.clone();
+    TlsListener::<()>::build()
+        .cert_pem(String::from_utf8(cert).unwrap())
+        .key_pem(String::from_utf8(key).unwrap())
+        .ssl_context_hook(move |context| {
+            let cert = context.certificate().unwrap();
+            *seen.lock().unwrap() = Some(format!("{:?}", cert.subject_name()));
+        })
+        .build_acceptor()
+        .unwrap();
+    let subject = subject.lock().unwrap().clone().unwrap();
+    assert!(subject.contains("localhost"), "{}", subject);
+}
+
+#[test]
+fn cloned_builders_share_settings() {
+    let (cert, key) = test_helpers::self_signed_cert();
+    let hooked = Arc::new(Mutex::new(0));
+    let count = hooked.clone();
+    let base = TlsListener::<()>::build()
+        .cert_pem(String::from_utf8(cert).unwrap())
+        .key_pem(String::from_utf8(key).unwrap())
+        .ssl_context_hook(move |_| *count.lock().unwrap() += 1);
+    base.clone().build_acceptor().unwrap();
+    base.build_acceptor().unwrap();
+    assert_eq!(*hooked.lock().unwrap(), 2);
+}
+
+#[test]
+fn builder_formatting_never_shows_key_material() {
+    let (cert, key) = test_helpers::self_signed_cert();
+    let key = String::from_utf8(key).unwrap();
+    let builder = TlsListener::<()>::build()
+        .addrs("127.0.0.1:4433")
+        .cert_pem(String::from_utf8(cert).unwrap())
+        .key_pem(key.clone())
+        .mozilla_intermediate_v5();
+
+    let debug = format!("{:?}", builder);
+    assert!(!debug.contains(key.lines().nth(1).unwrap()), "{}", debug);
+    assert!(debug.contains("<redacted>"), "{}", debug);
+    assert_eq!(
+        builder.to_string(),
+        "TlsListenerBuilder { addr: 127.0.0.1:4433, cert: <pem>, profile: MozillaIntermediateV5 }"
+    );
+}
+
+#[test]
+fn mismatched_key_fails_verification() {
+    let (cert, _) = test_helpers::self_signed_cert();
+    let (other_cert, key) = test_helpers::self_signed_cert();
+    let error = TlsListener::<()>::build()
+        .cert_pem(String::from_utf8(cert).unwrap())
+        .key_pem(String::from_utf8(key.clone()).unwrap())
+        .verify_cert_and_key()
+        .unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+    assert!(error.to_string().contains("does not match"), "{}", error);
+
+    let listener = TlsListener::<()>::build()
+        .cert_pem(String::from_utf8(other_cert).unwrap())
+        .key_pem(String::from_utf8(key).unwrap())
+        .verify_cert_and_key()
+        .unwrap()
+        .addrs("localhost:4433")
+        .finish();
+    assert!(listener.is_ok());
+}
+
+#[test]
+fn mismatched_key_fails_at_finish() {
+    let (cert, _) = test_helpers::self_signed_cert();
+    let (_, key) = test_helpers::self_signed_cert();
+    let error = TlsListener::<()>::build()
+        .addrs("localhost:4433")
+        .cert_pem(String::from_utf8(cert).unwrap())
+        .key_pem(String::from_utf8(key).unwrap())
+        .finish()
+        .unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+    assert!(error.to_string().contains("does not match"), "{}", error);
+}
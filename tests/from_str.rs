@@ -0,0 +1,49 @@
+use tide_openssl::typestate::Yes;
+use tide_openssl::TlsListenerBuilder;
+
+type ConfiguredBuilder = TlsListenerBuilder<(), Yes, Yes, Yes>;
+
+#[test]
+fn builder_parses_from_a_uri() {
+    let builder: ConfiguredBuilder =
+        "tls://127.0.0.1:4433?cert=/etc/tls/cert.pem&key=/etc/tls/key.pem"
+            .parse()
+            .unwrap();
+    assert_eq!(
+        builder.to_string(),
+        "TlsListenerBuilder { addr: 127.0.0.1:4433, cert: /etc/tls/cert.pem, profile: MozillaModernV5 }"
+    );
+}
+
+#[test]
+fn ipv6_addresses_are_parsed() {
+    let builder: ConfiguredBuilder = "tls://[::1]:4433?cert=cert.pem&key=key.pem"
+        .parse()
+        .unwrap();
+    assert_eq!(
+        builder.to_string(),
+        "TlsListenerBuilder { addr: [::1]:4433, cert: cert.pem, profile: MozillaModernV5 }"
+    );
+}
+
+#[test]
+fn malformed_uris_are_rejected() {
+    for uri in [
+        "https://127.0.0.1:4433?cert=a&key=b",
+        "tls://127.0.0.1?cert=a&key=b",
+        "tls://127.0.0.1:4433?cert=a&key=b&password=c",
+        "tls://127.0.0.1:4433?cert=a",
+        "not a uri",
+    ] {
+        assert!(uri.parse::<ConfiguredBuilder>().is_err(), "{}", uri);
+    }
+}
+
+#[test]
+fn builder_converts_from_a_tuple() {
+    let builder = ConfiguredBuilder::from(("127.0.0.1:4433", "cert.pem", "key.pem"));
+    assert_eq!(
+        builder.to_string(),
+        "TlsListenerBuilder { addr: 127.0.0.1:4433, cert: cert.pem, profile: MozillaModernV5 }"
+    );
+}
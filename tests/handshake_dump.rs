@@ -0,0 +1,49 @@
+use async_std::task;
+use std::time::Duration;
+use tide::listener::Listener;
+use tide_openssl::{test_helpers, TlsListener};
+
+#[test]
+fn handshakes_are_dumped() {
+    let dir = std::env::temp_dir().join(format!("tide-openssl-dump-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let (cert, key) = test_helpers::self_signed_cert();
+    let tcp = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = tcp.local_addr().unwrap();
+    let mut listener = TlsListener::build()
+        .tcp(tcp)
+        .cert_pem(String::from_utf8(cert.clone()).unwrap())
+        .key_pem(String::from_utf8(key).unwrap())
+        .debug_handshake_dump(&dir)
+        .finish()
+        .unwrap();
+    task::block_on(listener.bind(tide::new())).unwrap();
+    task::spawn(async move { listener.accept().await });
+
+    let connector = test_helpers::make_test_connector(&cert);
+    let tcp = std::net::TcpStream::connect(addr).unwrap();
+    connector.connect("localhost", tcp).unwrap();
+
+    // the file can be seen before the write to it has finished
+    let mut dumps = Vec::new();
+    let mut dump = String::new();
+    for _ in 0..50 {
+        dumps = std::fs::read_dir(&dir).unwrap().collect::<Vec<_>>();
+        if let [Ok(entry)] = &dumps[..] {
+            dump = std::fs::read_to_string(entry.path()).unwrap();
+            if dump.contains("\n# server (") {
+                break;
+            }
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    assert_eq!(dumps.len(), 1);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let mut lines = dump.lines().skip(1);
+    assert!(lines.next().unwrap().starts_with("# client ("));
+    // a handshake record holding a ClientHello
+    assert!(lines.next().unwrap().starts_with("1603"));
+    assert!(dump.contains("\n# server ("));
+}
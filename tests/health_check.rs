@@ -0,0 +1,64 @@
+use async_std::task;
+use std::sync::mpsc;
+use std::time::Duration;
+use tide::listener::Listener;
+use tide_openssl::{test_helpers, TlsListener};
+
+#[test]
+fn expired_certificate_fails_the_health_check() {
+    let key = test_helpers::generate_key();
+    let cert = test_helpers::issue_cert(&[("CN", "localhost")], 1, &key, None, -2..-1);
+
+    let (failures, failed) = mpsc::channel();
+    let tcp = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut listener = TlsListener::build()
+        .tcp(tcp)
+        .cert_pem(String::from_utf8(cert.to_pem().unwrap()).unwrap())
+        .key_pem(String::from_utf8(key.private_key_to_pem_pkcs8().unwrap()).unwrap())
+        .health_tls_check(Duration::from_millis(500))
+        .on_health_check_failure(move |error| {
+            let _ = failures.send(error.to_string());
+        })
+        .finish()
+        .unwrap();
+    task::block_on(listener.bind(tide::new())).unwrap();
+    task::spawn(async move { listener.accept().await });
+
+    let error = failed.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(error, "certificate has expired");
+}
+
+#[test]
+fn health_check_connections_are_not_reported() {
+    let (cert, key) = test_helpers::self_signed_cert();
+    let (handshakes, handshaken) = mpsc::channel();
+    let (failures, failed) = mpsc::channel();
+    let tcp = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = tcp.local_addr().unwrap();
+    let mut listener = TlsListener::build()
+        .tcp(tcp)
+        .cert_pem(String::from_utf8(cert.clone()).unwrap())
+        .key_pem(String::from_utf8(key).unwrap())
+        .health_tls_check(Duration::from_millis(100))
+        .on_health_check_failure(move |error| {
+            let _ = failures.send(error.to_string());
+        })
+        .session_cache_hit_callback(move |peer_addr, _| {
+            let _ = handshakes.send(peer_addr);
+        })
+        .finish()
+        .unwrap();
+    task::block_on(listener.bind(tide::new())).unwrap();
+    task::spawn(async move { listener.accept().await });
+
+    std::thread::sleep(Duration::from_millis(500));
+    assert!(failed.try_recv().is_err());
+    assert!(handshaken.try_recv().is_err());
+
+    let connector = test_helpers::make_test_connector(&cert);
+    let tcp = std::net::TcpStream::connect(addr).unwrap();
+    let client_addr = tcp.local_addr().unwrap();
+    let _stream = connector.connect("localhost", tcp).unwrap();
+    let peer_addr = handshaken.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(peer_addr, client_addr);
+}
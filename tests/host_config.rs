@@ -0,0 +1,190 @@
+use async_std::task;
+use openssl::pkey::{PKey, Private};
+use openssl::ssl::{SslAcceptor, SslConnector, SslMethod, SslStream, SslVerifyMode, SslVersion};
+use openssl::x509::X509;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::sync::{Arc, Mutex};
+use tide::listener::Listener;
+use tide_openssl::{test_helpers, HostConfig, TlsListener, TlsProfile};
+
+fn connect(addr: SocketAddr, hostname: &str) -> Result<SslStream<TcpStream>, String> {
+    let mut connector = SslConnector::builder(SslMethod::tls()).unwrap();
+    connector.set_verify(SslVerifyMode::NONE);
+    connector.set_alpn_protos(b"\x02h2\x08http/1.1").unwrap();
+    let tcp = TcpStream::connect(addr).unwrap();
+    connector
+        .build()
+        .connect(hostname, tcp)
+        .map_err(|e| e.to_string())
+}
+
+fn served_cert(stream: &SslStream<TcpStream>) -> Vec<u8> {
+    stream.ssl().peer_certificate().unwrap().to_der().unwrap()
+}
+
+fn der(pem: &[u8]) -> Vec<u8> {
+    X509::from_pem(pem).unwrap().to_der().unwrap()
+}
+
+fn host(cert: Vec<u8>, key: Vec<u8>, alpn_protocol: &str) -> HostConfig {
+    HostConfig::build()
+        .cert_pem(String::from_utf8(cert).unwrap())
+        .key_pem(String::from_utf8(key).unwrap())
+        .alpn_protocols([alpn_protocol])
+        .min_tls_version(SslVersion::TLS1_3)
+        .finish()
+        .unwrap()
+}
+
+#[test]
+fn hosts_are_served_their_own_context() {
+    let (cert, key) = test_helpers::self_signed_cert();
+    let (grpc_cert, grpc_key) = test_helpers::self_signed_cert();
+    let (api_cert, api_key) = test_helpers::self_signed_cert();
+    let tcp = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = tcp.local_addr().unwrap();
+
+    let mut listener = TlsListener::build()
+        .tcp(tcp)
+        .cert_pem(String::from_utf8(cert.clone()).unwrap())
+        .key_pem(String::from_utf8(key).unwrap())
+        .alpn_protocols(["http/1.1"])
+        .add_host_config("grpc.example.com", host(grpc_cert.clone(), grpc_key, "h2"))
+        .add_host_config(
+            "API.example.com",
+            host(api_cert.clone(), api_key, "http/1.1"),
+        )
+        .finish()
+        .unwrap();
+    task::block_on(listener.bind(tide::new())).unwrap();
+    task::spawn(async move { listener.accept().await });
+
+    let grpc = connect(addr, "grpc.example.com").unwrap();
+    assert_eq!(served_cert(&grpc), der(&grpc_cert));
+    assert_eq!(grpc.ssl().selected_alpn_protocol(), Some(&b"h2"[..]));
+
+    let api = connect(addr, "api.example.com").unwrap();
+    assert_eq!(served_cert(&api), der(&api_cert));
+    assert_eq!(api.ssl().selected_alpn_protocol(), Some(&b"http/1.1"[..]));
+
+    let other = connect(addr, "other.example.com").unwrap();
+    assert_eq!(served_cert(&other), der(&cert));
+    assert_eq!(other.ssl().selected_alpn_protocol(), Some(&b"http/1.1"[..]));
+}
+
+#[test]
+fn hosts_refuse_older_protocol_versions() {
+    let (cert, key) = test_helpers::self_signed_cert();
+    let (grpc_cert, grpc_key) = test_helpers::self_signed_cert();
+    let tcp = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = tcp.local_addr().unwrap();
+
+    let mut listener = TlsListener::build()
+        .tcp(tcp)
+        .cert_pem(String::from_utf8(cert).unwrap())
+        .key_pem(String::from_utf8(key).unwrap())
+        .tls_profile(TlsProfile::MozillaIntermediateV5)
+        .add_host_config("grpc.example.com", host(grpc_cert, grpc_key, "h2"))
+        .finish()
+        .unwrap();
+    task::block_on(listener.bind(tide::new())).unwrap();
+    task::spawn(async move { listener.accept().await });
+
+    let mut connector = SslConnector::builder(SslMethod::tls()).unwrap();
+    connector.set_verify(SslVerifyMode::NONE);
+    connector
+        .set_max_proto_version(Some(SslVersion::TLS1_2))
+        .unwrap();
+    let connector = connector.build();
+
+    let tcp = TcpStream::connect(addr).unwrap();
+    assert!(connector.connect("grpc.example.com", tcp).is_err());
+    let tcp = TcpStream::connect(addr).unwrap();
+    let other = connector.connect("other.example.com", tcp).unwrap();
+    assert_eq!(other.ssl().version_str(), "TLSv1.2");
+}
+
+/// Makes a request as a client presenting `client`, if any, over
+/// TLS 1.2 so that a refused client certificate fails the handshake.
+fn request_as(
+    addr: SocketAddr,
+    hostname: &str,
+    client: Option<(&X509, &PKey<Private>)>,
+) -> Result<String, String> {
+    let mut connector = SslConnector::builder(SslMethod::tls()).unwrap();
+    connector.set_verify(SslVerifyMode::NONE);
+    connector
+        .set_max_proto_version(Some(SslVersion::TLS1_2))
+        .unwrap();
+    if let Some((cert, key)) = client {
+        connector.set_certificate(cert).unwrap();
+        connector.set_private_key(key).unwrap();
+    }
+    let tcp = TcpStream::connect(addr).unwrap();
+    let mut stream = connector
+        .build()
+        .connect(hostname, tcp)
+        .map_err(|error| error.to_string())?;
+    let mut response = String::new();
+    stream
+        .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .and_then(|_| stream.read_to_string(&mut response))
+        .map_err(|error| error.to_string())?;
+    Ok(response)
+}
+
+#[test]
+fn hosts_keep_client_verification_and_context_hook() {
+    let (cert, key) = test_helpers::self_signed_cert();
+    let (grpc_cert, grpc_key) = test_helpers::self_signed_cert();
+    let client_key = test_helpers::generate_key();
+    let client = test_helpers::issue_cert(&[("CN", "client")], 1, &client_key, None, 0..1);
+    let mut profile = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls()).unwrap();
+    profile.cert_store_mut().add_cert(client.clone()).unwrap();
+    profile.set_verify(SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT);
+
+    let hooked = Arc::new(Mutex::new(Vec::new()));
+    let seen = hooked.clone();
+    let tcp = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = tcp.local_addr().unwrap();
+    let mut listener = TlsListener::build()
+        .tcp(tcp)
+        .cert_pem(String::from_utf8(cert).unwrap())
+        .key_pem(String::from_utf8(key).unwrap())
+        .tls_profile(TlsProfile::Custom(profile))
+        .ssl_context_hook(move |context| {
+            let cert = context.certificate().unwrap().to_der().unwrap();
+            seen.lock().unwrap().push(cert);
+        })
+        .add_host_config(
+            "grpc.example.com",
+            HostConfig::build()
+                .cert_pem(String::from_utf8(grpc_cert.clone()).unwrap())
+                .key_pem(String::from_utf8(grpc_key).unwrap())
+                .finish()
+                .unwrap(),
+        )
+        .finish()
+        .unwrap();
+    task::block_on(listener.bind(tide::new())).unwrap();
+    task::spawn(async move { listener.accept().await });
+    assert!(hooked.lock().unwrap().contains(&der(&grpc_cert)));
+
+    let client = Some((&client, &client_key));
+    assert!(request_as(addr, "localhost", client).is_ok());
+    assert!(request_as(addr, "localhost", None).is_err());
+    assert!(request_as(addr, "grpc.example.com", None).is_err());
+    // the custom profile's trusted CAs stay with the listener's context
+    assert!(request_as(addr, "grpc.example.com", client).is_err());
+}
+
+#[test]
+fn host_config_needs_key_material() {
+    let error = HostConfig::build()
+        .cert("./tls/localhost-4433.cert")
+        .key_pem("")
+        .finish()
+        .unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+}
@@ -0,0 +1,146 @@
+use async_std::net::TcpStream;
+use async_std::task;
+use bytes::Bytes;
+use h2::client::SendRequest;
+use openssl::ssl::{SslConnector, SslMethod, SslVerifyMode};
+use std::net::SocketAddr;
+use std::pin::Pin;
+use tide::listener::Listener;
+use tide::Response;
+use tide_openssl::{test_helpers, TlsListener};
+use tokio_util::compat::FuturesAsyncReadCompatExt;
+
+fn spawn_server() -> SocketAddr {
+    let (cert, key) = test_helpers::self_signed_cert();
+    let mut app = tide::new();
+    app.at("/len").post(|mut req: tide::Request<()>| async move {
+        Ok(req.body_bytes().await?.len().to_string())
+    });
+    app.at("/large")
+        .get(|_| async { Ok(tide::Body::from(vec![b'x'; 1024 * 1024])) });
+    app.at("/headers").get(|_| async {
+        let mut res = Response::new(200);
+        res.insert_header("connection", "x-hop");
+        res.insert_header("x-hop", "1");
+        res.insert_header("keep-alive", "timeout=5");
+        res.insert_header("upgrade", "websocket");
+        res.insert_header("x-kept", "1");
+        Ok(res)
+    });
+
+    let tcp = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = tcp.local_addr().unwrap();
+    let mut listener = TlsListener::build()
+        .tcp(tcp)
+        .cert_pem(String::from_utf8(cert).unwrap())
+        .key_pem(String::from_utf8(key).unwrap())
+        .http2_max_request_body_size(16)
+        .finish()
+        .unwrap();
+    task::block_on(listener.bind(app)).unwrap();
+    task::spawn(async move { listener.accept().await });
+    addr
+}
+
+async fn connect(addr: SocketAddr) -> SendRequest<Bytes> {
+    let mut connector = SslConnector::builder(SslMethod::tls()).unwrap();
+    connector.set_verify(SslVerifyMode::NONE);
+    connector.set_alpn_protos(b"\x02h2").unwrap();
+    let ssl = connector
+        .build()
+        .configure()
+        .unwrap()
+        .into_ssl("localhost")
+        .unwrap();
+    let tcp = TcpStream::connect(addr).await.unwrap();
+    let mut stream = async_std_openssl::SslStream::new(ssl, tcp).unwrap();
+    Pin::new(&mut stream).connect().await.unwrap();
+
+    let (client, connection) = h2::client::handshake(stream.compat()).await.unwrap();
+    task::spawn(async move {
+        let _ = connection.await;
+    });
+    client
+}
+
+/// Sends a request, with `body` unless it is `None`, and reads the
+/// whole response.
+async fn send(
+    client: &mut SendRequest<Bytes>,
+    req: http::Request<()>,
+    body: Option<&[u8]>,
+) -> (http::response::Parts, Vec<u8>) {
+    let mut client = client.clone().ready().await.unwrap();
+    let (response, mut stream) = client.send_request(req, body.is_none()).unwrap();
+    if let Some(body) = body {
+        stream
+            .send_data(Bytes::copy_from_slice(body), true)
+            .unwrap();
+    }
+    let (parts, mut recv) = response.await.unwrap().into_parts();
+    let mut body = Vec::new();
+    while let Some(chunk) = recv.data().await {
+        let chunk = chunk.unwrap();
+        let _ = recv.flow_control().release_capacity(chunk.len());
+        body.extend_from_slice(&chunk);
+    }
+    (parts, body)
+}
+
+fn post(content_length: Option<usize>) -> http::Request<()> {
+    let mut req = http::Request::post("https://localhost/len");
+    if let Some(len) = content_length {
+        req = req.header("content-length", len);
+    }
+    req.body(()).unwrap()
+}
+
+#[test]
+fn request_bodies_are_limited() {
+    let addr = spawn_server();
+    task::block_on(async {
+        let mut client = connect(addr).await;
+
+        let (parts, body) = send(&mut client, post(Some(10)), Some(&[0; 10])).await;
+        assert_eq!(parts.status, 200);
+        assert_eq!(body, b"10");
+
+        // refused on the declared length, before any data is sent
+        let (parts, _) = send(&mut client, post(Some(1_000_000)), None).await;
+        assert_eq!(parts.status, 413);
+
+        let (parts, _) = send(&mut client, post(None), Some(&[0; 20])).await;
+        assert_eq!(parts.status, 413);
+    });
+}
+
+#[test]
+fn responses_larger_than_the_window_are_streamed() {
+    let addr = spawn_server();
+    task::block_on(async {
+        let mut client = connect(addr).await;
+        let req = http::Request::get("https://localhost/large")
+            .body(())
+            .unwrap();
+        let (parts, body) = send(&mut client, req, None).await;
+        assert_eq!(parts.status, 200);
+        assert_eq!(body.len(), 1024 * 1024);
+    });
+}
+
+#[test]
+fn connection_specific_headers_are_removed() {
+    let addr = spawn_server();
+    task::block_on(async {
+        let mut client = connect(addr).await;
+        let req = http::Request::get("https://localhost/headers")
+            .body(())
+            .unwrap();
+        let (parts, _) = send(&mut client, req, None).await;
+        assert_eq!(parts.status, 200);
+        for name in ["connection", "x-hop", "keep-alive", "upgrade"] {
+            assert!(!parts.headers.contains_key(name), "{} was sent", name);
+        }
+        assert_eq!(parts.headers["x-kept"], "1");
+    });
+}
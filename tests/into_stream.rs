@@ -0,0 +1,100 @@
+use async_std::prelude::*;
+use async_std::task;
+use std::io::{ErrorKind, Read, Write};
+use tide_openssl::{test_helpers, TlsListener};
+
+#[test]
+fn streamed_connections_have_completed_handshakes() {
+    let (cert, key) = test_helpers::self_signed_cert();
+    let tcp = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = tcp.local_addr().unwrap();
+    let listener = TlsListener::<()>::build()
+        .tcp(tcp)
+        .cert_pem(String::from_utf8(cert.clone()).unwrap())
+        .key_pem(String::from_utf8(key).unwrap())
+        .finish()
+        .unwrap();
+    let mut connections = Box::pin(task::block_on(listener.into_stream()).unwrap());
+
+    task::spawn(async move {
+        while let Some(connection) = connections.next().await {
+            let (mut stream, _) = connection.unwrap();
+            let mut buf = [0; 4];
+            stream.read_exact(&mut buf).await.unwrap();
+            stream.write_all(&buf).await.unwrap();
+            stream.flush().await.unwrap();
+        }
+    });
+
+    let connector = test_helpers::make_test_connector(&cert);
+    let tcp = std::net::TcpStream::connect(addr).unwrap();
+    let mut stream = connector.connect("localhost", tcp).unwrap();
+    stream.write_all(b"ping").unwrap();
+    let mut echoed = [0; 4];
+    stream.read_exact(&mut echoed).unwrap();
+    assert_eq!(&echoed, b"ping");
+}
+
+#[test]
+fn client_hello_inspection_is_unsupported() {
+    let (cert, key) = test_helpers::self_signed_cert();
+    let listener = TlsListener::<()>::build()
+        .addrs("127.0.0.1:0")
+        .cert_pem(String::from_utf8(cert).unwrap())
+        .key_pem(String::from_utf8(key).unwrap())
+        .tls_fingerprint_allowlist(vec![String::from("0123456789abcdef0123456789abcdef")])
+        .finish()
+        .unwrap();
+    let error = task::block_on(listener.into_stream()).err().unwrap();
+    assert_eq!(error.kind(), ErrorKind::Unsupported);
+}
+
+#[test]
+fn idle_clients_do_not_hold_up_other_handshakes() {
+    let (cert, key) = test_helpers::self_signed_cert();
+    let tcp = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = tcp.local_addr().unwrap();
+    let listener = TlsListener::<()>::build()
+        .tcp(tcp)
+        .cert_pem(String::from_utf8(cert.clone()).unwrap())
+        .key_pem(String::from_utf8(key).unwrap())
+        .finish()
+        .unwrap();
+    let mut connections = Box::pin(task::block_on(listener.into_stream()).unwrap());
+
+    task::spawn(async move {
+        while let Some(connection) = connections.next().await {
+            let (mut stream, _) = connection.unwrap();
+            stream.write_all(b"pong").await.unwrap();
+            stream.flush().await.unwrap();
+        }
+    });
+
+    // more idle connections than there are handshake slots, which
+    // only time out of the handshake
+    let idle: Vec<_> = (0..64)
+        .map(|_| std::net::TcpStream::connect(addr).unwrap())
+        .collect();
+
+    let connector = test_helpers::make_test_connector(&cert);
+    let tcp = std::net::TcpStream::connect(addr).unwrap();
+    let mut stream = connector.connect("localhost", tcp).unwrap();
+    let mut pong = [0; 4];
+    stream.read_exact(&mut pong).unwrap();
+    assert_eq!(&pong, b"pong");
+    drop(idle);
+}
+
+#[test]
+fn compression_assertion_is_unsupported() {
+    let (cert, key) = test_helpers::self_signed_cert();
+    let listener = TlsListener::<()>::build()
+        .addrs("127.0.0.1:0")
+        .cert_pem(String::from_utf8(cert).unwrap())
+        .key_pem(String::from_utf8(key).unwrap())
+        .assert_no_compression(true)
+        .finish()
+        .unwrap();
+    let error = task::block_on(listener.into_stream()).err().unwrap();
+    assert_eq!(error.kind(), ErrorKind::Unsupported);
+}
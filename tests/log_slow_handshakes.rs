@@ -0,0 +1,92 @@
+use async_std::task;
+use std::fmt::Debug;
+use std::sync::Mutex;
+use std::time::Duration;
+use tide::listener::Listener;
+use tide_openssl::{test_helpers, TlsListener};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+static EVENTS: Mutex<Vec<Vec<(&'static str, String)>>> = Mutex::new(Vec::new());
+
+/// Keeps the fields of every event.
+struct Recorder;
+
+struct Fields(Vec<(&'static str, String)>);
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.0.push((field.name(), format!("{:?}", value)));
+    }
+}
+
+impl Subscriber for Recorder {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, _: &Attributes<'_>) -> Id {
+        Id::from_u64(1)
+    }
+
+    fn record(&self, _: &Id, _: &Record<'_>) {}
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = Fields(Vec::new());
+        event.record(&mut fields);
+        EVENTS.lock().unwrap().push(fields.0);
+    }
+
+    fn enter(&self, _: &Id) {}
+
+    fn exit(&self, _: &Id) {}
+}
+
+fn slow_handshake_warnings(threshold: Duration) -> Vec<Vec<(&'static str, String)>> {
+    let (cert, key) = test_helpers::self_signed_cert();
+    let tcp = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = tcp.local_addr().unwrap();
+    let mut listener = TlsListener::build()
+        .tcp(tcp)
+        .cert_pem(String::from_utf8(cert.clone()).unwrap())
+        .key_pem(String::from_utf8(key).unwrap())
+        .log_slow_handshakes(threshold)
+        .finish()
+        .unwrap();
+    task::block_on(listener.bind(tide::new())).unwrap();
+    task::spawn(async move { listener.accept().await });
+
+    EVENTS.lock().unwrap().clear();
+    let connector = test_helpers::make_test_connector(&cert);
+    let tcp = std::net::TcpStream::connect(addr).unwrap();
+    connector.connect("localhost", tcp).unwrap();
+    std::thread::sleep(Duration::from_millis(100));
+
+    EVENTS
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|fields| fields.contains(&("message", "slow tls handshake".into())))
+        .cloned()
+        .collect()
+}
+
+#[test]
+fn slow_handshakes_are_logged() {
+    tracing::subscriber::set_global_default(Recorder).unwrap();
+
+    let warnings = slow_handshake_warnings(Duration::ZERO);
+    assert_eq!(warnings.len(), 1);
+    let fields: Vec<_> = warnings[0].iter().map(|(name, _)| *name).collect();
+    assert!(
+        fields.contains(&"peer_ip")
+            && fields.contains(&"cipher")
+            && fields.contains(&"tls_version")
+            && fields.contains(&"duration")
+    );
+
+    assert!(slow_handshake_warnings(Duration::from_secs(60)).is_empty());
+}
@@ -0,0 +1,36 @@
+use std::io::ErrorKind;
+use tide_openssl::typestate::{No, Yes};
+use tide_openssl::{test_helpers, TlsListener, TlsListenerBuilder};
+
+/// A builder serving a leaf certificate followed by `intermediates`
+/// more certificates.
+fn chain_of(intermediates: usize) -> TlsListenerBuilder<(), Yes, Yes, No> {
+    let (mut cert, key) = test_helpers::self_signed_cert();
+    let (intermediate, _) = test_helpers::self_signed_cert();
+    for _ in 0..intermediates {
+        cert.extend(&intermediate);
+    }
+    TlsListener::build()
+        .cert_pem(String::from_utf8(cert).unwrap())
+        .key_pem(String::from_utf8(key).unwrap())
+}
+
+#[test]
+fn chains_up_to_five_intermediates_are_served_by_default() {
+    assert!(chain_of(5).build_acceptor().is_ok());
+    let error = chain_of(6).build_acceptor().err().unwrap();
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+}
+
+#[test]
+fn depth_is_configurable() {
+    assert!(chain_of(6)
+        .max_cert_chain_depth_validate(6)
+        .build_acceptor()
+        .is_ok());
+    assert!(chain_of(1)
+        .max_cert_chain_depth_validate(0)
+        .addrs("localhost:4433")
+        .finish()
+        .is_err());
+}
@@ -0,0 +1,48 @@
+use async_std::task;
+use std::io::{Read, Write};
+use tide::listener::Listener;
+use tide_openssl::{test_helpers, TlsListener};
+
+#[test]
+fn connection_closes_after_the_limit() {
+    let (cert, key) = test_helpers::self_signed_cert();
+    let tcp = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = tcp.local_addr().unwrap();
+
+    let mut app = tide::new();
+    app.at("/").get(|_| async { Ok("hello") });
+    let mut listener = TlsListener::build()
+        .tcp(tcp)
+        .cert_pem(String::from_utf8(cert.clone()).unwrap())
+        .key_pem(String::from_utf8(key).unwrap())
+        .max_requests_per_connection(2)
+        .finish()
+        .unwrap();
+    task::block_on(listener.bind(app)).unwrap();
+    task::spawn(async move { listener.accept().await });
+
+    let connector = test_helpers::make_test_connector(&cert);
+    let tcp = std::net::TcpStream::connect(addr).unwrap();
+    let mut stream = connector.connect("localhost", tcp).unwrap();
+    let mut get = || {
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .unwrap();
+        let mut response = Vec::new();
+        let mut buf = [0; 1024];
+        while !response.ends_with(b"hello") {
+            match stream.read(&mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => response.extend_from_slice(&buf[..n]),
+            }
+        }
+        String::from_utf8(response).unwrap().to_lowercase()
+    };
+
+    let first = get();
+    assert!(first.ends_with("hello") && !first.contains("connection: close"));
+    let second = get();
+    assert!(second.ends_with("hello") && second.contains("connection: close"));
+    let closed = stream.read(&mut [0; 1024]);
+    assert!(matches!(closed, Ok(0)), "{:?}", closed);
+}
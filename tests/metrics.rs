@@ -0,0 +1,102 @@
+use async_std::task;
+use metrics::{
+    Counter, Gauge, Histogram, HistogramFn, Key, KeyName, Metadata, Recorder, SharedString, Unit,
+};
+use std::io::{Read, Write};
+use std::sync::{Arc, Mutex};
+use tide::listener::Listener;
+use tide_openssl::{test_helpers, TlsListener};
+
+type Samples = Arc<Mutex<Vec<(String, Vec<(String, String)>)>>>;
+
+struct Histograms(Samples);
+
+struct Sample {
+    key: Key,
+    samples: Samples,
+}
+
+impl HistogramFn for Sample {
+    fn record(&self, _: f64) {
+        let labels = self
+            .key
+            .labels()
+            .map(|label| (label.key().to_string(), label.value().to_string()))
+            .collect();
+        let sample = (self.key.name().to_string(), labels);
+        self.samples.lock().unwrap().push(sample);
+    }
+}
+
+impl Recorder for Histograms {
+    fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+    fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+    fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+    fn register_counter(&self, _: &Key, _: &Metadata<'_>) -> Counter {
+        Counter::noop()
+    }
+
+    fn register_gauge(&self, _: &Key, _: &Metadata<'_>) -> Gauge {
+        Gauge::noop()
+    }
+
+    fn register_histogram(&self, key: &Key, _: &Metadata<'_>) -> Histogram {
+        Histogram::from_arc(Arc::new(Sample {
+            key: key.clone(),
+            samples: self.0.clone(),
+        }))
+    }
+}
+
+#[test]
+fn handshakes_and_requests_are_recorded_in_histograms() {
+    let samples = Samples::default();
+    metrics::set_global_recorder(Histograms(samples.clone())).unwrap();
+
+    let (cert, key) = test_helpers::self_signed_cert();
+    let tcp = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = tcp.local_addr().unwrap();
+
+    let mut app = tide::new();
+    app.at("/").get(|_| async { Ok("hello") });
+    let mut listener = TlsListener::build()
+        .tcp(tcp)
+        .cert_pem(String::from_utf8(cert.clone()).unwrap())
+        .key_pem(String::from_utf8(key).unwrap())
+        .finish()
+        .unwrap();
+    task::block_on(listener.bind(app)).unwrap();
+    task::spawn(async move { listener.accept().await });
+
+    let connector = test_helpers::make_test_connector(&cert);
+    let tcp = std::net::TcpStream::connect(addr).unwrap();
+    let mut stream = connector.connect("localhost", tcp).unwrap();
+    stream
+        .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .unwrap();
+    let mut response = String::new();
+    let _ = stream.read_to_string(&mut response);
+
+    let labels = |alpn_protocol: &str| {
+        vec![
+            (String::from("peer_addr"), String::from("127.0.0.0/24")),
+            (String::from("alpn_protocol"), String::from(alpn_protocol)),
+        ]
+    };
+    assert_eq!(
+        *samples.lock().unwrap(),
+        [
+            (
+                String::from("tide_openssl.handshake_duration_seconds"),
+                labels("none")
+            ),
+            (
+                String::from("tide_openssl.request_duration_seconds"),
+                labels("http/1.1")
+            ),
+        ]
+    );
+}
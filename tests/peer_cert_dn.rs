@@ -0,0 +1,103 @@
+use async_std::task;
+use openssl::pkey::{PKey, Private};
+use openssl::ssl::{SslAcceptor, SslConnector, SslMethod, SslVerifyMode};
+use openssl::x509::X509;
+use std::io::{Read, Write};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tide::listener::Listener;
+use tide_openssl::{test_helpers, AuditEntry, ConnectionAuditWriter, TlsListener};
+
+#[derive(Default)]
+struct Entries(Mutex<Vec<AuditEntry>>);
+
+#[tide::utils::async_trait]
+impl ConnectionAuditWriter for Entries {
+    async fn write(&self, entry: AuditEntry) -> std::io::Result<()> {
+        self.0.lock().unwrap().push(entry);
+        Ok(())
+    }
+}
+
+fn self_signed(organization: &str) -> (X509, PKey<Private>) {
+    let key = test_helpers::generate_key();
+    let subject = [("O", organization), ("CN", "localhost")];
+    (test_helpers::issue_cert(&subject, 1, &key, None, 0..1), key)
+}
+
+fn spawn_server(audit: Arc<Entries>) -> SocketAddr {
+    let (cert, key) = self_signed("Server");
+    let mut acceptor = SslAcceptor::mozilla_modern_v5(SslMethod::tls()).unwrap();
+    acceptor.set_certificate(&cert).unwrap();
+    acceptor.set_private_key(&key).unwrap();
+    // accept any client certificate, leaving the check to the dn filter
+    acceptor.set_verify_callback(
+        SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT,
+        |_, _| true,
+    );
+    let acceptor = acceptor.build();
+
+    let mut app = tide::new();
+    app.at("/").get(|_| async { Ok("hello") });
+    let tcp = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = tcp.local_addr().unwrap();
+    let mut listener = TlsListener::build()
+        .tcp(tcp)
+        .async_configure(move || {
+            let acceptor = acceptor.clone();
+            Box::pin(async move { Ok(acceptor) })
+        })
+        .peer_cert_dn_required(|dn| dn == "/O=Acme Corp/CN=localhost")
+        .connection_audit_log(audit)
+        .finish()
+        .unwrap();
+    task::block_on(listener.bind(app)).unwrap();
+    task::spawn(async move { listener.accept().await });
+    addr
+}
+
+fn get_as(addr: SocketAddr, organization: &str) -> String {
+    let (cert, key) = self_signed(organization);
+    let mut connector = SslConnector::builder(SslMethod::tls()).unwrap();
+    connector.set_verify(SslVerifyMode::NONE);
+    connector.set_certificate(&cert).unwrap();
+    connector.set_private_key(&key).unwrap();
+    let connector = connector.build();
+
+    let tcp = std::net::TcpStream::connect(addr).unwrap();
+    let mut stream = connector.connect("localhost", tcp).unwrap();
+    let mut response = String::new();
+    let _ = stream
+        .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .and_then(|_| stream.read_to_string(&mut response));
+    response
+}
+
+#[test]
+fn matching_peer_dn_is_served() {
+    let addr = spawn_server(Arc::default());
+    assert!(get_as(addr, "Acme Corp").ends_with("\r\n\r\nhello"));
+}
+
+#[test]
+fn other_peer_dn_is_closed() {
+    let addr = spawn_server(Arc::default());
+    assert_eq!(get_as(addr, "Evil Corp"), "");
+}
+
+#[test]
+fn audit_log_has_the_dn_the_filter_sees() {
+    let audit = Arc::new(Entries::default());
+    let addr = spawn_server(audit.clone());
+    get_as(addr, "Acme Corp");
+    for _ in 0..100 {
+        if let Some(entry) = audit.0.lock().unwrap().first() {
+            let dn = entry.peer_cert_dn.as_deref();
+            assert_eq!(dn, Some("/O=Acme Corp/CN=localhost"));
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    panic!("no audit entry was written");
+}
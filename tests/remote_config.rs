@@ -0,0 +1,215 @@
+use async_std::task;
+use openssl::hash::MessageDigest;
+use openssl::x509::X509;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener};
+use std::time::Duration;
+use tide::listener::Listener;
+use tide_openssl::{test_helpers, TlsListener};
+
+/// Answers every request to a server bound to `addr` with `response`,
+/// returning the server's address.
+fn serve(addr: &str, response: String) -> SocketAddr {
+    let server = TcpListener::bind(addr).unwrap();
+    let addr = server.local_addr().unwrap();
+    std::thread::spawn(move || {
+        for stream in server.incoming() {
+            let mut stream = stream.unwrap();
+            let mut request = [0; 4096];
+            let _ = stream.read(&mut request).unwrap();
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+    addr
+}
+
+fn json_response(json: String) -> String {
+    format!(
+        "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+        json.len(),
+        json
+    )
+}
+
+/// Answers every request with `json`, returning the url to fetch it
+/// from.
+fn serve_json(json: String) -> String {
+    let addr = serve("127.0.0.1:0", json_response(json));
+    format!("http://{}/tls.json", addr)
+}
+
+/// A document with a fresh certificate and key, and the fingerprint
+/// of the certificate.
+fn key_material_document() -> (String, Vec<u8>) {
+    let (cert, key) = test_helpers::self_signed_cert();
+    let fingerprint = X509::from_pem(&cert)
+        .unwrap()
+        .digest(MessageDigest::sha256())
+        .unwrap()
+        .to_vec();
+    let json = tide::prelude::json!({
+        "cert_pem": String::from_utf8(cert).unwrap(),
+        "key_pem": String::from_utf8(key).unwrap(),
+    });
+    (json.to_string(), fingerprint)
+}
+
+/// Waits for the listener to serve the certificate with `fingerprint`.
+fn serves<State>(listener: &TlsListener<State>, fingerprint: &[u8]) -> bool {
+    for _ in 0..100 {
+        if served_fingerprint(listener) == fingerprint {
+            return true;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    false
+}
+
+fn served_fingerprint<State>(listener: &TlsListener<State>) -> Vec<u8> {
+    let acceptor = listener.clone_acceptor().unwrap();
+    let cert = acceptor.context().certificate().unwrap();
+    cert.digest(MessageDigest::sha256()).unwrap().to_vec()
+}
+
+#[test]
+fn remote_key_material_is_applied() {
+    let (cert, key) = test_helpers::self_signed_cert();
+    let (remote_cert, remote_key) = test_helpers::self_signed_cert();
+    let expected = X509::from_pem(&remote_cert)
+        .unwrap()
+        .digest(MessageDigest::sha256())
+        .unwrap()
+        .to_vec();
+    let url = serve_json(
+        tide::prelude::json!({
+            "cert_pem": String::from_utf8(remote_cert).unwrap(),
+            "key_pem": String::from_utf8(remote_key).unwrap(),
+            "min_protocol_version": "TLSv1.3",
+        })
+        .to_string(),
+    );
+
+    let mut listener = TlsListener::build()
+        .addrs("127.0.0.1:0")
+        .cert_pem(String::from_utf8(cert).unwrap())
+        .key_pem(String::from_utf8(key).unwrap())
+        .remote_configuration(url, Duration::from_millis(50))
+        .finish()
+        .unwrap();
+    assert_ne!(served_fingerprint(&listener), expected);
+    task::block_on(listener.bind(tide::new())).unwrap();
+    assert!(
+        serves(&listener, &expected),
+        "remote configuration was not applied"
+    );
+}
+
+#[test]
+fn invalid_remote_configuration_is_ignored() {
+    let (cert, key) = test_helpers::self_signed_cert();
+    let (remote_cert, remote_key) = test_helpers::self_signed_cert();
+    let url = serve_json(
+        tide::prelude::json!({
+            "cert_pem": String::from_utf8(remote_cert).unwrap(),
+            "key_pem": String::from_utf8(remote_key).unwrap(),
+            "max_protocol_version": "SSLv3",
+        })
+        .to_string(),
+    );
+
+    let mut listener = TlsListener::build()
+        .addrs("127.0.0.1:0")
+        .cert_pem(String::from_utf8(cert).unwrap())
+        .key_pem(String::from_utf8(key).unwrap())
+        .remote_configuration(url, Duration::from_millis(50))
+        .finish()
+        .unwrap();
+    let before = served_fingerprint(&listener);
+    task::block_on(listener.bind(tide::new())).unwrap();
+
+    std::thread::sleep(Duration::from_millis(300));
+    assert_eq!(served_fingerprint(&listener), before);
+}
+
+#[test]
+fn remote_configuration_survives_other_rebuilds() {
+    let (cert, key) = test_helpers::self_signed_cert();
+    let (json, expected) = key_material_document();
+    let url = serve_json(json);
+
+    let mut listener = TlsListener::build()
+        .addrs("127.0.0.1:0")
+        .cert_pem(String::from_utf8(cert).unwrap())
+        .key_pem(String::from_utf8(key).unwrap())
+        .remote_configuration(url, Duration::from_millis(50))
+        .invalidation_check(|| true, Duration::from_millis(10))
+        .finish()
+        .unwrap();
+    task::block_on(listener.bind(tide::new())).unwrap();
+    assert!(serves(&listener, &expected));
+
+    for _ in 0..20 {
+        std::thread::sleep(Duration::from_millis(10));
+        assert_eq!(served_fingerprint(&listener), expected);
+    }
+}
+
+#[test]
+fn redirects_are_followed() {
+    let (cert, key) = test_helpers::self_signed_cert();
+    let (json, expected) = key_material_document();
+    let target = serve_json(json);
+    let redirect = serve(
+        "127.0.0.1:0",
+        format!(
+            "HTTP/1.1 302 Found\r\nlocation: {}\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+            target
+        ),
+    );
+
+    let mut listener = TlsListener::build()
+        .addrs("127.0.0.1:0")
+        .cert_pem(String::from_utf8(cert).unwrap())
+        .key_pem(String::from_utf8(key).unwrap())
+        .remote_configuration(
+            format!("http://{}/tls.json", redirect),
+            Duration::from_millis(50),
+        )
+        .finish()
+        .unwrap();
+    task::block_on(listener.bind(tide::new())).unwrap();
+    assert!(serves(&listener, &expected));
+}
+
+#[test]
+fn ipv6_hosts_are_fetched_from() {
+    let (cert, key) = test_helpers::self_signed_cert();
+    let (json, expected) = key_material_document();
+    let addr = serve("[::1]:0", json_response(json));
+
+    let mut listener = TlsListener::build()
+        .addrs("127.0.0.1:0")
+        .cert_pem(String::from_utf8(cert).unwrap())
+        .key_pem(String::from_utf8(key).unwrap())
+        .remote_configuration(
+            format!("http://[::1]:{}/tls.json", addr.port()),
+            Duration::from_millis(50),
+        )
+        .finish()
+        .unwrap();
+    task::block_on(listener.bind(tide::new())).unwrap();
+    assert!(serves(&listener, &expected));
+}
+
+#[test]
+fn plain_http_is_refused_beyond_loopback() {
+    let (cert, key) = test_helpers::self_signed_cert();
+    let error = TlsListener::<()>::build()
+        .addrs("127.0.0.1:0")
+        .cert_pem(String::from_utf8(cert).unwrap())
+        .key_pem(String::from_utf8(key).unwrap())
+        .remote_configuration("http://config.internal/tls.json", Duration::from_secs(30))
+        .finish()
+        .unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+}
@@ -0,0 +1,158 @@
+use async_std::task;
+use openssl::hash::MessageDigest;
+use openssl::pkey::PKey;
+use openssl::sign::Signer;
+use std::io::{Read, Write};
+use std::net::SocketAddr;
+use std::time::Duration;
+use tide::listener::Listener;
+use tide_openssl::{test_helpers, SigningDigest, TlsListener};
+
+const KEY: [u8; 32] = [7; 32];
+
+fn sign(body: &[u8]) -> String {
+    let key = PKey::hmac(&KEY).unwrap();
+    let mut signer = Signer::new(MessageDigest::sha384(), &key).unwrap();
+    signer.update(body).unwrap();
+    signer
+        .sign_to_vec()
+        .unwrap()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn post(addr: SocketAddr, cert: &[u8], body: &str, signature: Option<String>) -> String {
+    let connector = test_helpers::make_test_connector(cert);
+    let tcp = std::net::TcpStream::connect(addr).unwrap();
+    let mut stream = connector.connect("localhost", tcp).unwrap();
+    let signature = signature
+        .map(|signature| format!("X-Body-Signature: {}\r\n", signature))
+        .unwrap_or_default();
+    let request = format!(
+        "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n{}",
+        body.len(),
+        signature,
+        body
+    );
+    stream.write_all(request.as_bytes()).unwrap();
+    let mut response = String::new();
+    let _ = stream.read_to_string(&mut response);
+    response
+}
+
+#[test]
+fn only_signed_requests_reach_the_app() {
+    let (cert, key) = test_helpers::self_signed_cert();
+    let tcp = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = tcp.local_addr().unwrap();
+
+    let mut app = tide::new();
+    app.at("/")
+        .post(|mut req: tide::Request<()>| async move { req.body_string().await });
+    let mut listener = TlsListener::build()
+        .tcp(tcp)
+        .cert_pem(String::from_utf8(cert.clone()).unwrap())
+        .key_pem(String::from_utf8(key).unwrap())
+        .request_signing_key(&KEY)
+        .request_signing_header("X-Body-Signature")
+        .request_signing_digest(SigningDigest::Sha384)
+        .finish()
+        .unwrap();
+    task::block_on(listener.bind(app)).unwrap();
+    task::spawn(async move { listener.accept().await });
+
+    let signed = post(addr, &cert, "hello", Some(sign(b"hello")));
+    assert!(signed.starts_with("HTTP/1.1 200 OK"), "{}", signed);
+    assert!(signed.ends_with("hello"), "{}", signed);
+
+    let unsigned = post(addr, &cert, "hello", None);
+    assert!(
+        unsigned.starts_with("HTTP/1.1 401 Unauthorized"),
+        "{}",
+        unsigned
+    );
+
+    let tampered = post(addr, &cert, "hellO", Some(sign(b"hello")));
+    assert!(
+        tampered.starts_with("HTTP/1.1 401 Unauthorized"),
+        "{}",
+        tampered
+    );
+}
+
+#[test]
+fn oversized_bodies_are_refused_without_being_read() {
+    let (cert, key) = test_helpers::self_signed_cert();
+    let tcp = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = tcp.local_addr().unwrap();
+
+    let mut app = tide::new();
+    app.at("/")
+        .post(|mut req: tide::Request<()>| async move { req.body_string().await });
+    let mut listener = TlsListener::build()
+        .tcp(tcp)
+        .cert_pem(String::from_utf8(cert.clone()).unwrap())
+        .key_pem(String::from_utf8(key).unwrap())
+        .request_signing_key(&KEY)
+        .request_signing_header("X-Body-Signature")
+        .request_signing_digest(SigningDigest::Sha384)
+        .request_signing_max_body_size(16)
+        .finish()
+        .unwrap();
+    task::block_on(listener.bind(app)).unwrap();
+    task::spawn(async move { listener.accept().await });
+
+    // only the headers are sent, so reading the body would never finish
+    let headers_only = |signature: Option<String>| {
+        let connector = test_helpers::make_test_connector(&cert);
+        let tcp = std::net::TcpStream::connect(addr).unwrap();
+        tcp.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let mut stream = connector.connect("localhost", tcp).unwrap();
+        let signature = signature
+            .map(|signature| format!("X-Body-Signature: {}\r\n", signature))
+            .unwrap_or_default();
+        let request = format!(
+            "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: 1000000\r\n{}\r\n",
+            signature
+        );
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut response = [0; 64];
+        let read = stream.read(&mut response).unwrap();
+        String::from_utf8_lossy(&response[..read]).into_owned()
+    };
+
+    let unsigned = headers_only(None);
+    assert!(
+        unsigned.starts_with("HTTP/1.1 401 Unauthorized"),
+        "{}",
+        unsigned
+    );
+    let oversized = headers_only(Some(sign(b"hello")));
+    assert!(
+        oversized.starts_with("HTTP/1.1 413 Payload Too Large"),
+        "{}",
+        oversized
+    );
+
+    let chunked = {
+        let signature = sign(b"01234567890123456789");
+        let body = "14\r\n01234567890123456789\r\n0\r\n\r\n";
+        let connector = test_helpers::make_test_connector(&cert);
+        let tcp = std::net::TcpStream::connect(addr).unwrap();
+        let mut stream = connector.connect("localhost", tcp).unwrap();
+        let request = format!(
+            "POST / HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\nX-Body-Signature: {}\r\nConnection: close\r\n\r\n{}",
+            signature, body
+        );
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut response = String::new();
+        let _ = stream.read_to_string(&mut response);
+        response
+    };
+    assert!(
+        chunked.starts_with("HTTP/1.1 413 Payload Too Large"),
+        "{}",
+        chunked
+    );
+}
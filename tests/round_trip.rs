@@ -0,0 +1,34 @@
+use async_std::task;
+use std::io::{Read, Write};
+use tide_openssl::test_helpers;
+
+#[test]
+fn get_over_verified_tls() {
+    let (cert, key) = test_helpers::self_signed_cert();
+    let mut app = tide::new();
+    app.at("/").get(|_| async { Ok("hello") });
+    let addr = task::block_on(test_helpers::spawn_test_server(app, &cert, &key));
+
+    let connector = test_helpers::make_test_connector(&cert);
+    let tcp = std::net::TcpStream::connect(addr).unwrap();
+    let mut stream = connector.connect("localhost", tcp).unwrap();
+    stream
+        .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.ends_with("\r\n\r\nhello"));
+}
+
+#[test]
+fn untrusted_certificate_is_refused() {
+    let (cert, key) = test_helpers::self_signed_cert();
+    let (other_ca, _) = test_helpers::self_signed_cert();
+    let addr = task::block_on(test_helpers::spawn_test_server(tide::new(), &cert, &key));
+
+    let connector = test_helpers::make_test_connector(&other_ca);
+    let tcp = std::net::TcpStream::connect(addr).unwrap();
+    assert!(connector.connect("localhost", tcp).is_err());
+}
@@ -0,0 +1,48 @@
+use async_std::task;
+use openssl::pkey::{Id, PKey, Private};
+use openssl::rsa::Rsa;
+use openssl::ssl::{SslConnector, SslMethod, SslVerifyMode};
+use openssl::x509::X509;
+use tide::listener::Listener;
+use tide_openssl::{test_helpers, TlsListener};
+
+fn self_signed(key: &PKey<Private>) -> X509 {
+    test_helpers::issue_cert(&[("CN", "localhost")], 1, key, None, 0..1)
+}
+
+fn served_key_type(port: u16, sigalgs: &str) -> Id {
+    let mut connector = SslConnector::builder(SslMethod::tls()).unwrap();
+    connector.set_verify(SslVerifyMode::NONE);
+    connector.set_sigalgs_list(sigalgs).unwrap();
+    let connector = connector.build();
+
+    let tcp = std::net::TcpStream::connect(("127.0.0.1", port)).unwrap();
+    let stream = connector.connect("localhost", tcp).unwrap();
+    let cert = stream.ssl().peer_certificate().unwrap();
+    cert.public_key().unwrap().id()
+}
+
+#[test]
+fn rsa_and_ecdsa_certs_are_both_served() {
+    let rsa_key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+    let ec_key = test_helpers::generate_key();
+
+    let pem = |bytes: Vec<u8>| String::from_utf8(bytes).unwrap();
+    let tcp = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = tcp.local_addr().unwrap().port();
+
+    let mut listener = TlsListener::build()
+        .tcp(tcp)
+        .cert_pem(pem(self_signed(&rsa_key).to_pem().unwrap()))
+        .key_pem(pem(rsa_key.private_key_to_pem_pkcs8().unwrap()))
+        .secondary_cert_der(self_signed(&ec_key).to_der().unwrap())
+        .secondary_key_der(ec_key.private_key_to_der().unwrap())
+        .finish()
+        .unwrap();
+
+    task::block_on(listener.bind(tide::new())).unwrap();
+    task::spawn(async move { listener.accept().await });
+
+    assert_eq!(served_key_type(port, "ECDSA+SHA256"), Id::EC);
+    assert_eq!(served_key_type(port, "RSA-PSS+SHA256"), Id::RSA);
+}
@@ -0,0 +1,62 @@
+use async_std::task;
+use openssl::ssl::{SslSession, SslSessionRef};
+use std::io::{Read, Write};
+use std::sync::{Arc, Mutex};
+use tide::listener::Listener;
+use tide_openssl::{test_helpers, TlsListener};
+
+#[test]
+fn fresh_and_resumed_sessions_are_reported() {
+    let (cert, key) = test_helpers::self_signed_cert();
+    let tcp = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = tcp.local_addr().unwrap();
+    let reuses = Arc::new(Mutex::new(Vec::new()));
+    let recorded = reuses.clone();
+
+    let mut app = tide::new();
+    app.at("/").get(|_| async { Ok("hello") });
+    let mut listener = TlsListener::build()
+        .tcp(tcp)
+        .cert_pem(String::from_utf8(cert.clone()).unwrap())
+        .key_pem(String::from_utf8(key).unwrap())
+        .session_cache_hit_callback(move |_, reused| recorded.lock().unwrap().push(reused))
+        .finish()
+        .unwrap();
+    task::block_on(listener.bind(app)).unwrap();
+    task::spawn(async move { listener.accept().await });
+
+    let connector = test_helpers::make_test_connector(&cert);
+    let get = |session: Option<&SslSessionRef>| {
+        let mut ssl = connector
+            .configure()
+            .unwrap()
+            .into_ssl("localhost")
+            .unwrap();
+        if let Some(session) = session {
+            // SAFETY: the session was negotiated with the same connector
+            unsafe { ssl.set_session(session).unwrap() };
+        }
+        let tcp = std::net::TcpStream::connect(addr).unwrap();
+        let mut stream = ssl.connect(tcp).unwrap();
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .unwrap();
+        // with TLS 1.3 the session ticket arrives after the handshake
+        let mut response = Vec::new();
+        while !response.ends_with(b"hello") {
+            let mut buf = [0; 1024];
+            let n = stream.read(&mut buf).unwrap();
+            response.extend_from_slice(&buf[..n]);
+        }
+        // a copy, since openssl marks the session of a connection that
+        // ends without a close_notify as not resumable
+        let session = stream.ssl().session().unwrap().to_der().unwrap();
+        SslSession::from_der(&session).unwrap()
+    };
+
+    let session = get(None);
+    assert_eq!(*reuses.lock().unwrap(), vec![false]);
+
+    get(Some(&session));
+    assert_eq!(*reuses.lock().unwrap(), vec![false, true]);
+}
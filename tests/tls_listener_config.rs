@@ -0,0 +1,70 @@
+use openssl::pkey::PKey;
+use openssl::x509::X509;
+use std::collections::HashSet;
+use tide_openssl::{test_helpers, TlsListener, TlsListenerConfig};
+
+fn paths(cert: &str) -> TlsListenerConfig {
+    TlsListenerConfig::Paths {
+        cert: cert.into(),
+        key: "./tls/localhost-4433.key".into(),
+        chain: None,
+    }
+}
+
+#[test]
+fn identical_configs_are_deduplicated() {
+    let (cert, key) = test_helpers::self_signed_cert();
+    let pem = TlsListenerConfig::Pem {
+        cert: String::from_utf8(cert).unwrap(),
+        key: String::from_utf8(key).unwrap(),
+    };
+
+    let configs: HashSet<_> = vec![
+        paths("./tls/localhost-4433.cert"),
+        pem.clone(),
+        paths("./tls/localhost-4433.cert"),
+        paths("./tls/localhost-4434.cert"),
+        pem,
+    ]
+    .into_iter()
+    .collect();
+    assert_eq!(configs.len(), 3);
+}
+
+#[test]
+fn der_material_is_served() {
+    let (cert, key) = test_helpers::self_signed_cert();
+    let config = TlsListenerConfig::Der {
+        cert: X509::from_pem(&cert).unwrap().to_der().unwrap(),
+        key: PKey::private_key_from_pem(&key)
+            .unwrap()
+            .private_key_to_der()
+            .unwrap(),
+    };
+    let acceptor = TlsListener::<()>::build()
+        .config(config)
+        .build_acceptor()
+        .unwrap();
+    assert!(acceptor.context().certificate().is_some());
+}
+
+#[test]
+fn prebuilt_acceptors_are_only_equal_to_themselves() {
+    let (cert, key) = test_helpers::self_signed_cert();
+    let build = || {
+        TlsListener::<()>::build()
+            .cert_pem(String::from_utf8(cert.clone()).unwrap())
+            .key_pem(String::from_utf8(key.clone()).unwrap())
+            .build_acceptor()
+            .unwrap()
+    };
+    let acceptor = TlsListenerConfig::PrebuiltAcceptor(build());
+    assert_eq!(acceptor, acceptor.clone());
+    assert_ne!(acceptor, TlsListenerConfig::PrebuiltAcceptor(build()));
+
+    let built = TlsListener::<()>::build()
+        .config(acceptor.clone())
+        .build_acceptor()
+        .unwrap();
+    assert_eq!(TlsListenerConfig::PrebuiltAcceptor(built), acceptor);
+}
@@ -0,0 +1,70 @@
+use async_std::task;
+use openssl::ssl::{SslAcceptor, SslMethod, SslStream, SslVersion};
+use openssl::x509::X509;
+use std::net::TcpStream;
+use tide::listener::Listener;
+use tide_openssl::{test_helpers, TlsListener, TlsListenerBuilder, TlsProfile};
+
+/// Serves a TLS 1.2 only custom profile, changed by `configure`, and
+/// connects to it.
+fn connect_to_custom_profile(
+    cert: &[u8],
+    key: &[u8],
+    configure: impl FnOnce(TlsListenerBuilder<()>) -> TlsListenerBuilder<()>,
+) -> SslStream<TcpStream> {
+    let tcp = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = tcp.local_addr().unwrap();
+
+    let mut profile = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls()).unwrap();
+    profile
+        .set_max_proto_version(Some(SslVersion::TLS1_2))
+        .unwrap();
+    let builder = TlsListener::build().tls_profile(TlsProfile::Custom(profile));
+    let mut listener = configure(builder)
+        .tcp(tcp)
+        .cert_pem(String::from_utf8(cert.to_vec()).unwrap())
+        .key_pem(String::from_utf8(key.to_vec()).unwrap())
+        .finish()
+        .unwrap();
+    task::block_on(listener.bind(tide::new())).unwrap();
+    task::spawn(async move { listener.accept().await });
+
+    let connector = test_helpers::make_test_connector(cert);
+    let tcp = TcpStream::connect(addr).unwrap();
+    connector.connect("localhost", tcp).unwrap()
+}
+
+#[test]
+fn custom_profile_is_built_upon() {
+    let (cert, key) = test_helpers::self_signed_cert();
+    let stream = connect_to_custom_profile(&cert, &key, |builder| builder);
+    assert_eq!(stream.ssl().version_str(), "TLSv1.2");
+
+    // the builder's own key material is still applied on top
+    let served = stream.ssl().peer_certificate().unwrap();
+    let expected = X509::from_pem(&cert).unwrap();
+    assert_eq!(served.to_der().unwrap(), expected.to_der().unwrap());
+}
+
+#[test]
+fn disabling_legacy_client_support_keeps_a_custom_profile() {
+    let (cert, key) = test_helpers::self_signed_cert();
+    let stream =
+        connect_to_custom_profile(&cert, &key, |builder| builder.legacy_client_support(false));
+    assert_eq!(stream.ssl().version_str(), "TLSv1.2");
+}
+
+#[test]
+fn custom_profile_is_refused_with_rebuilds() {
+    let (cert, key) = test_helpers::self_signed_cert();
+    let profile = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls()).unwrap();
+    let error = TlsListener::<()>::build()
+        .addrs("127.0.0.1:0")
+        .cert_pem(String::from_utf8(cert).unwrap())
+        .key_pem(String::from_utf8(key).unwrap())
+        .tls_profile(TlsProfile::Custom(profile))
+        .invalidation_check(|| false, std::time::Duration::from_secs(60))
+        .finish()
+        .unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+}
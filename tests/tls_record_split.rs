@@ -0,0 +1,39 @@
+use foreign_types::ForeignTypeRef;
+use openssl::ssl::{SslContextRef, SslOptions};
+use tide_openssl::{test_helpers, TlsListener};
+
+/// The options of a built context, which the openssl crate only reads
+/// back from a builder.
+fn context_options(context: &SslContextRef) -> SslOptions {
+    // SAFETY: the pointer is to a live SSL_CTX
+    let options = unsafe { openssl_sys::SSL_CTX_get_options(context.as_ptr()) };
+    SslOptions::from_bits_retain(options as _)
+}
+
+fn options(tls_record_split: Option<bool>) -> SslOptions {
+    let (cert, key) = test_helpers::self_signed_cert();
+    let mut builder = TlsListener::<()>::build()
+        .cert_pem(String::from_utf8(cert).unwrap())
+        .key_pem(String::from_utf8(key).unwrap())
+        .legacy_client_support(true);
+    if let Some(enabled) = tls_record_split {
+        builder = builder.enable_tls_record_split(enabled);
+    }
+    let acceptor = builder.build_acceptor().unwrap();
+    context_options(acceptor.context())
+}
+
+#[test]
+fn records_are_split_by_default() {
+    assert!(!options(None).contains(SslOptions::DONT_INSERT_EMPTY_FRAGMENTS));
+}
+
+#[test]
+fn records_are_split_when_enabled() {
+    assert!(!options(Some(true)).contains(SslOptions::DONT_INSERT_EMPTY_FRAGMENTS));
+}
+
+#[test]
+fn records_are_not_split_when_disabled() {
+    assert!(options(Some(false)).contains(SslOptions::DONT_INSERT_EMPTY_FRAGMENTS));
+}
@@ -0,0 +1,95 @@
+use async_std::task;
+use std::fmt::Debug;
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+use tide::listener::Listener;
+use tide_openssl::{test_helpers, TlsListener};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+static EVENTS: Mutex<Vec<Vec<(&'static str, String)>>> = Mutex::new(Vec::new());
+
+/// Keeps the fields of every event.
+struct Recorder;
+
+struct Fields(Vec<(&'static str, String)>);
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.0.push((field.name(), format!("{:?}", value)));
+    }
+}
+
+impl Subscriber for Recorder {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, _: &Attributes<'_>) -> Id {
+        Id::from_u64(1)
+    }
+
+    fn record(&self, _: &Id, _: &Record<'_>) {}
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = Fields(Vec::new());
+        event.record(&mut fields);
+        EVENTS.lock().unwrap().push(fields.0);
+    }
+
+    fn enter(&self, _: &Id) {}
+
+    fn exit(&self, _: &Id) {}
+}
+
+#[test]
+fn stats_are_logged_every_interval() {
+    tracing::subscriber::set_global_default(Recorder).unwrap();
+
+    let (cert, key) = test_helpers::self_signed_cert();
+    let tcp = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = tcp.local_addr().unwrap();
+    let mut listener = TlsListener::build()
+        .tcp(tcp)
+        .cert_pem(String::from_utf8(cert.clone()).unwrap())
+        .key_pem(String::from_utf8(key).unwrap())
+        .tls_stats_interval(Duration::from_millis(50))
+        .finish()
+        .unwrap();
+    task::block_on(listener.bind(tide::new())).unwrap();
+    task::spawn(async move { listener.accept().await });
+
+    let connector = test_helpers::make_test_connector(&cert);
+    let tcp = std::net::TcpStream::connect(addr).unwrap();
+    let _open = connector.connect("localhost", tcp).unwrap();
+    let mut plaintext = std::net::TcpStream::connect(addr).unwrap();
+    plaintext.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+    std::thread::sleep(Duration::from_millis(200));
+
+    let events = EVENTS.lock().unwrap();
+    let stats = events
+        .iter()
+        .rev()
+        .find(|fields| fields.contains(&("message", "tls stats".into())))
+        .expect("no stats logged");
+    for expected in [
+        ("accepted", "2"),
+        ("rejected", "0"),
+        ("active", "1"),
+        ("handshakes_succeeded", "1"),
+        ("handshakes_failed", "1"),
+        ("handshake_success_rate", "0.5"),
+        ("session_hit_rate", "0"),
+    ] {
+        assert!(
+            stats.contains(&(expected.0, expected.1.into())),
+            "{:?} in {:?}",
+            expected,
+            stats
+        );
+    }
+}
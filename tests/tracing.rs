@@ -0,0 +1,141 @@
+use async_std::task;
+use std::cell::RefCell;
+use std::fmt::Debug;
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+use tide::listener::Listener;
+use tide_openssl::{test_helpers, TlsListener};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+use tracing_core::span::Current;
+
+static SPANS: Mutex<Vec<&'static Metadata<'static>>> = Mutex::new(Vec::new());
+static RECORDED: Mutex<Vec<(&'static str, &'static str)>> = Mutex::new(Vec::new());
+static MESSAGES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+thread_local! {
+    static ENTERED: RefCell<Vec<Id>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Keeps span names, the fields recorded on spans after creation, and
+/// event messages.
+struct Recorder;
+
+struct Fields(Vec<(&'static str, String)>);
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.0.push((field.name(), format!("{:?}", value)));
+    }
+}
+
+fn metadata(span: &Id) -> &'static Metadata<'static> {
+    SPANS.lock().unwrap()[span.into_u64() as usize - 1]
+}
+
+impl Subscriber for Recorder {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let mut spans = SPANS.lock().unwrap();
+        spans.push(span.metadata());
+        Id::from_u64(spans.len() as u64)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        let name = metadata(span).name();
+        let mut fields = Fields(Vec::new());
+        values.record(&mut fields);
+        let mut recorded = RECORDED.lock().unwrap();
+        for (field, _) in fields.0 {
+            recorded.push((name, field));
+        }
+    }
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = Fields(Vec::new());
+        event.record(&mut fields);
+        for (field, value) in fields.0 {
+            if field == "message" {
+                MESSAGES.lock().unwrap().push(value);
+            }
+        }
+    }
+
+    fn enter(&self, span: &Id) {
+        ENTERED.with(|entered| entered.borrow_mut().push(span.clone()));
+    }
+
+    fn exit(&self, _: &Id) {
+        ENTERED.with(|entered| entered.borrow_mut().pop());
+    }
+
+    fn current_span(&self) -> Current {
+        match ENTERED.with(|entered| entered.borrow().last().cloned()) {
+            Some(span) => Current::new(span.clone(), metadata(&span)),
+            None => Current::none(),
+        }
+    }
+}
+
+#[test]
+fn connections_are_traced() {
+    tracing::subscriber::set_global_default(Recorder).unwrap();
+
+    let (cert, key) = test_helpers::self_signed_cert();
+    let tcp = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = tcp.local_addr().unwrap();
+    let mut listener = TlsListener::build()
+        .tcp(tcp)
+        .cert_pem(String::from_utf8(cert.clone()).unwrap())
+        .key_pem(String::from_utf8(key).unwrap())
+        .finish()
+        .unwrap();
+    task::block_on(listener.bind(tide::new())).unwrap();
+    task::spawn(async move { listener.accept().await });
+
+    let connector = test_helpers::make_test_connector(&cert);
+    let tcp = std::net::TcpStream::connect(addr).unwrap();
+    connector.connect("localhost", tcp).unwrap();
+
+    let mut plain = std::net::TcpStream::connect(addr).unwrap();
+    plain
+        .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+        .unwrap();
+
+    for _ in 0..50 {
+        if MESSAGES
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|message| message == "tls error")
+        {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+
+    let spans: Vec<_> = SPANS
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|span| span.name())
+        .collect();
+    assert!(spans.contains(&"tls_accept"), "{:?}", spans);
+    assert!(spans.contains(&"tls_connection"), "{:?}", spans);
+    let recorded = RECORDED.lock().unwrap();
+    assert!(recorded.contains(&("tls_connection", "protocol_version")));
+    assert!(recorded.contains(&("tls_connection", "cipher")));
+    let messages = MESSAGES.lock().unwrap();
+    assert!(
+        messages.iter().any(|message| message == "tls error"),
+        "{:?}",
+        messages
+    );
+}
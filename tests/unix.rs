@@ -0,0 +1,71 @@
+#![cfg(unix)]
+
+use async_std::task;
+use std::io::{ErrorKind, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::time::Duration;
+use tide::listener::Listener;
+use tide_openssl::{test_helpers, TlsListener};
+
+fn socket_path(name: &str) -> PathBuf {
+    let path =
+        std::env::temp_dir().join(format!("tide-openssl-{}-{}.sock", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+#[test]
+fn serves_tls_over_a_unix_socket() {
+    let path = socket_path("serve");
+    let (cert, key) = test_helpers::self_signed_cert();
+    let mut listener = TlsListener::build()
+        .unix_path(&path)
+        .cert_pem(String::from_utf8(cert.clone()).unwrap())
+        .key_pem(String::from_utf8(key).unwrap())
+        .finish()
+        .unwrap();
+
+    let mut app = tide::new();
+    app.at("/").get(|_| async { Ok("hello") });
+    task::block_on(listener.bind(app)).unwrap();
+
+    let info = listener.info();
+    assert_eq!(info[0].transport(), "unix");
+    assert!(info[0]
+        .connection()
+        .starts_with(&path.display().to_string()));
+    assert_eq!(listener.to_string(), path.display().to_string());
+    task::spawn(async move { listener.accept().await.unwrap() });
+
+    let stream = UnixStream::connect(&path).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    let connector = test_helpers::make_test_connector(&cert);
+    let mut stream = connector.connect("localhost", stream).unwrap();
+    stream
+        .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert!(response.ends_with("hello"), "{}", response);
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn unix_path_is_exclusive_with_addrs() {
+    let path = socket_path("exclusive");
+    let (cert, key) = test_helpers::self_signed_cert();
+    let error = TlsListener::<()>::build()
+        .addrs("127.0.0.1:0")
+        .unix_path(&path)
+        .cert_pem(String::from_utf8(cert).unwrap())
+        .key_pem(String::from_utf8(key).unwrap())
+        .finish()
+        .err()
+        .unwrap();
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+}